@@ -0,0 +1,48 @@
+//! `cargo bench --bench spawn`
+//! Compares `World::spawn_batch` against spawning entities one component at a time
+
+use std::time::{Duration, Instant};
+
+use light::ecs::World;
+
+
+#[derive(Clone, Copy)]
+struct Position(#[allow(dead_code)] [f32; 3]);
+
+#[derive(Clone, Copy)]
+struct Velocity(#[allow(dead_code)] [f32; 3]);
+
+#[derive(Clone, Copy)]
+struct Health(#[allow(dead_code)] u32);
+
+const ENTITIES: usize = 100_000;
+const RUNS: u32 = 10;
+
+fn bench(name: &str, spawn: impl Fn(&mut World)) -> Duration {
+    let mut total = Duration::ZERO;
+    for _ in 0..RUNS {
+        let mut world = World::new();
+        let start = Instant::now();
+        spawn(&mut world);
+        total += start.elapsed();
+        assert_eq!(ENTITIES, world.get_entities().len());
+    }
+    let average = total / RUNS;
+    println!("{:<12} {:>10.3?} for {} entities", name, average, ENTITIES);
+    average
+}
+
+fn main() {
+    let per_entity = bench("per entity", |world| {
+        for i in 0..ENTITIES {
+            world.spawn()
+                .insert(Position([i as f32; 3]))
+                .insert(Velocity([1.0; 3]))
+                .insert(Health(100));
+        }
+    });
+    let batch = bench("spawn_batch", |world| {
+        world.spawn_batch((0..ENTITIES).map(|i| (Position([i as f32; 3]), Velocity([1.0; 3]), Health(100))));
+    });
+    println!("spawn_batch is {:.1}x faster", per_entity.as_secs_f64() / batch.as_secs_f64());
+}
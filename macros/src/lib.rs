@@ -0,0 +1,146 @@
+use proc_macro::TokenStream;
+use proc_macro2::TokenStream as TokenStream2;
+use quote::quote;
+use proc_macro2::Span;
+use syn::{parse_macro_input, parse_quote, Data, DeriveInput, Fields, Ident, Index, Member};
+
+
+/// `#[derive(Reflect)]` for structs, every field should implement `Reflect`
+/// Tuple struct fields are named by their index: `"0"`, `"1"`...
+#[proc_macro_derive(Reflect)]
+pub fn derive_reflect(input: TokenStream) -> TokenStream {
+    let mut input = parse_macro_input!(input as DeriveInput);
+
+    let fields = match &input.data {
+        Data::Struct(data) => match &data.fields {
+            Fields::Named(fields) => fields.named.iter()
+                .map(|field| {
+                    let ident = field.ident.clone().unwrap();
+                    (ident.to_string(), Member::Named(ident), field.ty.clone())
+                })
+                .collect(),
+            Fields::Unnamed(fields) => fields.unnamed.iter()
+                .enumerate()
+                .map(|(index, field)| (index.to_string(), Member::Unnamed(Index::from(index)), field.ty.clone()))
+                .collect(),
+            Fields::Unit => Vec::new(),
+        },
+        _ => return syn::Error::new_spanned(&input.ident, "Reflect can only be derived for structs")
+            .to_compile_error()
+            .into(),
+    };
+
+    let type_params: Vec<_> = input.generics.type_params().map(|param| param.ident.clone()).collect();
+    let where_clause = input.generics.make_where_clause();
+    for param in type_params {
+        where_clause.predicates.push(parse_quote!(#param: ::light::ecs::reflect::Reflect));
+    }
+
+    let ident = &input.ident;
+    let (impl_generics, ty_generics, where_clause) = input.generics.split_for_impl();
+    let names: Vec<_> = fields.iter().map(|(name, _, _)| name).collect();
+    let members: Vec<_> = fields.iter().map(|(_, member, _)| member).collect();
+    let types: Vec<_> = fields.iter().map(|(_, _, ty)| ty).collect();
+
+    let expanded: TokenStream2 = quote! {
+        impl #impl_generics ::light::ecs::reflect::Reflect for #ident #ty_generics #where_clause {
+            fn type_info() -> ::light::ecs::reflect::TypeInfo where Self: Sized {
+                ::light::ecs::reflect::TypeInfo::new::<Self>(vec![
+                    #(::light::ecs::reflect::FieldInfo::new::<#types>(#names),)*
+                ])
+            }
+
+            fn field_names(&self) -> &'static [&'static str] {
+                &[#(#names),*]
+            }
+
+            fn field(&self, name: &str) -> Option<&dyn ::light::ecs::reflect::Reflect> {
+                match name {
+                    #(#names => Some(&self.#members),)*
+                    _ => None,
+                }
+            }
+
+            fn field_mut(&mut self, name: &str) -> Option<&mut dyn ::light::ecs::reflect::Reflect> {
+                match name {
+                    #(#names => Some(&mut self.#members),)*
+                    _ => None,
+                }
+            }
+        }
+    };
+    expanded.into()
+}
+
+/// `#[derive(StableHash)]` for structs and enums, hashes the fields in declaration order
+/// Enum variants are told apart by their index, every field should implement `StableHash`
+#[proc_macro_derive(StableHash)]
+pub fn derive_stable_hash(input: TokenStream) -> TokenStream {
+    let mut input = parse_macro_input!(input as DeriveInput);
+
+    let body = match &input.data {
+        Data::Struct(data) => {
+            let members: Vec<Member> = match &data.fields {
+                Fields::Named(fields) => fields.named.iter()
+                    .map(|field| Member::Named(field.ident.clone().unwrap()))
+                    .collect(),
+                Fields::Unnamed(fields) => (0..fields.unnamed.len())
+                    .map(|index| Member::Unnamed(Index::from(index)))
+                    .collect(),
+                Fields::Unit => Vec::new(),
+            };
+            quote! {
+                #(::light::ecs::stable_hash::StableHash::stable_hash(&self.#members, hasher);)*
+            }
+        },
+        Data::Enum(data) => {
+            let arms = data.variants.iter().enumerate().map(|(index, variant)| {
+                let variant_ident = &variant.ident;
+                let index = index as u32;
+                let bindings: Vec<Ident> = (0..variant.fields.len())
+                    .map(|field| Ident::new(&format!("field_{}", field), Span::call_site()))
+                    .collect();
+                let pattern = match &variant.fields {
+                    Fields::Named(fields) => {
+                        let names = fields.named.iter().map(|field| field.ident.clone().unwrap());
+                        quote! { { #(#names: #bindings),* } }
+                    },
+                    Fields::Unnamed(_) => quote! { ( #(#bindings),* ) },
+                    Fields::Unit => quote! {},
+                };
+                quote! {
+                    Self::#variant_ident #pattern => {
+                        hasher.write_u32(#index);
+                        #(::light::ecs::stable_hash::StableHash::stable_hash(#bindings, hasher);)*
+                    }
+                }
+            });
+            quote! {
+                match self {
+                    #(#arms,)*
+                }
+            }
+        },
+        Data::Union(_) => return syn::Error::new_spanned(&input.ident, "StableHash cannot be derived for unions")
+            .to_compile_error()
+            .into(),
+    };
+
+    let type_params: Vec<_> = input.generics.type_params().map(|param| param.ident.clone()).collect();
+    let where_clause = input.generics.make_where_clause();
+    for param in type_params {
+        where_clause.predicates.push(parse_quote!(#param: ::light::ecs::stable_hash::StableHash));
+    }
+
+    let ident = &input.ident;
+    let (impl_generics, ty_generics, where_clause) = input.generics.split_for_impl();
+    let expanded: TokenStream2 = quote! {
+        impl #impl_generics ::light::ecs::stable_hash::StableHash for #ident #ty_generics #where_clause {
+            #[allow(unused_variables)]
+            fn stable_hash(&self, hasher: &mut ::light::ecs::stable_hash::StableHasher) {
+                #body
+            }
+        }
+    };
+    expanded.into()
+}
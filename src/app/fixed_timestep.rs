@@ -0,0 +1,176 @@
+use std::{collections::HashMap, time::Duration};
+
+use crate::{ecs::World, time::Time};
+
+use super::stage::{RunCriteria, ShouldRun};
+
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct FixedTimestepState {
+    pub step: Duration,
+    pub accumulator: Duration,
+}
+
+impl FixedTimestepState {
+    /// Fraction of a step left in the accumulator after the last run,
+    /// used to interpolate between the last two simulation states when rendering
+    pub fn alpha(&self) -> f64 {
+        self.accumulator.as_secs_f64() / self.step.as_secs_f64()
+    }
+}
+
+/// Resource exposing the state of labeled fixed timesteps
+#[derive(Default)]
+pub struct FixedTimesteps {
+    states: HashMap<&'static str, FixedTimestepState>,
+}
+
+impl FixedTimesteps {
+    pub fn get(&self, label: &str) -> Option<&FixedTimestepState> {
+        self.states.get(label)
+    }
+}
+
+/// Run criteria running a stage once per `step` of time accumulated from the `Time` resource
+///
+/// `app.set_run_criteria(Stage::Custom("physics"), FixedTimestep::step(Duration::from_secs_f64(1.0 / 60.0)))`
+pub struct FixedTimestep {
+    state: FixedTimestepState,
+    max_steps_per_frame: u32,
+    steps_this_frame: u32,
+    label: Option<&'static str>,
+}
+
+impl FixedTimestep {
+    pub const DEFAULT_MAX_STEPS_PER_FRAME: u32 = 8;
+
+    pub fn step(step: Duration) -> Self {
+        assert!(!step.is_zero(), "Fixed timestep cannot be zero");
+        FixedTimestep {
+            state: FixedTimestepState {
+                step,
+                accumulator: Duration::ZERO,
+            },
+            max_steps_per_frame: Self::DEFAULT_MAX_STEPS_PER_FRAME,
+            steps_this_frame: 0,
+            label: None,
+        }
+    }
+
+    pub fn steps_per_second(rate: f64) -> Self {
+        Self::step(Duration::from_secs_f64(1.0 / rate))
+    }
+
+    /// Caps the number of catch-up runs in a single frame,
+    /// time exceeding the cap is dropped to avoid a spiral of death
+    pub fn with_max_steps_per_frame(mut self, max_steps_per_frame: u32) -> Self {
+        self.max_steps_per_frame = max_steps_per_frame;
+        self
+    }
+
+    /// Publishes the state to the `FixedTimesteps` resource under `label`
+    pub fn with_label(mut self, label: &'static str) -> Self {
+        self.label = Some(label);
+        self
+    }
+
+    fn publish(&self, world: &mut World) {
+        if let Some(label) = self.label {
+            if let Some(fixed_timesteps) = world.get_resource_mut::<FixedTimesteps>() {
+                fixed_timesteps.states.insert(label, self.state);
+            }
+        }
+    }
+}
+
+impl RunCriteria for FixedTimestep {
+    fn initialize(&mut self, world: &mut World) {
+        if self.label.is_some() && !world.contains_resource::<FixedTimesteps>() {
+            world.insert_resource(FixedTimesteps::default());
+        }
+    }
+
+    fn should_run(&mut self, world: &mut World) -> ShouldRun {
+        // first check of the frame
+        if self.steps_this_frame == 0 {
+            if let Some(time) = world.get_resource::<Time>() {
+                self.state.accumulator += time.delta();
+            }
+        }
+
+        if self.state.accumulator >= self.state.step && self.steps_this_frame < self.max_steps_per_frame {
+            self.state.accumulator -= self.state.step;
+            self.steps_this_frame += 1;
+            return ShouldRun::YesAndCheckAgain;
+        }
+
+        if self.state.accumulator >= self.state.step {
+            // hit the cap, keep only the partial step
+            let step_nanos = self.state.step.as_nanos();
+            let left_nanos = self.state.accumulator.as_nanos() % step_nanos;
+            self.state.accumulator = Duration::from_nanos(left_nanos as u64);
+        }
+        self.steps_this_frame = 0;
+        self.publish(world);
+        ShouldRun::No
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use crate::app::{App, Stage};
+    use crate::ecs::system::param::ResMut;
+    use crate::time::{Time, ManualClock};
+
+    use super::{FixedTimestep, FixedTimesteps};
+
+    #[derive(Default)]
+    struct Steps(u32);
+
+    fn count_steps(mut steps: ResMut<Steps>) {
+        steps.0 += 1;
+    }
+
+    fn advance(app: &mut App, clock: &ManualClock, millis: u64) -> u32 {
+        clock.advance(Duration::from_millis(millis));
+        app.world_mut().get_resource_mut::<Steps>().unwrap().0 = 0;
+        app.update();
+        app.world().get_resource::<Steps>().unwrap().0
+    }
+
+    fn fixed_app(clock: &ManualClock, fixed_timestep: FixedTimestep) -> App {
+        let mut app = App::new();
+        app.insert_resource(Time::with_clock(clock.clone()))
+            .insert_resource(Steps::default())
+            .add_stage_after(Stage::Update, Stage::Custom("fixed"))
+            .set_run_criteria(Stage::Custom("fixed"), fixed_timestep)
+            .add_system_to_stage(Stage::Custom("fixed"), count_steps);
+        app
+    }
+
+    #[test]
+    fn runs_zero_or_more_times_per_frame() {
+        let clock = ManualClock::new();
+        let mut app = fixed_app(&clock, FixedTimestep::step(Duration::from_millis(10)).with_label("fixed"));
+        assert_eq!(0, advance(&mut app, &clock, 0));
+        assert_eq!(0, advance(&mut app, &clock, 5));
+        assert_eq!(1, advance(&mut app, &clock, 7));
+        assert_eq!(3, advance(&mut app, &clock, 33));
+
+        let state = *app.world().get_resource::<FixedTimesteps>().unwrap().get("fixed").unwrap();
+        assert_eq!(Duration::from_millis(5), state.accumulator);
+        assert!((state.alpha() - 0.5).abs() < 1e-9);
+    }
+
+    #[test]
+    fn catch_up_is_capped() {
+        let clock = ManualClock::new();
+        let mut app = fixed_app(&clock, FixedTimestep::step(Duration::from_millis(10)).with_max_steps_per_frame(2));
+        advance(&mut app, &clock, 0);
+        assert_eq!(2, advance(&mut app, &clock, 105));
+        assert_eq!(0, advance(&mut app, &clock, 1));
+    }
+}
@@ -1,16 +1,208 @@
 use std::collections::HashMap;
 
-use crate::ecs::{World, system::System};
+use crate::time::{Time, time_system};
+use crate::hierarchy::register_hierarchy;
+use crate::transform::transform_propagate_system;
+use crate::relation::register_relation;
+use crate::ecs::{World, component::{Component, Resource}, system::{System, IntoSystem, exclusive::IntoExclusiveSystem}};
 
+use self::stage::{SystemStage, RunCriteria};
+use self::state::{StateData, State, StateDriver, SystemSet};
 
+pub mod stage;
+pub mod state;
+pub mod fixed_timestep;
+
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
 pub enum Stage {
     Startup,
+    /// Runs before `Update` every frame, core systems like `time_system` live here
+    First,
     Update,
+    /// Runs after `Update` every frame, propagates transforms
+    PostUpdate,
     Shutdown,
     Custom(&'static str)
 }
 
 pub struct App {
     world: World,
-    stage_order: Vec<Stage>
-}
\ No newline at end of file
+    stage_order: Vec<Stage>,
+    stages: HashMap<Stage, SystemStage>,
+    state_drivers: Vec<Box<dyn StateDriver>>,
+    startup_done: bool,
+}
+
+impl Default for App {
+    fn default() -> Self {
+        App::new()
+    }
+}
+
+impl App {
+    pub fn new() -> Self {
+        let stage_order = vec![Stage::Startup, Stage::First, Stage::Update, Stage::PostUpdate, Stage::Shutdown];
+        let stages = stage_order.iter()
+                .map(|stage| (stage.clone(), SystemStage::new()))
+                .collect();
+        let mut app = App {
+            world: World::new(),
+            stage_order,
+            stages,
+            state_drivers: Vec::new(),
+            startup_done: false,
+        };
+        app.insert_resource(Time::new())
+            .add_system_to_stage(Stage::First, time_system)
+            .add_exclusive_system_to_stage(Stage::PostUpdate, transform_propagate_system);
+        register_hierarchy(&mut app.world);
+        app
+    }
+
+    pub fn world(&self) -> &World {
+        &self.world
+    }
+
+    pub fn world_mut(&mut self) -> &mut World {
+        &mut self.world
+    }
+
+    pub fn insert_resource<T: Resource>(&mut self, value: T) -> &mut Self {
+        self.world.insert_resource(value);
+        self
+    }
+
+    pub fn add_stage_before(&mut self, target: Stage, stage: Stage) -> &mut Self {
+        let index = self.stage_index(&target);
+        self.insert_stage(index, stage)
+    }
+
+    pub fn add_stage_after(&mut self, target: Stage, stage: Stage) -> &mut Self {
+        let index = self.stage_index(&target);
+        self.insert_stage(index + 1, stage)
+    }
+
+    fn stage_index(&self, stage: &Stage) -> usize {
+        self.stage_order.iter()
+            .position(|s| s == stage)
+            .unwrap_or_else(|| panic!("Stage {:?} does not exist", stage))
+    }
+
+    fn insert_stage(&mut self, index: usize, stage: Stage) -> &mut Self {
+        if self.stages.contains_key(&stage) {
+            panic!("Stage {:?} already exists", stage);
+        }
+        self.stage_order.insert(index, stage.clone());
+        self.stages.insert(stage, SystemStage::new());
+        self
+    }
+
+    pub fn set_run_criteria(&mut self, stage: Stage, mut run_criteria: impl RunCriteria + 'static) -> &mut Self {
+        run_criteria.initialize(&mut self.world);
+        self.get_stage_mut(&stage)
+            .set_run_criteria(Box::new(run_criteria));
+        self
+    }
+
+    pub fn add_system<Marker, Sys>(&mut self, system: Sys) -> &mut Self
+    where
+        Sys: IntoSystem<(), (), Marker>,
+        Sys::Sys: 'static
+    {
+        self.add_system_to_stage(Stage::Update, system)
+    }
+
+    pub fn add_startup_system<Marker, Sys>(&mut self, system: Sys) -> &mut Self
+    where
+        Sys: IntoSystem<(), (), Marker>,
+        Sys::Sys: 'static
+    {
+        self.add_system_to_stage(Stage::Startup, system)
+    }
+
+    pub fn add_system_to_stage<Marker, Sys>(&mut self, stage: Stage, system: Sys) -> &mut Self
+    where
+        Sys: IntoSystem<(), (), Marker>,
+        Sys::Sys: 'static
+    {
+        let mut system = system.system();
+        system.initialize(&mut self.world);
+        self.get_stage_mut(&stage)
+            .add_system(Box::new(system));
+        self
+    }
+
+    pub fn add_exclusive_system(&mut self, system: impl IntoExclusiveSystem) -> &mut Self {
+        self.add_exclusive_system_to_stage(Stage::Update, system)
+    }
+
+    pub fn add_startup_exclusive_system(&mut self, system: impl IntoExclusiveSystem) -> &mut Self {
+        self.add_exclusive_system_to_stage(Stage::Startup, system)
+    }
+
+    pub fn add_exclusive_system_to_stage(&mut self, stage: Stage, system: impl IntoExclusiveSystem) -> &mut Self {
+        let mut descriptor = system.exclusive_system();
+        descriptor.system.initialize(&mut self.world);
+        self.get_stage_mut(&stage)
+            .add_exclusive_system(descriptor);
+        self
+    }
+
+    fn get_stage_mut(&mut self, stage: &Stage) -> &mut SystemStage {
+        self.stages.get_mut(stage)
+            .unwrap_or_else(|| panic!("Stage {:?} does not exist", stage))
+    }
+
+    /// Cleans the `R` relations of despawned entities, see `register_relation`
+    pub fn add_relation<R: Component>(&mut self) -> &mut Self {
+        register_relation::<R>(&mut self.world);
+        self
+    }
+
+    /// Inserts the `State<S>` resource and drives its transitions
+    /// at the start of `Stage::Update` every frame
+    pub fn add_state<S: StateData>(&mut self, initial: S) -> &mut Self {
+        self.world.insert_resource(State::new(initial));
+        self.state_drivers.push(Box::new(state::StateSchedule::<S>::new()));
+        self
+    }
+
+    pub fn add_system_set<S: StateData>(&mut self, system_set: SystemSet<S>) -> &mut Self {
+        let schedule = self.state_drivers.iter_mut()
+            .find_map(|driver| driver.as_any_mut().downcast_mut::<state::StateSchedule<S>>())
+            .expect("State is not added, call App::add_state first");
+        schedule.add_system_set(&mut self.world, system_set);
+        self
+    }
+
+    fn run_stage(&mut self, stage: &Stage) {
+        if *stage == Stage::Update {
+            for driver in self.state_drivers.iter_mut() {
+                driver.run(&mut self.world);
+            }
+        }
+        if let Some(system_stage) = self.stages.get_mut(stage) {
+            system_stage.run(&mut self.world);
+        }
+    }
+
+    /// Runs a single frame, `Stage::Startup` is run only on the first call
+    /// and `Stage::Shutdown` is left for `App::shutdown`
+    pub fn update(&mut self) {
+        let stage_order = self.stage_order.clone();
+        for stage in stage_order.iter() {
+            match stage {
+                Stage::Startup if self.startup_done => continue,
+                Stage::Shutdown => continue,
+                _ => self.run_stage(stage),
+            }
+        }
+        self.startup_done = true;
+        self.world.clear_trackers();
+    }
+
+    pub fn shutdown(&mut self) {
+        self.run_stage(&Stage::Shutdown);
+    }
+}
@@ -0,0 +1,191 @@
+use crate::ecs::{World, system::{BoxedSystem, exclusive::{BoxedExclusiveSystem, ExclusiveSystemDescriptor, ExclusivePosition}}};
+
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ShouldRun {
+    Yes,
+    No,
+    /// Run the stage, then ask again in the same frame
+    YesAndCheckAgain,
+}
+
+/// Decides if and how many times a stage runs in a frame
+pub trait RunCriteria {
+    fn initialize(&mut self, _world: &mut World) {}
+    fn should_run(&mut self, world: &mut World) -> ShouldRun;
+}
+
+enum StageSystem {
+    Parallel(BoxedSystem),
+    Exclusive(BoxedExclusiveSystem),
+}
+
+/// Systems of a single Stage
+///
+/// Runs exclusive systems added `at_start`, then the rest in insertion order,
+/// then exclusive systems added `at_end`
+/// An exclusive system is a synchronisation barrier:
+/// it only starts after every system before it is finished
+#[derive(Default)]
+pub struct SystemStage {
+    run_criteria: Option<Box<dyn RunCriteria>>,
+    exclusive_at_start: Vec<BoxedExclusiveSystem>,
+    systems: Vec<StageSystem>,
+    exclusive_at_end: Vec<BoxedExclusiveSystem>,
+}
+
+impl SystemStage {
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    /// `run_criteria` should already be initialized
+    pub fn set_run_criteria(&mut self, run_criteria: Box<dyn RunCriteria>) {
+        self.run_criteria = Some(run_criteria);
+    }
+
+    /// `system` should already be initialized
+    pub fn add_system(&mut self, system: BoxedSystem) {
+        self.systems.push(StageSystem::Parallel(system));
+    }
+
+    /// `descriptor.system` should already be initialized
+    pub fn add_exclusive_system(&mut self, descriptor: ExclusiveSystemDescriptor) {
+        match descriptor.position {
+            ExclusivePosition::AtStart => self.exclusive_at_start.push(descriptor.system),
+            ExclusivePosition::InOrder => self.systems.push(StageSystem::Exclusive(descriptor.system)),
+            ExclusivePosition::AtEnd => self.exclusive_at_end.push(descriptor.system),
+        }
+    }
+
+    pub fn run(&mut self, world: &mut World) {
+        let mut run_criteria = match self.run_criteria.take() {
+            Some(run_criteria) => run_criteria,
+            None => {
+                self.run_once(world);
+                return;
+            }
+        };
+        loop {
+            match run_criteria.should_run(world) {
+                ShouldRun::No => break,
+                ShouldRun::Yes => {
+                    self.run_once(world);
+                    break;
+                },
+                ShouldRun::YesAndCheckAgain => self.run_once(world),
+            }
+        }
+        self.run_criteria = Some(run_criteria);
+    }
+
+    /// Every system runs with its own change tick, so a system sees the changes made
+    /// by the systems after it in the previous frame and before it in this one
+    fn run_once(&mut self, world: &mut World) {
+        for system in self.exclusive_at_start.iter_mut() {
+            world.increment_change_tick();
+            system.run(world);
+        }
+        for system in self.systems.iter_mut() {
+            world.increment_change_tick();
+            match system {
+                StageSystem::Parallel(system) => unsafe { system.run(world, ()); },
+                StageSystem::Exclusive(system) => system.run(world),
+            }
+        }
+        for system in self.exclusive_at_end.iter_mut() {
+            world.increment_change_tick();
+            system.run(world);
+        }
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use crate::app::App;
+    use crate::ecs::{World, entity::Entity, query::state::Query};
+    use crate::ecs::system::{exclusive::IntoExclusiveSystem, param::ResMut};
+
+    #[derive(Default)]
+    struct Log(Vec<&'static str>);
+
+    fn normal(mut log: ResMut<Log>) {
+        log.0.push("normal");
+    }
+
+    fn exclusive_start(world: &mut World) {
+        world.get_resource_mut::<Log>().unwrap().0.push("start");
+    }
+
+    fn exclusive_in_order(world: &mut World) {
+        world.get_resource_mut::<Log>().unwrap().0.push("in order");
+    }
+
+    fn exclusive_end(world: &mut World) {
+        world.get_resource_mut::<Log>().unwrap().0.push("end");
+    }
+
+    #[test]
+    fn exclusive_system_positions() {
+        let mut app = App::new();
+        app.insert_resource(Log::default())
+            .add_exclusive_system(exclusive_end.exclusive_system().at_end())
+            .add_system(normal)
+            .add_exclusive_system(exclusive_in_order)
+            .add_system(normal)
+            .add_exclusive_system(exclusive_start.exclusive_system().at_start());
+        app.update();
+
+        assert_eq!(
+            vec!["start", "normal", "in order", "normal", "end"],
+            app.world().get_resource::<Log>().unwrap().0
+        );
+    }
+
+    #[test]
+    fn exclusive_system_can_insert_resources() {
+        let mut app = App::new();
+        app.add_startup_exclusive_system(|world: &mut World| world.insert_resource(Log::default()))
+            .add_system(normal);
+        app.update();
+
+        assert_eq!(vec!["normal"], app.world().get_resource::<Log>().unwrap().0);
+    }
+
+    struct Health(u32);
+
+    #[derive(Default)]
+    struct Changes {
+        last_run: u32,
+        seen: Vec<Vec<Entity>>,
+    }
+
+    fn read_changes(world: &mut World) {
+        let last_run = world.get_resource::<Changes>().unwrap().last_run;
+        let changed = world.changed_since::<Health>(last_run);
+        let this_run = world.change_tick();
+        let changes = world.get_resource_mut::<Changes>().unwrap();
+        changes.seen.push(changed);
+        changes.last_run = this_run;
+    }
+
+    fn heal(mut healths: Query<&mut Health>) {
+        healths.for_each_mut(|health| health.0 += 1);
+    }
+
+    #[test]
+    fn writes_after_a_reader_are_seen_next_frame() {
+        let mut app = App::new();
+        let entity = app.world_mut().spawn().insert(Health(1)).id();
+        app.insert_resource(Changes::default())
+            .add_exclusive_system(read_changes)
+            .add_system(heal);
+        app.update();
+        app.update();
+
+        let changes = app.world().get_resource::<Changes>().unwrap();
+        assert_eq!(vec![vec![entity], vec![entity]], changes.seen);
+        assert_eq!(3, app.world().get::<Health>(entity).unwrap().0);
+    }
+}
@@ -0,0 +1,335 @@
+use std::{any::Any, collections::HashMap, hash::Hash};
+
+use crate::ecs::{World, system::{IntoSystem, BoxedSystem}};
+
+
+pub trait StateData: Clone + Eq + Hash + Send + Sync + 'static {}
+impl<T> StateData for T where T: Clone + Eq + Hash + Send + Sync + 'static {}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum StateError {
+    AlreadyInState,
+    StateAlreadyQueued,
+    StackEmpty,
+}
+
+enum ScheduledOperation<S: StateData> {
+    Set(S),
+    Push(S),
+    Pop,
+}
+
+/// Resource holding a stack of states
+/// Only the top of the stack is active, the rest are inactive (paused)
+/// Transitions are queued and applied by the App at the start of `Stage::Update`
+pub struct State<S: StateData> {
+    stack: Vec<S>,
+    scheduled: Option<ScheduledOperation<S>>,
+}
+
+impl<S: StateData> State<S> {
+    pub fn new(initial: S) -> Self {
+        State {
+            stack: vec![initial],
+            scheduled: None,
+        }
+    }
+
+    pub fn current(&self) -> &S {
+        self.stack.last().unwrap()
+    }
+
+    pub fn inactives(&self) -> &[S] {
+        self.stack.split_last()
+            .map(|(_, rest)| rest)
+            .unwrap_or(&[])
+    }
+
+    /// Replaces the current state, runs `on_exit` of the current and `on_enter` of the new state
+    pub fn set(&mut self, state: S) -> Result<(), StateError> {
+        if *self.current() == state {
+            return Err(StateError::AlreadyInState);
+        }
+        self.schedule(ScheduledOperation::Set(state))
+    }
+
+    /// Pauses the current state and puts the new one on top, runs `on_enter` of the new state
+    pub fn push(&mut self, state: S) -> Result<(), StateError> {
+        if *self.current() == state {
+            return Err(StateError::AlreadyInState);
+        }
+        self.schedule(ScheduledOperation::Push(state))
+    }
+
+    /// Removes the current state and resumes the one below, runs `on_exit` of the current state
+    pub fn pop(&mut self) -> Result<(), StateError> {
+        if self.stack.len() == 1 {
+            return Err(StateError::StackEmpty);
+        }
+        self.schedule(ScheduledOperation::Pop)
+    }
+
+    fn schedule(&mut self, operation: ScheduledOperation<S>) -> Result<(), StateError> {
+        if self.scheduled.is_some() {
+            return Err(StateError::StateAlreadyQueued);
+        }
+        self.scheduled = Some(operation);
+        Ok(())
+    }
+}
+
+#[derive(Clone, Copy)]
+enum StateHook {
+    Enter,
+    Exit,
+    Update,
+}
+
+/// Systems to run on a transition into, out of or while in a state
+///
+/// `SystemSet::on_enter(GameState::Menu).with_system(setup_menu)`
+pub struct SystemSet<S: StateData> {
+    state: S,
+    hook: StateHook,
+    systems: Vec<BoxedSystem>,
+}
+
+impl<S: StateData> SystemSet<S> {
+    fn new(state: S, hook: StateHook) -> Self {
+        SystemSet {
+            state,
+            hook,
+            systems: Vec::new(),
+        }
+    }
+
+    pub fn on_enter(state: S) -> Self {
+        Self::new(state, StateHook::Enter)
+    }
+
+    pub fn on_exit(state: S) -> Self {
+        Self::new(state, StateHook::Exit)
+    }
+
+    pub fn on_update(state: S) -> Self {
+        Self::new(state, StateHook::Update)
+    }
+
+    pub fn with_system<Marker, Sys>(mut self, system: Sys) -> Self
+    where
+        Sys: IntoSystem<(), (), Marker>,
+        Sys::Sys: 'static
+    {
+        self.systems.push(Box::new(system.system()));
+        self
+    }
+}
+
+#[derive(Default)]
+struct StateSystems {
+    on_enter: Vec<BoxedSystem>,
+    on_exit: Vec<BoxedSystem>,
+    on_update: Vec<BoxedSystem>,
+}
+
+/// Type erased `StateSchedule` so the App can drive states of different types
+pub trait StateDriver {
+    fn run(&mut self, world: &mut World);
+    fn as_any_mut(&mut self) -> &mut dyn Any;
+}
+
+pub struct StateSchedule<S: StateData> {
+    systems: HashMap<S, StateSystems>,
+    entered_initial: bool,
+}
+
+impl<S: StateData> Default for StateSchedule<S> {
+    fn default() -> Self {
+        StateSchedule {
+            systems: HashMap::new(),
+            entered_initial: false,
+        }
+    }
+}
+
+impl<S: StateData> StateSchedule<S> {
+    // Upper bound for transitions queued by on_enter/on_exit systems in a single frame
+    const MAX_TRANSITIONS_PER_FRAME: usize = 8;
+
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    pub fn add_system_set(&mut self, world: &mut World, system_set: SystemSet<S>) {
+        let state_systems = self.systems.entry(system_set.state).or_default();
+        let systems = match system_set.hook {
+            StateHook::Enter => &mut state_systems.on_enter,
+            StateHook::Exit => &mut state_systems.on_exit,
+            StateHook::Update => &mut state_systems.on_update,
+        };
+        for mut system in system_set.systems {
+            system.initialize(world);
+            systems.push(system);
+        }
+    }
+
+    fn run_hook(&mut self, world: &mut World, state: &S, hook: StateHook) {
+        let state_systems = match self.systems.get_mut(state) {
+            Some(state_systems) => state_systems,
+            None => return,
+        };
+        let systems = match hook {
+            StateHook::Enter => &mut state_systems.on_enter,
+            StateHook::Exit => &mut state_systems.on_exit,
+            StateHook::Update => &mut state_systems.on_update,
+        };
+        for system in systems.iter_mut() {
+            world.increment_change_tick();
+            unsafe { system.run(world, ()); }
+        }
+    }
+
+    /// Applies the queued operation, returns false if there was none
+    fn apply_transition(&mut self, world: &mut World) -> bool {
+        let state = match world.get_resource_mut::<State<S>>() {
+            Some(state) => state,
+            None => return false,
+        };
+        let operation = match state.scheduled.take() {
+            Some(operation) => operation,
+            None => return false,
+        };
+        let current = state.current().clone();
+
+        match operation {
+            ScheduledOperation::Set(next) => {
+                self.run_hook(world, &current, StateHook::Exit);
+                *world.get_resource_mut::<State<S>>().unwrap()
+                    .stack.last_mut().unwrap() = next.clone();
+                self.run_hook(world, &next, StateHook::Enter);
+            },
+            ScheduledOperation::Push(next) => {
+                world.get_resource_mut::<State<S>>().unwrap()
+                    .stack.push(next.clone());
+                self.run_hook(world, &next, StateHook::Enter);
+            },
+            ScheduledOperation::Pop => {
+                self.run_hook(world, &current, StateHook::Exit);
+                world.get_resource_mut::<State<S>>().unwrap()
+                    .stack.pop();
+            },
+        }
+        true
+    }
+}
+
+impl<S: StateData> StateDriver for StateSchedule<S> {
+    fn run(&mut self, world: &mut World) {
+        if !self.entered_initial {
+            self.entered_initial = true;
+            if let Some(state) = world.get_resource::<State<S>>() {
+                let initial = state.current().clone();
+                self.run_hook(world, &initial, StateHook::Enter);
+            }
+        }
+
+        for _ in 0..Self::MAX_TRANSITIONS_PER_FRAME {
+            if !self.apply_transition(world) {
+                break;
+            }
+        }
+
+        let current = match world.get_resource::<State<S>>() {
+            Some(state) => state.current().clone(),
+            None => return,
+        };
+        self.run_hook(world, &current, StateHook::Update);
+    }
+
+    fn as_any_mut(&mut self) -> &mut dyn Any {
+        self
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use crate::app::App;
+    use crate::ecs::system::param::ResMut;
+
+    use super::{State, StateError, SystemSet};
+
+    #[derive(Debug, Clone, PartialEq, Eq, Hash)]
+    enum GameState {
+        Menu,
+        Playing,
+        Paused,
+    }
+
+    #[derive(Default)]
+    struct Log(Vec<&'static str>);
+
+    fn enter_menu(mut log: ResMut<Log>) { log.0.push("enter menu"); }
+    fn exit_menu(mut log: ResMut<Log>) { log.0.push("exit menu"); }
+    fn update_menu(mut log: ResMut<Log>) { log.0.push("update menu"); }
+    fn enter_playing(mut log: ResMut<Log>) { log.0.push("enter playing"); }
+    fn update_playing(mut log: ResMut<Log>) { log.0.push("update playing"); }
+    fn enter_paused(mut log: ResMut<Log>) { log.0.push("enter paused"); }
+    fn exit_paused(mut log: ResMut<Log>) { log.0.push("exit paused"); }
+
+    #[test]
+    fn state_operations_are_queued() {
+        let mut state = State::new(GameState::Menu);
+        assert_eq!(Err(StateError::AlreadyInState), state.set(GameState::Menu));
+        assert_eq!(Err(StateError::StackEmpty), state.pop());
+        assert_eq!(Ok(()), state.set(GameState::Playing));
+        assert_eq!(Err(StateError::StateAlreadyQueued), state.push(GameState::Paused));
+        assert_eq!(&GameState::Menu, state.current());
+    }
+
+    #[test]
+    fn transitions_run_enter_exit_update() {
+        let mut app = App::new();
+        app.insert_resource(Log::default())
+            .add_state(GameState::Menu)
+            .add_system_set(SystemSet::on_update(GameState::Menu).with_system(update_menu))
+            .add_system_set(SystemSet::on_exit(GameState::Menu).with_system(exit_menu))
+            .add_system_set(SystemSet::on_enter(GameState::Playing).with_system(enter_playing))
+            .add_system_set(SystemSet::on_update(GameState::Playing).with_system(update_playing))
+            .add_system_set(SystemSet::on_enter(GameState::Paused).with_system(enter_paused))
+            .add_system_set(SystemSet::on_exit(GameState::Paused).with_system(exit_paused));
+
+        app.update();
+        app.world_mut().get_resource_mut::<State<GameState>>().unwrap()
+            .set(GameState::Playing).unwrap();
+        app.update();
+        app.world_mut().get_resource_mut::<State<GameState>>().unwrap()
+            .push(GameState::Paused).unwrap();
+        app.update();
+        assert_eq!(&[GameState::Playing], app.world().get_resource::<State<GameState>>().unwrap().inactives());
+        app.world_mut().get_resource_mut::<State<GameState>>().unwrap()
+            .pop().unwrap();
+        app.update();
+
+        assert_eq!(
+            vec![
+                "update menu",
+                "exit menu", "enter playing", "update playing",
+                "enter paused",
+                "exit paused", "update playing",
+            ],
+            app.world().get_resource::<Log>().unwrap().0
+        );
+    }
+
+    #[test]
+    fn initial_state_is_entered_on_first_update() {
+        let mut app = App::new();
+        app.insert_resource(Log::default())
+            .add_state(GameState::Menu)
+            .add_system_set(SystemSet::on_enter(GameState::Menu).with_system(enter_menu));
+        app.update();
+        app.update();
+        assert_eq!(vec!["enter menu"], app.world().get_resource::<Log>().unwrap().0);
+    }
+}
@@ -0,0 +1,40 @@
+use super::component::{Component, ComponentId, Components};
+
+
+/// Tuple of components spawned together: `world.spawn_batch((0..100).map(|i| (Position(i), Velocity(1))))`
+///
+/// # Safety
+/// `field_offsets` should give the byte offset of every field of `Self`,
+/// in the order of `component_ids`
+pub unsafe trait Bundle: Send + Sync + 'static {
+    /// Ids of the components in field order, registers the missing ones
+    fn component_ids(components: &mut Components) -> Vec<ComponentId>;
+    fn field_offsets() -> Vec<usize>;
+}
+
+macro_rules! impl_bundle {
+    ($($name:ident $index:tt),*) => {
+        unsafe impl<$($name: Component),*> Bundle for ($($name,)*) {
+            fn component_ids(components: &mut Components) -> Vec<ComponentId> {
+                vec![$(components.add_component::<$name>()),*]
+            }
+
+            fn field_offsets() -> Vec<usize> {
+                vec![$(std::mem::offset_of!(Self, $index)),*]
+            }
+        }
+    };
+}
+
+impl_bundle!(A 0);
+impl_bundle!(A 0, B 1);
+impl_bundle!(A 0, B 1, C 2);
+impl_bundle!(A 0, B 1, C 2, D 3);
+impl_bundle!(A 0, B 1, C 2, D 3, E 4);
+impl_bundle!(A 0, B 1, C 2, D 3, E 4, F 5);
+impl_bundle!(A 0, B 1, C 2, D 3, E 4, F 5, G 6);
+impl_bundle!(A 0, B 1, C 2, D 3, E 4, F 5, G 6, H 7);
+impl_bundle!(A 0, B 1, C 2, D 3, E 4, F 5, G 6, H 7, I 8);
+impl_bundle!(A 0, B 1, C 2, D 3, E 4, F 5, G 6, H 7, I 8, J 9);
+impl_bundle!(A 0, B 1, C 2, D 3, E 4, F 5, G 6, H 7, I 8, J 9, K 10);
+impl_bundle!(A 0, B 1, C 2, D 3, E 4, F 5, G 6, H 7, I 8, J 9, K 10, L 11);
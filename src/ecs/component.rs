@@ -2,6 +2,10 @@ use std::{any::TypeId, alloc::Layout, collections::HashMap};
 
 use fixedbitset::FixedBitSet;
 
+use super::stable_hash::{StableHash, StableHashFn, stable_hash_ptr};
+use super::entity_map::{MapEntities, MapEntitiesFn, map_entities_ptr};
+use super::{World, entity::Entity};
+
 
 pub trait Resource: Send + Sync + 'static {}
 impl<T> Resource for T where T: Send + Sync + 'static {}
@@ -10,7 +14,7 @@ pub trait Component: Send + Sync + 'static {}
 impl<T> Component for T where T: Send + Sync + 'static {}
 
 
-#[derive(Clone, Hash, PartialEq, Eq, PartialOrd, Ord)]
+#[derive(Debug, Clone, Hash, PartialEq, Eq, PartialOrd, Ord)]
 pub struct ComponentId(pub usize);
 impl ComponentId {
     pub fn id(&self) -> usize {
@@ -18,38 +22,171 @@ impl ComponentId {
     }
 }
 
+/// # Safety
+/// `ptr` should point to a valid `T`, which is dropped in place
+pub unsafe fn drop_ptr<T>(ptr: *mut u8) {
+    ptr.cast::<T>().drop_in_place();
+}
+
+/// # Safety
+/// `src` should point to a valid `T`, `dst` to memory for a `T` that is overwritten without drop
+pub unsafe fn clone_ptr<T: Clone>(src: *const u8, dst: *mut u8) {
+    dst.cast::<T>().write((*src.cast::<T>()).clone());
+}
+
+/// # Safety
+/// both should point to valid `T`s
+pub unsafe fn eq_ptr<T: PartialEq>(a: *const u8, b: *const u8) -> bool {
+    *a.cast::<T>() == *b.cast::<T>()
+}
+
+pub type CloneFn = unsafe fn(*const u8, *mut u8);
+pub type EqFn = unsafe fn(*const u8, *const u8) -> bool;
+
+/// Called with the entity whose component changed, see `ComponentHooks`
+pub type ComponentHook = fn(&mut World, Entity, ComponentId);
+
+/// Callbacks run by the world when a component of the type changes on an entity,
+/// to release what the value owns outside the world (GPU buffers, physics bodies...)
+/// - `on_add`: the entity did not have the component, runs before `on_insert`
+/// - `on_insert`: a value was inserted, new or replacing the old one
+/// - `on_remove`: the component is about to be removed or its entity despawned, the value can still be read
+/// - `on_move`: the entity is about to leave for another world through `World::move_entities_from`,
+///   runs in the world it leaves, which keeps what the hook removes
+///
+/// `world.register_component_hooks::<RigidBody>().on_remove(|world, entity, _| ..)`
+#[derive(Debug, Clone, Copy, Default)]
+pub struct ComponentHooks {
+    pub(crate) on_add: Option<ComponentHook>,
+    pub(crate) on_insert: Option<ComponentHook>,
+    pub(crate) on_remove: Option<ComponentHook>,
+    pub(crate) on_move: Option<ComponentHook>,
+}
+
+impl ComponentHooks {
+    /// Replaces the `on_add` hook
+    pub fn on_add(&mut self, hook: ComponentHook) -> &mut Self {
+        self.on_add = Some(hook);
+        self
+    }
+
+    pub fn on_insert(&mut self, hook: ComponentHook) -> &mut Self {
+        self.on_insert = Some(hook);
+        self
+    }
+
+    pub fn on_remove(&mut self, hook: ComponentHook) -> &mut Self {
+        self.on_remove = Some(hook);
+        self
+    }
+
+    pub fn on_move(&mut self, hook: ComponentHook) -> &mut Self {
+        self.on_move = Some(hook);
+        self
+    }
+
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.on_add.is_none() && self.on_insert.is_none() && self.on_remove.is_none() && self.on_move.is_none()
+    }
+}
+
+/// Drop fn of components without drop glue
+fn drop_nothing(_ptr: *mut u8) {}
+
+/// Where the values of a component live
+/// `SparseSet` suits markers that are added and removed often, they do not move the entity between tables
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum StorageType {
+    #[default]
+    Table,
+    SparseSet,
+}
+
 #[derive(Clone)]
 pub struct ComponentDescriptor {
     pub id: ComponentId,
-    pub typeid: TypeId,
+    pub name: String,
+    pub typeid: Option<TypeId>, // None for dynamic components
     pub layout: Layout,
+    pub drop: unsafe fn(*mut u8),
+    pub storage_type: StorageType,
     pub bitmask: FixedBitSet,
+    /// Set by `Components::register_cloneable`, values without it are left out of snapshots
+    pub clone: Option<CloneFn>,
+    pub eq: Option<EqFn>,
+    /// Set by `Components::register_stable_hash`, values without it are left out of `World::checksum`
+    pub stable_hash: Option<StableHashFn>,
+    /// Set by `Components::register_map_entities`, remaps the handles of moved entities
+    pub map_entities: Option<MapEntitiesFn>,
+    /// Set through `Components::hooks_mut`
+    pub hooks: ComponentHooks,
 }
 
 impl ComponentDescriptor {
     #[inline]
     pub fn of<T: Component>(component_id: ComponentId) -> ComponentDescriptor {
-        let id = component_id.id();
-        let id_one = id + 1;
+        ComponentDescriptor {
+            bitmask: Self::bitmask_of(&component_id),
+            id: component_id,
+            name: std::any::type_name::<T>().to_string(),
+            typeid: Some(TypeId::of::<T>()),
+            layout: Layout::new::<T>(),
+            drop: drop_ptr::<T>,
+            storage_type: StorageType::Table,
+            clone: None,
+            eq: None,
+            stable_hash: None,
+            map_entities: None,
+            hooks: ComponentHooks::default(),
+        }
+    }
+
+    /// Descriptor of a component only known at runtime, `drop` is None for plain data
+    #[inline]
+    pub fn dynamic(component_id: ComponentId, name: &str, layout: Layout, drop: Option<unsafe fn(*mut u8)>) -> ComponentDescriptor {
+        ComponentDescriptor {
+            bitmask: Self::bitmask_of(&component_id),
+            id: component_id,
+            name: name.to_string(),
+            typeid: None,
+            layout,
+            drop: drop.unwrap_or(drop_nothing),
+            storage_type: StorageType::Table,
+            clone: None,
+            eq: None,
+            stable_hash: None,
+            map_entities: None,
+            hooks: ComponentHooks::default(),
+        }
+    }
+
+    fn bitmask_of(component_id: &ComponentId) -> FixedBitSet {
+        let id_one = component_id.id() + 1;
 
         let block_size: usize = 32;
         
-        let (mut blocks, rem) = (id_one / block_size, id_one % block_size);
+        // bit `id_one` needs `id_one + 1` bits
+        let (mut blocks, rem) = ((id_one + 1) / block_size, (id_one + 1) % block_size);
         blocks += (rem > 0) as usize;
         let mut bitmask = FixedBitSet::with_capacity(blocks * block_size);
         bitmask.set(id_one, true);
-
-        ComponentDescriptor {
-            id: component_id,
-            typeid: TypeId::of::<T>(),
-            layout: Layout::new::<T>(),
-            bitmask,
-        }
+        bitmask
     }
 
     #[inline]
     pub fn is_for<T: Component>(&self) -> bool {
-        self.typeid == TypeId::of::<T>()
+        self.typeid == Some(TypeId::of::<T>())
+    }
+
+    #[inline]
+    pub fn is_dynamic(&self) -> bool {
+        self.typeid.is_none()
+    }
+
+    #[inline]
+    pub fn is_cloneable(&self) -> bool {
+        self.clone.is_some()
     }
 }
 
@@ -60,6 +197,7 @@ pub struct Components {
     descriptors: Vec<ComponentDescriptor>,
     indices: HashMap<TypeId, usize>,
     resource_indices: HashMap<TypeId, usize>,
+    dynamic_indices: HashMap<String, usize>,
 }
 
 impl Components {
@@ -68,17 +206,145 @@ impl Components {
         Default::default()
     }
 
+    /// Registers `T` with `StorageType::Table` if it is not registered yet
     #[inline]
     pub fn add_component<T: Component>(&mut self) -> ComponentId {
         let typeid = TypeId::of::<T>();
         let index = self.descriptors.len();
-        self.indices.entry(typeid).or_insert_with(|| {
+        let index = *self.indices.entry(typeid).or_insert_with(|| {
             self.descriptors.push(ComponentDescriptor::of::<T>(ComponentId(index)));
             index
         });
         ComponentId(index)
     }
 
+    /// # Panics
+    /// if `T` is already registered with another storage type
+    pub fn add_component_with_storage<T: Component>(&mut self, storage_type: StorageType) -> ComponentId {
+        if let Some(descriptor) = self.get_component::<T>() {
+            assert_eq!(storage_type, descriptor.storage_type,
+                "component `{}` is already registered with another storage type", descriptor.name);
+            return descriptor.id.clone();
+        }
+        let component_id = self.add_component::<T>();
+        self.descriptors[component_id.id()].storage_type = storage_type;
+        component_id
+    }
+
+    /// Registers `T` if needed and lets `World::snapshot` clone it,
+    /// `PartialEq` finds the changed values in `Snapshot::diff`
+    pub fn register_cloneable<T: Component + Clone + PartialEq>(&mut self) -> ComponentId {
+        let component_id = self.add_component::<T>();
+        self.set_cloneable::<T>(&component_id);
+        component_id
+    }
+
+    /// `register_cloneable` for resources
+    pub fn register_cloneable_resource<T: Resource + Clone + PartialEq>(&mut self) -> ComponentId {
+        let resource_id = self.add_resource::<T>();
+        self.set_cloneable::<T>(&resource_id);
+        resource_id
+    }
+
+    fn set_cloneable<T: Clone + PartialEq>(&mut self, component_id: &ComponentId) {
+        let descriptor = &mut self.descriptors[component_id.id()];
+        descriptor.clone = Some(clone_ptr::<T>);
+        descriptor.eq = Some(eq_ptr::<T>);
+    }
+
+    /// Registers `T` if needed and includes it in `World::checksum`
+    pub fn register_stable_hash<T: Component + StableHash>(&mut self) -> ComponentId {
+        let component_id = self.add_component::<T>();
+        self.descriptors[component_id.id()].stable_hash = Some(stable_hash_ptr::<T>);
+        component_id
+    }
+
+    pub fn register_stable_hash_resource<T: Resource + StableHash>(&mut self) -> ComponentId {
+        let resource_id = self.add_resource::<T>();
+        self.descriptors[resource_id.id()].stable_hash = Some(stable_hash_ptr::<T>);
+        resource_id
+    }
+
+    /// Registers `T` if needed and remaps its `Entity` handles in `World::move_entities_from`
+    pub fn register_map_entities<T: Component + MapEntities>(&mut self) -> ComponentId {
+        let component_id = self.add_component::<T>();
+        self.descriptors[component_id.id()].map_entities = Some(map_entities_ptr::<T>);
+        component_id
+    }
+
+    /// Registers `T` if needed, the hooks are shared by every entity
+    pub fn hooks_mut<T: Component>(&mut self) -> &mut ComponentHooks {
+        let component_id = self.add_component::<T>();
+        &mut self.descriptors[component_id.id()].hooks
+    }
+
+    /// Id of the component described by `descriptor`, which comes from another `Components`
+    /// Components are matched by type, or by name for dynamic ones, and registered with
+    /// the storage type and functions of `descriptor` if they are missing
+    ///
+    /// # Panics
+    /// if a dynamic component with the same name has another layout
+    pub fn register_foreign(&mut self, descriptor: &ComponentDescriptor) -> ComponentId {
+        let existing = match descriptor.typeid {
+            Some(typeid) => self.indices.get(&typeid),
+            None => self.dynamic_indices.get(&descriptor.name),
+        };
+        if let Some(index) = existing {
+            assert_eq!(descriptor.layout, self.descriptors[*index].layout,
+                "component `{}` is registered with another layout", descriptor.name);
+            return ComponentId(*index);
+        }
+
+        let component_id = ComponentId(self.descriptors.len());
+        match descriptor.typeid {
+            Some(typeid) => self.indices.insert(typeid, component_id.id()),
+            None => self.dynamic_indices.insert(descriptor.name.clone(), component_id.id()),
+        };
+        self.descriptors.push(ComponentDescriptor {
+            bitmask: ComponentDescriptor::bitmask_of(&component_id),
+            id: component_id.clone(),
+            ..descriptor.clone()
+        });
+        component_id
+    }
+
+    /// Registered components and resources, indexed by `ComponentId`
+    #[inline]
+    pub fn descriptors(&self) -> &[ComponentDescriptor] {
+        &self.descriptors
+    }
+
+    /// Registers a component defined at runtime (scripts, asset files), returns the existing id
+    /// if `name` is already registered
+    ///
+    /// # Panics
+    /// if `name` is already registered with another layout
+    pub fn register_dynamic(&mut self, name: &str, layout: Layout, drop: Option<unsafe fn(*mut u8)>) -> ComponentId {
+        if let Some(index) = self.dynamic_indices.get(name) {
+            assert_eq!(layout, self.descriptors[*index].layout, "dynamic component `{}` registered with another layout", name);
+            return ComponentId(*index);
+        }
+        let index = self.descriptors.len();
+        self.descriptors.push(ComponentDescriptor::dynamic(ComponentId(index), name, layout, drop));
+        self.dynamic_indices.insert(name.to_string(), index);
+        ComponentId(index)
+    }
+
+    #[inline]
+    pub fn get_dynamic_id(&self, name: &str) -> Option<ComponentId> {
+        self.dynamic_indices.get(name).map(|index| ComponentId(*index))
+    }
+
+    #[inline]
+    pub fn get_descriptor(&self, component_id: &ComponentId) -> Option<&ComponentDescriptor> {
+        self.descriptors.get(component_id.id())
+    }
+
+    #[inline]
+    pub fn get_component_id<T: Component>(&self) -> Option<ComponentId> {
+        self.indices.get(&TypeId::of::<T>()).map(|index| ComponentId(*index))
+    }
+
     #[inline]
     pub fn get_component<T: Component>(&self) -> Option<&ComponentDescriptor> {
         let typeid = TypeId::of::<T>();
@@ -90,7 +356,7 @@ impl Components {
     pub fn add_resource<T: Resource>(&mut self) -> ComponentId {
         let typeid = TypeId::of::<T>();
         let index = self.descriptors.len();
-        self.resource_indices.entry(typeid).or_insert_with(|| {
+        let index = *self.resource_indices.entry(typeid).or_insert_with(|| {
             self.descriptors.push(ComponentDescriptor::of::<T>(ComponentId(index)));
             index
         });
@@ -110,8 +376,27 @@ impl Components {
 mod tests {
     use std::ops::{BitAnd, BitOr};
 
+    use std::alloc::Layout;
+
     use fixedbitset::FixedBitSet;
 
+    use super::Components;
+
+    #[test]
+    fn more_components_than_a_bitmask_block() {
+        let mut components = Components::new();
+        let mut ids: Vec<_> = (0..70)
+            .map(|i| components.register_dynamic(&format!("dynamic_{}", i), Layout::new::<u32>(), None))
+            .collect();
+        ids.push(components.add_component::<u32>());
+        ids.push(components.add_resource::<u64>());
+
+        for id in ids.iter() {
+            let descriptor = components.get_descriptor(id).unwrap();
+            assert!(descriptor.bitmask.contains(id.id() + 1));
+            assert_eq!(1, descriptor.bitmask.count_ones(..));
+        }
+    }
 
     #[test]
     fn fixedbitset() {
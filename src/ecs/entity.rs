@@ -1,23 +1,50 @@
-
-#[derive(Clone, Hash, PartialEq, Eq, PartialOrd, Ord)]
+/// Handle of an entity
+/// `id` indexes into `Entities`, `generation` tells apart handles of despawned entities
+/// whose index got reused
+#[derive(Debug, Clone, Copy, Hash, PartialEq, Eq, PartialOrd, Ord)]
 pub struct Entity {
-    pub id: usize, // Which Row
-    pub table_id: usize, // Which Table
+    pub id: usize,
+    pub generation: u32,
 }
 
 impl Entity {
+    /// Handle that is never alive, stands in for handles that cannot be remapped
+    pub const DANGLING: Entity = Entity { id: usize::MAX, generation: u32::MAX };
+
     #[inline]
-    pub fn new(id: usize, table_id: usize) -> Self {
+    pub fn new(id: usize, generation: u32) -> Self {
         Entity {
             id,
-            table_id,
+            generation,
         }
     }
 }
 
+/// Where the components of an entity are stored
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct EntityLocation {
+    pub table_id: usize, // Which Table
+    pub row: usize, // Which Row
+}
+
+#[derive(Debug, Clone, Default)]
+struct EntityMeta {
+    generation: u32,
+    location: Option<EntityLocation>, // None if the entity is despawned
+}
+
+/// Generations and free list of `Entities`, which decide the handles of the next spawns
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AllocatorState {
+    generations: Vec<u32>,
+    free: Vec<usize>,
+}
+
 #[derive(Default)]
 pub struct Entities {
-    entity_vec: Vec<Entity>,   
+    meta: Vec<EntityMeta>,
+    free: Vec<usize>,
+    len: usize,
 }
 
 impl Entities {
@@ -26,19 +53,133 @@ impl Entities {
         Default::default()
     }
 
+    /// Reserves a handle, the location should be set before the entity is used
+    pub fn alloc(&mut self) -> Entity {
+        self.len += 1;
+        match self.free.pop() {
+            Some(id) => Entity::new(id, self.meta[id].generation),
+            None => {
+                self.meta.push(EntityMeta::default());
+                Entity::new(self.meta.len() - 1, 0)
+            }
+        }
+    }
+
+    /// Makes the exact handle alive again, used to bring back despawned entities
+    /// The location should be set before the entity is used
+    ///
+    /// # Panics
+    /// if an entity with the same id is alive
+    pub fn alloc_at(&mut self, entity: Entity) {
+        if entity.id >= self.meta.len() {
+            self.free.extend(self.meta.len()..entity.id);
+            self.meta.resize(entity.id + 1, EntityMeta::default());
+        }
+        else {
+            assert!(self.free.contains(&entity.id),
+                "Entity id {} is alive", entity.id);
+            self.free.retain(|id| *id != entity.id);
+        }
+        self.meta[entity.id].generation = entity.generation;
+        self.len += 1;
+    }
+
+    /// Releases the handle, returns the last location of the entity
+    pub fn free(&mut self, entity: Entity) -> Option<EntityLocation> {
+        let meta = self.meta.get_mut(entity.id)?;
+        if meta.generation != entity.generation {
+            return None;
+        }
+        meta.generation += 1;
+        self.free.push(entity.id);
+        self.len -= 1;
+        meta.location.take()
+    }
+
+    #[inline]
+    pub fn contains(&self, entity: Entity) -> bool {
+        self.meta.get(entity.id)
+            .map(|meta| meta.generation == entity.generation)
+            .unwrap_or(false)
+    }
+
+    #[inline]
+    pub fn get(&self, entity: Entity) -> Option<EntityLocation> {
+        let meta = self.meta.get(entity.id)?;
+        if meta.generation != entity.generation {
+            return None;
+        }
+        meta.location
+    }
+
+    /// # Safety
+    /// `entity` should be alive and `location` should point to its row
     #[inline]
-    pub fn add_entity(&mut self, id: usize, table_id: usize) -> usize {
-        self.entity_vec.push(Entity::new(id, table_id));
-        self.entity_vec.len() - 1
+    pub unsafe fn set_location(&mut self, entity: Entity, location: EntityLocation) {
+        self.meta.get_unchecked_mut(entity.id).location = Some(location);
     }
 
+    /// Number of alive entities
     #[inline]
-    pub fn get_entity(&self, index: usize) -> Option<&Entity> {
-        self.entity_vec.get(index)
+    pub fn len(&self) -> usize {
+        self.len
     }
 
     #[inline]
-    pub fn get_entity_mut(&mut self, index: usize) -> Option<&mut Entity> {
-        self.entity_vec.get_mut(index)
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Alive entities in id order
+    pub fn iter(&self) -> impl Iterator<Item = Entity> + '_ {
+        self.meta.iter()
+            .enumerate()
+            .filter(|(_, meta)| meta.location.is_some())
+            .map(|(id, meta)| Entity::new(id, meta.generation))
+    }
+
+    pub fn allocator_state(&self) -> AllocatorState {
+        AllocatorState {
+            generations: self.meta.iter().map(|meta| meta.generation).collect(),
+            free: self.free.clone(),
+        }
     }
-}
\ No newline at end of file
+
+    /// Brings back the generations and free list of `state`, so that the next spawns get the same
+    /// handles as after `state` was taken
+    ///
+    /// # Panics
+    /// if the alive entities are not the ones alive when `state` was taken
+    pub fn set_allocator_state(&mut self, state: &AllocatorState) {
+        assert!(self.meta.iter().skip(state.generations.len()).all(|meta| meta.location.is_none()),
+            "Entities spawned after the allocator state are alive");
+        self.meta.resize(state.generations.len(), EntityMeta::default());
+        for (meta, generation) in self.meta.iter_mut().zip(state.generations.iter()) {
+            assert!(meta.location.is_none() || meta.generation == *generation,
+                "Entities alive with another generation than in the allocator state");
+            meta.generation = *generation;
+        }
+        self.free = state.free.clone();
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::{Entities, EntityLocation};
+
+    #[test]
+    fn reused_id_has_new_generation() {
+        let mut entities = Entities::new();
+        let e0 = entities.alloc();
+        unsafe { entities.set_location(e0, EntityLocation { table_id: 0, row: 0 }); }
+        assert_eq!(Some(EntityLocation { table_id: 0, row: 0 }), entities.free(e0));
+        assert_eq!(None, entities.free(e0));
+
+        let e1 = entities.alloc();
+        assert_eq!(e0.id, e1.id);
+        assert_ne!(e0.generation, e1.generation);
+        assert!(!entities.contains(e0));
+        assert!(entities.contains(e1));
+    }
+}
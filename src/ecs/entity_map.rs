@@ -0,0 +1,191 @@
+use std::collections::BTreeMap;
+
+use super::{World, component::{ComponentId, StorageType}, entity::{Entity, EntityLocation}};
+
+
+/// Handles of moved entities in the source world to their handles in the destination world
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct EntityMap {
+    map: BTreeMap<Entity, Entity>,
+}
+
+impl EntityMap {
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    pub fn insert(&mut self, from: Entity, to: Entity) {
+        self.map.insert(from, to);
+    }
+
+    pub fn get(&self, from: Entity) -> Option<Entity> {
+        self.map.get(&from).copied()
+    }
+
+    /// Destination handle of `from`, `Entity::DANGLING` if it was not moved
+    pub fn map(&self, from: Entity) -> Entity {
+        self.get(from).unwrap_or(Entity::DANGLING)
+    }
+
+    pub fn len(&self) -> usize {
+        self.map.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.map.is_empty()
+    }
+
+    /// Pairs in source handle order
+    pub fn iter(&self) -> impl Iterator<Item = (Entity, Entity)> + '_ {
+        self.map.iter().map(|(from, to)| (*from, *to))
+    }
+}
+
+/// Components holding `Entity` handles, remapped when their entity moves to another world
+/// Registered with `World::register_map_entities`, handles to entities that did not move
+/// become `Entity::DANGLING`
+pub trait MapEntities {
+    fn map_entities(&mut self, entity_map: &EntityMap);
+}
+
+/// # Safety
+/// `ptr` should point to a valid `T`
+pub unsafe fn map_entities_ptr<T: MapEntities>(ptr: *mut u8, entity_map: &EntityMap) {
+    (*ptr.cast::<T>()).map_entities(entity_map);
+}
+
+pub type MapEntitiesFn = unsafe fn(*mut u8, &EntityMap);
+
+/// See `World::move_entities_from`
+pub(crate) fn move_entities(dst: &mut World, src: &mut World, entities: &[Entity]) -> EntityMap {
+    let mut entity_map = EntityMap::new();
+    for entity in entities {
+        assert!(src.contains_entity(*entity) && entity_map.get(*entity).is_none(),
+            "Entity {:?} does not exist or is listed twice", entity);
+        entity_map.insert(*entity, dst.entities.alloc());
+    }
+
+    // hooks change `src`, they all run before the values are read
+    for entity in entities {
+        for component_id in component_ids(src, *entity).iter() {
+            let on_move = src.components.get_descriptor(component_id).unwrap().hooks.on_move;
+            // hooks can remove components
+            if let (Some(on_move), true) = (on_move, src.get_by_id(*entity, component_id).is_some()) {
+                on_move(src, *entity, component_id.clone());
+            }
+        }
+    }
+
+    for entity in entities {
+        let new_entity = entity_map.map(*entity);
+        let src_ids = component_ids(src, *entity);
+
+        // destination ids and values, the values stay in `src` until the entity is forgotten there
+        let mut values: Vec<(ComponentId, *mut u8)> = Vec::with_capacity(src_ids.len());
+        for src_id in src_ids.iter() {
+            let value = src.get_mut_by_id(*entity, src_id).unwrap();
+            let descriptor = src.components.get_descriptor(src_id).unwrap();
+            if let Some(map_entities) = descriptor.map_entities {
+                unsafe {
+                    map_entities(value, &entity_map);
+                }
+            }
+            values.push((dst.components.register_foreign(descriptor), value));
+        }
+
+        let mut table_ids: Vec<ComponentId> = values.iter()
+            .map(|(component_id, _)| component_id.clone())
+            .filter(|component_id| dst.components.get_descriptor(component_id).unwrap().storage_type == StorageType::Table)
+            .collect();
+        table_ids.sort();
+        let table_id = dst.get_or_insert_table(&table_ids);
+        let row = dst.tables.get_table_mut(table_id).unwrap().add_row(new_entity);
+        for (component_id, value) in values.iter().cloned() {
+            let descriptor = dst.components.get_descriptor(&component_id).unwrap();
+            unsafe {
+                match descriptor.storage_type {
+                    StorageType::Table => dst.tables.get_table_mut(table_id).unwrap()
+                        .get_column_mut(&component_id).unwrap()
+                        .init_unchecked(row, value),
+                    StorageType::SparseSet => dst.sparse_sets.get_or_insert(descriptor).insert(new_entity, value),
+                }
+            }
+        }
+        unsafe {
+            dst.entities.set_location(new_entity, EntityLocation { table_id, row });
+            src.despawn_forget(*entity);
+        }
+        for (component_id, _) in values.iter() {
+            dst.set_changed_by_id(new_entity, component_id);
+        }
+    }
+    entity_map
+}
+
+fn component_ids(world: &World, entity: Entity) -> Vec<ComponentId> {
+    let location = world.entities.get(entity)
+        .unwrap_or_else(|| panic!("Entity {:?} was despawned by an on_move hook", entity));
+    let mut component_ids = world.tables.get_table(location.table_id).unwrap().component_ids();
+    component_ids.extend(world.sparse_sets.iter()
+        .filter(|(_, sparse_set)| sparse_set.contains(entity))
+        .map(|(component_id, _)| component_id.clone()));
+    component_ids
+}
+
+
+#[cfg(test)]
+mod tests {
+    use crate::ecs::World;
+    use crate::ecs::component::StorageType;
+    use crate::hierarchy::{BuildWorldChildren, Children, Parent};
+
+    use super::{EntityMap, MapEntities};
+
+    #[derive(Debug, PartialEq)]
+    struct Name(String);
+    #[derive(Debug, PartialEq)]
+    struct Target(crate::ecs::entity::Entity);
+    #[derive(Debug, PartialEq)]
+    struct Selected;
+
+    impl MapEntities for Target {
+        fn map_entities(&mut self, entity_map: &EntityMap) {
+            self.0 = entity_map.map(self.0);
+        }
+    }
+
+    #[test]
+    fn append_remaps_handles_and_ids() {
+        let mut live = World::new();
+        live.register_dynamic("Tag", std::alloc::Layout::new::<u8>(), None);
+        let existing = live.spawn().insert(Name("existing".to_string())).id();
+
+        let mut scratch = World::new();
+        scratch.add_component_with_storage::<Selected>(StorageType::SparseSet);
+        scratch.register_map_entities::<Target>();
+        let child = scratch.spawn().insert(Name("child".to_string())).id();
+        let root = scratch.spawn().insert(Name("root".to_string())).insert(Selected).push_children(&[child]).id();
+        let other = scratch.spawn().insert(Target(root)).id();
+        let left = scratch.spawn().insert(Target(other)).id();
+
+        let moved = live.move_entities_from(&mut scratch, &[root, child, other]);
+        assert_eq!(vec![child, root, other], moved.iter().map(|(from, _)| from).collect::<Vec<_>>());
+        assert_eq!(1, scratch.get_entities().len());
+        assert!(!scratch.contains_entity(other));
+        // handles left behind are not remapped
+        assert_eq!(Some(&Target(other)), scratch.get::<Target>(left));
+
+        let (root, child, other) = (moved.map(root), moved.map(child), moved.map(other));
+        assert_eq!(Some(&Name("root".to_string())), live.get::<Name>(root));
+        assert_eq!(Some(&Selected), live.get::<Selected>(root));
+        assert_eq!(Some(&Parent(root)), live.get::<Parent>(child));
+        assert_eq!(&[child], &**live.get::<Children>(root).unwrap());
+        assert_eq!(Some(&Target(root)), live.get::<Target>(other));
+        assert_eq!(Some(&Name("existing".to_string())), live.get::<Name>(existing));
+
+        let appended = live.append(&mut scratch);
+        assert!(scratch.get_entities().is_empty());
+        assert_eq!(5, live.get_entities().len());
+        assert_eq!(Some(&Target(crate::ecs::entity::Entity::DANGLING)), live.get::<Target>(appended.map(left)));
+    }
+}
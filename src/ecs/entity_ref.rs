@@ -0,0 +1,167 @@
+use super::{World, entity::Entity, component::Component};
+
+
+/// Read access to the components of a single entity
+pub struct EntityRef<'w> {
+    world: &'w World,
+    entity: Entity,
+}
+
+impl<'w> EntityRef<'w> {
+    pub(crate) fn new(world: &'w World, entity: Entity) -> Self {
+        EntityRef {
+            world,
+            entity,
+        }
+    }
+
+    pub fn id(&self) -> Entity {
+        self.entity
+    }
+
+    pub fn world(&self) -> &'w World {
+        self.world
+    }
+
+    pub fn get<T: Component>(&self) -> Option<&'w T> {
+        self.world.get::<T>(self.entity)
+    }
+
+    pub fn contains<T: Component>(&self) -> bool {
+        self.get::<T>().is_some()
+    }
+}
+
+/// Mutable access to the components of a single entity
+///
+/// `world.spawn().insert(Health(100)).insert(Name("player")).id()`
+pub struct EntityMut<'w> {
+    world: &'w mut World,
+    entity: Entity,
+}
+
+impl<'w> EntityMut<'w> {
+    pub(crate) fn new(world: &'w mut World, entity: Entity) -> Self {
+        EntityMut {
+            world,
+            entity,
+        }
+    }
+
+    pub fn id(&self) -> Entity {
+        self.entity
+    }
+
+    pub fn world(&self) -> &World {
+        self.world
+    }
+
+    pub fn world_mut(&mut self) -> &mut World {
+        self.world
+    }
+
+    pub fn get<T: Component>(&self) -> Option<&T> {
+        self.world.get::<T>(self.entity)
+    }
+
+    pub fn get_mut<T: Component>(&mut self) -> Option<&mut T> {
+        self.world.get_mut::<T>(self.entity)
+    }
+
+    pub fn contains<T: Component>(&self) -> bool {
+        self.get::<T>().is_some()
+    }
+
+    /// Adds the component, replaces the old value if the entity already has one
+    pub fn insert<T: Component>(&mut self, value: T) -> &mut Self {
+        let component_id = self.world.add_component::<T>();
+        let mut value = std::mem::ManuallyDrop::new(value);
+        unsafe {
+            self.world.insert_by_id(self.entity, &component_id, (&mut *value as *mut T).cast::<u8>());
+        }
+        self
+    }
+
+    pub fn remove<T: Component>(&mut self) -> Option<T> {
+        let component_id = self.world.get_components().get_component_id::<T>()?;
+        self.world.get_by_id(self.entity, &component_id)?;
+        self.world.run_remove_hook(self.entity, &component_id);
+        // read without `get_mut_by_id`, a removal is not a change
+        let value = unsafe {
+            self.world.get_by_id(self.entity, &component_id)?
+                .cast::<T>()
+                .read()
+        };
+        unsafe {
+            self.world.remove_by_id_unchecked(self.entity, &component_id, false);
+        }
+        Some(value)
+    }
+
+    pub fn despawn(self) {
+        self.world.despawn(self.entity);
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use std::rc::Rc;
+
+    use crate::ecs::World;
+
+    #[derive(Debug, PartialEq)]
+    struct Health(u32);
+    #[derive(Debug, PartialEq)]
+    struct Name(String);
+    struct Marker;
+
+    #[test]
+    fn insert_get_remove() {
+        let mut world = World::new();
+        let a = world.spawn().insert(Health(10)).insert(Name("a".to_string())).id();
+        let b = world.spawn().insert(Health(20)).insert(Marker).id();
+        let c = world.spawn().insert(Name("c".to_string())).insert(Health(30)).id();
+
+        assert_eq!(Some(&Health(10)), world.get::<Health>(a));
+        assert_eq!(Some(&Name("c".to_string())), world.get::<Name>(c));
+        assert!(world.entity(b).contains::<Marker>());
+        assert!(!world.entity(b).contains::<Name>());
+
+        world.get_mut::<Health>(b).unwrap().0 += 1;
+        assert_eq!(Some(Health(21)), world.entity_mut(b).remove::<Health>());
+        assert_eq!(None, world.get::<Health>(b));
+        assert!(world.entity(b).contains::<Marker>());
+
+        assert_eq!(Some(Name("a".to_string())), world.entity_mut(a).remove::<Name>());
+        assert_eq!(Some(&Health(10)), world.get::<Health>(a));
+        assert_eq!(Some(&Health(30)), world.get::<Health>(c));
+        assert_eq!(Some(&Name("c".to_string())), world.get::<Name>(c));
+    }
+
+    #[test]
+    fn despawn_drops_components() {
+        let counter = Rc::new(());
+        struct Shared(#[allow(dead_code)] Rc<()>);
+        unsafe impl Send for Shared {}
+        unsafe impl Sync for Shared {}
+
+        let mut world = World::new();
+        let a = world.spawn().insert(Shared(counter.clone())).id();
+        let b = world.spawn().insert(Shared(counter.clone())).insert(Health(2)).id();
+        let c = world.spawn().insert(Shared(counter.clone())).id();
+        assert_eq!(4, Rc::strong_count(&counter));
+
+        assert!(world.despawn(a));
+        assert!(!world.despawn(a));
+        assert_eq!(3, Rc::strong_count(&counter));
+        assert!(world.get::<Shared>(c).is_some());
+
+        world.entity_mut(b).insert(Shared(counter.clone()));
+        assert_eq!(3, Rc::strong_count(&counter));
+
+        drop(world);
+        assert_eq!(1, Rc::strong_count(&counter));
+        let _ = (b, c);
+    }
+}
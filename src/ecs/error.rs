@@ -5,4 +5,69 @@ pub enum StorageFault {
     ComponentNotRegistered,
     NoComponentOnEntity,
     ComponentUninitOnEntity,
-}
\ No newline at end of file
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SceneErrorKind {
+    Io(String),
+    Syntax(String),
+    InvalidFormat(String),
+    UnknownComponent(String),
+    NotAColumn(String),
+    MissingValue(String),
+    BadValue { component: String, message: String },
+}
+
+/// Error of loading a scene file, `line` is 1-based, 0 if not related to a line
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SceneError {
+    pub line: usize,
+    pub kind: SceneErrorKind,
+}
+
+impl SceneError {
+    pub fn new(line: usize, kind: SceneErrorKind) -> Self {
+        SceneError {
+            line,
+            kind,
+        }
+    }
+}
+
+impl std::fmt::Display for SceneError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "line {}: ", self.line)?;
+        match &self.kind {
+            SceneErrorKind::Io(message) => write!(f, "io error: {}", message),
+            SceneErrorKind::Syntax(message) => write!(f, "syntax error: {}", message),
+            SceneErrorKind::InvalidFormat(message) => write!(f, "invalid format: {}", message),
+            SceneErrorKind::UnknownComponent(name) => write!(f, "unknown component `{}`", name),
+            SceneErrorKind::NotAColumn(name) => write!(f, "component `{}` is not listed in `components`", name),
+            SceneErrorKind::MissingValue(name) => write!(f, "no value or default for component `{}`", name),
+            SceneErrorKind::BadValue { component, message } => write!(f, "bad value for component `{}`: {}", component, message),
+        }
+    }
+}
+
+impl std::error::Error for SceneError {}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ReflectError {
+    /// `depth` is the index of the first path segment that is not a field
+    NoField { path: String, depth: usize },
+    TypeMismatch { path: String, expected: &'static str, found: &'static str },
+}
+
+impl std::fmt::Display for ReflectError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ReflectError::NoField { path, depth } => {
+                let field = path.split('.').nth(*depth).unwrap_or_default();
+                write!(f, "no field `{}` in path `{}`", field, path)
+            },
+            ReflectError::TypeMismatch { path, expected, found } => write!(f, "`{}` is a `{}`, not a `{}`", path, found, expected),
+        }
+    }
+}
+
+impl std::error::Error for ReflectError {}
@@ -1,17 +1,31 @@
 
-use self::storage::{resource_table::ResourceTable, table::Tables};
-use self::entity::Entities;
-use self::component::{Components, ComponentId, Component, Resource};
+use std::alloc::Layout;
+
+use self::storage::{resource_table::ResourceTable, table::Tables, sparse_set::SparseSets};
+use self::entity::{Entities, Entity, EntityLocation};
+use self::entity_ref::{EntityRef, EntityMut};
+use self::component::{Components, ComponentId, ComponentDescriptor, Component, Resource, StorageType};
+use self::bundle::Bundle;
+use self::removal_detection::RemovedComponentEvents;
 
 
 pub mod error;
 pub mod storage;
 pub mod entity;
+pub mod entity_ref;
 pub mod component;
+pub mod bundle;
 pub mod system;
 pub mod query;
 pub mod event;
 pub mod util;
+pub mod registry;
+pub mod reflect;
+pub mod stats;
+pub mod snapshot;
+pub mod stable_hash;
+pub mod entity_map;
+pub mod removal_detection;
 
 
 pub struct World {
@@ -19,8 +33,15 @@ pub struct World {
     components: Components,
     resources: ResourceTable,
     tables: Tables,
+    sparse_sets: SparseSets,
+    removed_components: RemovedComponentEvents,
+    change_tick: u32,
 }
 
+// components and resources are Send + Sync, the storages only hold them behind raw pointers
+unsafe impl Send for World {}
+unsafe impl Sync for World {}
+
 impl World {
     pub fn new() -> Self {
         World {
@@ -28,6 +49,10 @@ impl World {
             components: Components::new(),
             resources: ResourceTable::new(),
             tables: Tables::new(),
+            sparse_sets: SparseSets::new(),
+            removed_components: RemovedComponentEvents::new(),
+            // values stamped with the first tick are newer than a reader that never ran (tick 0)
+            change_tick: 1,
         }
     }
 
@@ -35,10 +60,616 @@ impl World {
         self.components.add_component::<T>()
     }
 
+    /// See `Components::add_component_with_storage`
+    pub fn add_component_with_storage<T: Component>(&mut self, storage_type: StorageType) -> ComponentId {
+        self.components.add_component_with_storage::<T>(storage_type)
+    }
+
     pub fn add_resource<T: Resource>(&mut self) -> ComponentId {
         self.components.add_resource::<T>()
     }
 
+    /// See `Components::register_cloneable`
+    pub fn register_cloneable<T: Component + Clone + PartialEq>(&mut self) -> ComponentId {
+        self.components.register_cloneable::<T>()
+    }
+
+    pub fn register_cloneable_resource<T: Resource + Clone + PartialEq>(&mut self) -> ComponentId {
+        self.components.register_cloneable_resource::<T>()
+    }
+
+    /// See `Components::register_stable_hash`
+    pub fn register_stable_hash<T: Component + stable_hash::StableHash>(&mut self) -> ComponentId {
+        self.components.register_stable_hash::<T>()
+    }
+
+    pub fn register_stable_hash_resource<T: Resource + stable_hash::StableHash>(&mut self) -> ComponentId {
+        self.components.register_stable_hash_resource::<T>()
+    }
+
+    /// See `Components::register_dynamic`
+    pub fn register_dynamic(&mut self, name: &str, layout: Layout, drop: Option<unsafe fn(*mut u8)>) -> ComponentId {
+        self.components.register_dynamic(name, layout, drop)
+    }
+
+    /// Spawns an entity without components
+    pub fn spawn(&mut self) -> EntityMut<'_> {
+        let entity = self.entities.alloc();
+        self.add_to_empty_table(entity);
+        EntityMut::new(self, entity)
+    }
+
+    /// Gives a location to an allocated entity without components
+    fn add_to_empty_table(&mut self, entity: Entity) {
+        let table_id = self.tables.get_or_insert(&[]);
+        let row = self.tables.get_table_mut(table_id).unwrap()
+                .add_row(entity);
+        unsafe {
+            self.entities.set_location(entity, EntityLocation { table_id, row });
+        }
+    }
+
+    /// Spawns an entity per bundle, returns the handles in iteration order
+    ///
+    /// Rows are reserved once and the table columns are filled with one copy per component
+    /// of each bundle (a single copy of the whole batch for single component bundles),
+    /// instead of moving the entity to a new table per inserted component
+    ///
+    /// # Panics
+    /// if a component appears twice in the bundle
+    pub fn spawn_batch<B: Bundle, I: IntoIterator<Item = B>>(&mut self, bundles: I) -> Vec<Entity> {
+        let component_ids = B::component_ids(&mut self.components);
+        let mut sorted_ids = component_ids.clone();
+        sorted_ids.sort();
+        assert!(sorted_ids.windows(2).all(|pair| pair[0] != pair[1]),
+            "Bundle {} contains a component twice", std::any::type_name::<B>());
+
+        let fields: Vec<(ComponentId, usize, StorageType)> = component_ids.into_iter()
+                .zip(B::field_offsets())
+                .map(|(id, offset)| {
+                    let storage_type = self.components.get_descriptor(&id).unwrap().storage_type;
+                    (id, offset, storage_type)
+                })
+                .collect();
+        sorted_ids.retain(|id| fields.iter().any(|(field_id, _, storage_type)| field_id == id && *storage_type == StorageType::Table));
+        let table_id = self.get_or_insert_table(&sorted_ids);
+
+        let mut bundles: Vec<B> = bundles.into_iter().collect();
+        let count = bundles.len();
+        if count == 0 {
+            return Vec::new();
+        }
+        let entities: Vec<Entity> = (0..count).map(|_| self.entities.alloc()).collect();
+
+        let table = self.tables.get_table_mut(table_id).unwrap();
+        let first_row = table.add_rows(&entities);
+        let stride = std::mem::size_of::<B>();
+        let src = bundles.as_mut_ptr().cast::<u8>();
+        unsafe {
+            for (component_id, offset, storage_type) in fields.iter() {
+                match storage_type {
+                    StorageType::Table => {
+                        let column = table.get_column_mut(component_id).unwrap();
+                        let size = column.item_size();
+                        if size == 0 {
+                            continue;
+                        }
+                        let dst = column.get_unchecked(first_row);
+                        if size == stride {
+                            std::ptr::copy_nonoverlapping(src, dst, size * count);
+                        }
+                        else {
+                            for i in 0..count {
+                                std::ptr::copy_nonoverlapping(src.add(i * stride + offset), dst.add(i * size), size);
+                            }
+                        }
+                    },
+                    StorageType::SparseSet => {
+                        let descriptor = self.components.get_descriptor(component_id).unwrap();
+                        let sparse_set = self.sparse_sets.get_or_insert(descriptor);
+                        for (i, entity) in entities.iter().enumerate() {
+                            sparse_set.insert(*entity, src.add(i * stride + offset));
+                        }
+                    },
+                }
+            }
+            // the components are owned by the world now
+            bundles.set_len(0);
+
+            for (component_id, _, storage_type) in fields.iter() {
+                match storage_type {
+                    StorageType::Table => table.get_column(component_id).unwrap()
+                        .set_changed_range(first_row..first_row + count, self.change_tick),
+                    StorageType::SparseSet => {
+                        let sparse_set = self.sparse_sets.get(component_id).unwrap();
+                        for entity in entities.iter() {
+                            sparse_set.set_changed(*entity, self.change_tick);
+                        }
+                    },
+                }
+            }
+
+            for (i, entity) in entities.iter().enumerate() {
+                self.entities.set_location(*entity, EntityLocation { table_id, row: first_row + i });
+            }
+        }
+        for (component_id, _, _) in fields.iter() {
+            if !self.components.get_descriptor(component_id).unwrap().hooks.is_empty() {
+                for entity in entities.iter() {
+                    self.run_insert_hooks(*entity, component_id, true);
+                }
+            }
+        }
+        entities
+    }
+
+    pub fn contains_entity(&self, entity: Entity) -> bool {
+        self.entities.get(entity).is_some()
+    }
+
+    pub fn entity(&self, entity: Entity) -> EntityRef<'_> {
+        self.get_entity(entity)
+            .unwrap_or_else(|| panic!("Entity {:?} does not exist", entity))
+    }
+
+    pub fn get_entity(&self, entity: Entity) -> Option<EntityRef<'_>> {
+        self.entities.get(entity)?;
+        Some(EntityRef::new(self, entity))
+    }
+
+    pub fn entity_mut(&mut self, entity: Entity) -> EntityMut<'_> {
+        self.get_entity_mut(entity)
+            .unwrap_or_else(|| panic!("Entity {:?} does not exist", entity))
+    }
+
+    pub fn get_entity_mut(&mut self, entity: Entity) -> Option<EntityMut<'_>> {
+        self.entities.get(entity)?;
+        Some(EntityMut::new(self, entity))
+    }
+
+    pub fn get<T: Component>(&self, entity: Entity) -> Option<&T> {
+        let component_id = self.components.get_component_id::<T>()?;
+        unsafe {
+            Some(&*self.get_by_id(entity, &component_id)?.cast::<T>())
+        }
+    }
+
+    /// Marks the component changed, see `World::change_tick`
+    pub fn get_mut<T: Component>(&mut self, entity: Entity) -> Option<&mut T> {
+        let component_id = self.components.get_component_id::<T>()?;
+        unsafe {
+            Some(&mut *self.get_mut_by_id(entity, &component_id)?.cast::<T>())
+        }
+    }
+
+    /// Tick stamped on a component value when it is inserted or mutably accessed,
+    /// through `get_mut` or a `&mut T` query
+    /// A reader remembers the tick of its last run and looks for values changed after it
+    ///
+    /// `SystemStage` moves to the next tick before each system, worlds used without an `App`
+    /// should call `increment_change_tick` between the runs of their systems
+    pub fn change_tick(&self) -> u32 {
+        self.change_tick
+    }
+
+    /// Returns the current tick and moves to the next one, values changed afterwards are newer
+    pub fn increment_change_tick(&mut self) -> u32 {
+        self.change_tick += 1;
+        self.change_tick - 1
+    }
+
+    /// True if `T` of the entity was inserted or mutably accessed after `tick`
+    pub fn is_changed_since<T: Component>(&self, entity: Entity, tick: u32) -> bool {
+        self.components.get_component_id::<T>()
+            .and_then(|component_id| self.changed_tick_by_id(entity, &component_id))
+            .map(|changed| changed > tick)
+            .unwrap_or(false)
+    }
+
+    /// Entities whose `T` was inserted or mutably accessed after `tick`, in storage order
+    /// Only the change ticks are read, the entities of unchanged values are not looked up
+    pub fn changed_since<T: Component>(&self, tick: u32) -> Vec<Entity> {
+        let component_id = match self.components.get_component_id::<T>() {
+            Some(component_id) => component_id,
+            None => return Vec::new(),
+        };
+        match self.components.get_descriptor(&component_id).unwrap().storage_type {
+            StorageType::Table => self.tables.iter()
+                .filter_map(|table| Some((table, table.get_column(&component_id)?)))
+                .flat_map(|(table, column)| table.entities().iter()
+                    .enumerate()
+                    .filter(move |(row, _)| column.changed_tick(*row) > tick)
+                    .map(|(_, entity)| *entity))
+                .collect(),
+            StorageType::SparseSet => self.sparse_sets.get(&component_id)
+                .map(|sparse_set| sparse_set.entities().iter()
+                    .enumerate()
+                    .filter(|(index, _)| sparse_set.column().changed_tick(*index) > tick)
+                    .map(|(_, entity)| *entity)
+                    .collect())
+                .unwrap_or_default(),
+        }
+    }
+
+    pub fn changed_tick_by_id(&self, entity: Entity, component_id: &ComponentId) -> Option<u32> {
+        let location = self.entities.get(entity)?;
+        match self.components.get_descriptor(component_id)?.storage_type {
+            StorageType::Table => Some(self.tables.get_table(location.table_id)?
+                .get_column(component_id)?
+                .changed_tick(location.row)),
+            StorageType::SparseSet => self.sparse_sets.get(component_id)?.changed_tick(entity),
+        }
+    }
+
+    /// Stamps the component of the entity with the current change tick
+    pub(crate) fn set_changed_by_id(&self, entity: Entity, component_id: &ComponentId) {
+        let location = match self.entities.get(entity) {
+            Some(location) => location,
+            None => return,
+        };
+        match self.components.get_descriptor(component_id).map(|descriptor| descriptor.storage_type) {
+            Some(StorageType::Table) => {
+                if let Some(column) = self.tables.get_table(location.table_id).and_then(|table| table.get_column(component_id)) {
+                    column.set_changed(location.row, self.change_tick);
+                }
+            },
+            Some(StorageType::SparseSet) => {
+                if let Some(sparse_set) = self.sparse_sets.get(component_id) {
+                    sparse_set.set_changed(entity, self.change_tick);
+                }
+            },
+            None => (),
+        }
+    }
+
+    /// Despawns the entity and drops its components, returns false if it does not exist
+    /// The `on_remove` hooks of the components run first, in component id order,
+    /// components removed by an earlier hook are skipped
+    pub fn despawn(&mut self, entity: Entity) -> bool {
+        let location = match self.entities.get(entity) {
+            Some(location) => location,
+            None => return false,
+        };
+        let mut component_ids = self.tables.get_table(location.table_id).unwrap().component_ids();
+        component_ids.extend(self.sparse_sets.iter()
+            .filter(|(_, sparse_set)| sparse_set.contains(entity))
+            .map(|(component_id, _)| component_id.clone()));
+        for component_id in component_ids.iter() {
+            // hooks can remove components or despawn the entity
+            if self.get_by_id(entity, component_id).is_some() {
+                self.run_remove_hook(entity, component_id);
+            }
+        }
+
+        let location = match self.entities.free(entity) {
+            Some(location) => location,
+            None => return true,
+        };
+        self.sparse_sets.remove_entity(entity);
+        unsafe {
+            let swapped_entity = self.tables.get_table_mut(location.table_id).unwrap()
+                    .swap_remove_and_drop_unchecked(location.row);
+            if let Some(swapped_entity) = swapped_entity {
+                self.entities.set_location(swapped_entity, location);
+            }
+        }
+        true
+    }
+
+    /// Despawns the entity without dropping its components, which were moved out
+    ///
+    /// # Safety
+    /// the entity should exist and the caller should own its component values
+    pub(crate) unsafe fn despawn_forget(&mut self, entity: Entity) {
+        let location = self.entities.free(entity).unwrap();
+        for (_, sparse_set) in self.sparse_sets.iter_mut() {
+            sparse_set.remove_and_forget(entity);
+        }
+        let swapped_entity = self.tables.get_table_mut(location.table_id).unwrap()
+                .swap_remove_and_forget_unchecked(location.row);
+        if let Some(swapped_entity) = swapped_entity {
+            self.entities.set_location(swapped_entity, location);
+        }
+    }
+
+    /// Pointer to the component of the entity, valid until the world is changed
+    pub fn get_by_id(&self, entity: Entity, component_id: &ComponentId) -> Option<*const u8> {
+        self.get_ptr(entity, component_id).map(|ptr| ptr as *const u8)
+    }
+
+    /// Marks the component changed, see `World::change_tick`
+    pub fn get_mut_by_id(&mut self, entity: Entity, component_id: &ComponentId) -> Option<*mut u8> {
+        let ptr = self.get_ptr(entity, component_id)?;
+        self.set_changed_by_id(entity, component_id);
+        Some(ptr)
+    }
+
+    fn get_ptr(&self, entity: Entity, component_id: &ComponentId) -> Option<*mut u8> {
+        let location = self.entities.get(entity)?;
+        match self.components.get_descriptor(component_id)?.storage_type {
+            StorageType::Table => {
+                let column = self.tables.get_table(location.table_id)?
+                        .get_column(component_id)?;
+                unsafe {
+                    Some(column.get_unchecked(location.row))
+                }
+            },
+            StorageType::SparseSet => self.sparse_sets.get(component_id)?.get(entity),
+        }
+    }
+
+    /// Moves `value` into the component of the entity, replaces the old value if there is one
+    ///
+    /// Returns false if the entity does not exist
+    ///
+    /// # Safety
+    /// - `component_id` should be registered in `Components`
+    /// - `value` should point to a valid value of the component type,
+    ///   the world owns it afterwards and the caller should forget it
+    pub unsafe fn insert_by_id(&mut self, entity: Entity, component_id: &ComponentId, value: *mut u8) -> bool {
+        let added = self.get_by_id(entity, component_id).is_none();
+        if !self.insert_by_id_without_hooks(entity, component_id, value) {
+            return false;
+        }
+        self.set_changed_by_id(entity, component_id);
+        self.run_insert_hooks(entity, component_id, added);
+        true
+    }
+
+    unsafe fn insert_by_id_without_hooks(&mut self, entity: Entity, component_id: &ComponentId, value: *mut u8) -> bool {
+        let location = match self.entities.get(entity) {
+            Some(location) => location,
+            None => return false,
+        };
+        let descriptor = self.components.get_descriptor(component_id).unwrap();
+        if descriptor.storage_type == StorageType::SparseSet {
+            self.sparse_sets.get_or_insert(descriptor).insert(entity, value);
+            return true;
+        }
+
+        let table = self.tables.get_table_mut(location.table_id).unwrap();
+        if let Some(column) = table.get_column_mut(component_id) {
+            column.replace_unchecked(location.row, value);
+            return true;
+        }
+
+        let mut component_ids = table.component_ids();
+        component_ids.push(component_id.clone());
+        let dst_table_id = self.get_or_insert_table(&component_ids);
+
+        let (src_table, dst_table) = self.tables.get_two_mut(location.table_id, dst_table_id);
+        let result = src_table.move_row_to_superset_unchecked(location.row, dst_table);
+        dst_table.get_column_mut(component_id).unwrap()
+                .init_unchecked(result.moved_row, value);
+
+        self.update_moved_locations(entity, location, dst_table_id, result.moved_row, result.swapped_entity);
+        true
+    }
+
+    /// Drops the component of the entity, returns false if the entity does not have it
+    pub fn remove_by_id(&mut self, entity: Entity, component_id: &ComponentId) -> bool {
+        if self.get_by_id(entity, component_id).is_none() {
+            return false;
+        }
+        self.run_remove_hook(entity, component_id);
+        // the hook can remove it itself
+        if self.get_by_id(entity, component_id).is_some() {
+            unsafe {
+                self.remove_by_id_unchecked(entity, component_id, true);
+            }
+        }
+        true
+    }
+
+    /// Runs the `on_add` and `on_insert` hooks after a value was inserted,
+    /// `added` if the entity did not have the component before
+    fn run_insert_hooks(&mut self, entity: Entity, component_id: &ComponentId, added: bool) {
+        let hooks = self.components.get_descriptor(component_id).unwrap().hooks;
+        if let (true, Some(on_add)) = (added, hooks.on_add) {
+            on_add(self, entity, component_id.clone());
+        }
+        if let Some(on_insert) = hooks.on_insert {
+            on_insert(self, entity, component_id.clone());
+        }
+    }
+
+    /// Runs the `on_remove` hook and records the removal for `RemovedComponents`,
+    /// before the value of the component is removed
+    pub(crate) fn run_remove_hook(&mut self, entity: Entity, component_id: &ComponentId) {
+        self.removed_components.send(component_id, entity);
+        if let Some(on_remove) = self.components.get_descriptor(component_id).unwrap().hooks.on_remove {
+            on_remove(self, entity, component_id.clone());
+        }
+    }
+
+    /// Moves the entity to the table without the component
+    /// The component value is dropped if `drop`, otherwise forgotten (caller should have read it)
+    ///
+    /// # Safety
+    /// the entity should have the component
+    pub(crate) unsafe fn remove_by_id_unchecked(&mut self, entity: Entity, component_id: &ComponentId, drop: bool) {
+        if self.components.get_descriptor(component_id).unwrap().storage_type == StorageType::SparseSet {
+            let sparse_set = self.sparse_sets.get_mut(component_id).unwrap();
+            if drop {
+                sparse_set.remove_and_drop(entity);
+            }
+            else {
+                sparse_set.remove_and_forget(entity);
+            }
+            return;
+        }
+
+        let location = self.entities.get(entity).unwrap();
+        let component_ids: Vec<ComponentId> = self.tables.get_table(location.table_id).unwrap()
+                .component_ids()
+                .into_iter()
+                .filter(|id| id != component_id)
+                .collect();
+        let dst_table_id = self.get_or_insert_table(&component_ids);
+
+        let (src_table, dst_table) = self.tables.get_two_mut(location.table_id, dst_table_id);
+        let result = if drop {
+            src_table.move_row_drop_missing_unchecked(location.row, dst_table)
+        }
+        else {
+            src_table.move_row_forget_missing_unchecked(location.row, dst_table)
+        };
+
+        self.update_moved_locations(entity, location, dst_table_id, result.moved_row, result.swapped_entity);
+    }
+
+    unsafe fn update_moved_locations(&mut self, entity: Entity, old_location: EntityLocation, table_id: usize, row: usize, swapped_entity: Option<Entity>) {
+        if let Some(swapped_entity) = swapped_entity {
+            self.entities.set_location(swapped_entity, old_location);
+        }
+        self.entities.set_location(entity, EntityLocation { table_id, row });
+    }
+
+    fn get_or_insert_table(&mut self, component_ids: &[ComponentId]) -> usize {
+        if let Some(table_id) = self.tables.get_id(component_ids) {
+            return table_id;
+        }
+        let descriptors: Vec<ComponentDescriptor> = component_ids.iter()
+                .map(|id| self.components.get_descriptor(id).unwrap().clone())
+                .collect();
+        self.tables.get_or_insert(&descriptors)
+    }
+
+    pub fn insert_resource<T: Resource>(&mut self, value: T) {
+        self.add_resource::<T>();
+        let descriptor = self.components.get_resource::<T>().unwrap();
+        let mut value = std::mem::ManuallyDrop::new(value);
+        unsafe {
+            self.resources.insert_resource_unchecked(
+                descriptor, (&mut *value as *mut T).cast::<u8>());
+        }
+    }
+
+    pub fn contains_resource<T: Resource>(&self) -> bool {
+        self.components.get_resource::<T>()
+            .map(|descriptor| self.resources.has_resource(&descriptor.id))
+            .unwrap_or(false)
+    }
+
+    pub fn get_resource<T: Resource>(&self) -> Option<&T> {
+        let descriptor = self.components.get_resource::<T>()?;
+        unsafe {
+            Some(&*self.resources.get_resource(&descriptor.id)?.cast::<T>())
+        }
+    }
+
+    pub fn get_resource_mut<T: Resource>(&mut self) -> Option<&mut T> {
+        let descriptor = self.components.get_resource::<T>()?;
+        unsafe {
+            Some(&mut *self.resources.get_resource_mut(&descriptor.id)?.cast::<T>())
+        }
+    }
+
+    pub fn remove_resource<T: Resource>(&mut self) -> Option<T> {
+        let resource_id = self.components.get_resource::<T>()?.id.clone();
+        if !self.resources.has_resource(&resource_id) {
+            return None;
+        }
+        unsafe {
+            Some(self.resources.take_resource_unchecked::<T>(&resource_id))
+        }
+    }
+
+    /// Removes empty tables and releases unused capacity of tables and sparse sets
+    /// Table ids change, `EntityLocation`s read before are invalid afterwards
+    pub fn compact(&mut self) {
+        self.tables.remove_empty();
+        for (table_id, table) in self.tables.iter_mut().enumerate() {
+            table.shrink_to_fit();
+            for (row, entity) in table.entities().iter().enumerate() {
+                unsafe {
+                    self.entities.set_location(*entity, EntityLocation { table_id, row });
+                }
+            }
+        }
+        for (_, sparse_set) in self.sparse_sets.iter_mut() {
+            sparse_set.shrink_to_fit();
+        }
+    }
+
+    /// See `Components::register_map_entities`
+    pub fn register_map_entities<T: Component + entity_map::MapEntities>(&mut self) -> ComponentId {
+        self.components.register_map_entities::<T>()
+    }
+
+    /// Moves the entities and their components out of `other`, returns their new handles
+    /// - components are matched by type, or by name for dynamic ones, whatever their ids in `other`
+    /// - handles in components registered with `register_map_entities` are remapped,
+    ///   handles to entities that were not moved become `Entity::DANGLING`
+    /// - handles to the moved entities held by entities left in `other` are not changed
+    /// - the `on_move` hooks run in `other` before any entity moves, what they remove stays there
+    /// - the other component hooks do not run and the moves are not recorded as removals in `other`
+    ///
+    /// # Panics
+    /// if an entity does not exist in `other` or is listed twice, or an `on_move` hook despawns it
+    pub fn move_entities_from(&mut self, other: &mut World, entities: &[Entity]) -> entity_map::EntityMap {
+        entity_map::move_entities(self, other, entities)
+    }
+
+    /// Moves every entity of `other`, see `move_entities_from`
+    /// Resources of `other` are not moved
+    pub fn append(&mut self, other: &mut World) -> entity_map::EntityMap {
+        let entities: Vec<Entity> = other.entities.iter().collect();
+        self.move_entities_from(other, &entities)
+    }
+
+    /// Reusable state of a query run outside of systems, see `QueryState::query`
+    ///
+    /// `let names = world.query::<&Name>();`
+    pub fn query<Fe: query::fetch::FetchQuery>(&mut self) -> query::state::QueryState<Fe> {
+        query::state::QueryState::new(self)
+    }
+
+    pub fn query_filtered<Fe: query::fetch::FetchQuery, Fi: query::filter::FilterQuery>(&mut self) -> query::state::QueryState<Fe, Fi> {
+        query::state::QueryState::new(self)
+    }
+
+    /// See `ComponentHooks`
+    pub fn register_component_hooks<T: Component>(&mut self) -> &mut component::ComponentHooks {
+        self.components.hooks_mut::<T>()
+    }
+
+    /// Entities that lost each component, read through `RemovedComponents`
+    pub fn removed_components(&self) -> &RemovedComponentEvents {
+        &self.removed_components
+    }
+
+    /// Drops the removals recorded before the previous call, `App::update` calls it every frame
+    /// Worlds used without an `App` should call it regularly
+    pub fn clear_trackers(&mut self) {
+        self.removed_components.clear();
+    }
+
+    /// Clones the cloneable components and resources, see `Snapshot`
+    pub fn snapshot(&self) -> snapshot::Snapshot {
+        snapshot::Snapshot::of(self)
+    }
+
+    /// Brings the world back to `snapshot`, which should be taken from this world
+    /// - entities spawned since are despawned, despawned ones come back with the same handle
+    /// - the entity allocator is restored, the next spawns get the handles they got after the snapshot
+    /// - cloneable components and resources get the snapshot values, or are removed
+    /// - other components of entities alive in both are left untouched
+    pub fn restore(&mut self, snapshot: &snapshot::Snapshot) {
+        snapshot.restore(self);
+    }
+
+    /// Hash of the entities and of the components and resources registered with `register_stable_hash`,
+    /// equal on every machine running the same simulation
+    pub fn checksum(&self) -> u64 {
+        stable_hash::checksum(self)
+    }
+
+    /// Per table and per component storage usage, see `WorldStats`
+    pub fn stats(&self) -> stats::WorldStats {
+        stats::WorldStats::of(self)
+    }
+
     pub fn get_entities(&self) -> &Entities {
         &self.entities
     }
@@ -71,16 +702,211 @@ impl World {
         &mut self.tables
     }
 
+    pub fn get_sparse_sets(&self) -> &SparseSets {
+        &self.sparse_sets
+    }
+
 }
 
 
 #[cfg(test)]
 mod tests {
     
+    use super::World;
+
     #[test]
     fn bitwise() {
         println!("{}", 1 << 4);
         assert_eq!(16, 1 << 4);
     }
 
-}
\ No newline at end of file
+    #[derive(Debug, PartialEq)]
+    struct Score(u32);
+
+    #[test]
+    fn insert_get_remove_resource() {
+        let mut world = World::new();
+        assert!(world.get_resource::<Score>().is_none());
+
+        world.insert_resource(Score(1));
+        assert_eq!(Some(&Score(1)), world.get_resource::<Score>());
+
+        world.get_resource_mut::<Score>().unwrap().0 += 1;
+        world.insert_resource(Score(world.get_resource::<Score>().unwrap().0 + 1));
+        assert_eq!(Some(&Score(3)), world.get_resource::<Score>());
+
+        assert_eq!(Some(Score(3)), world.remove_resource::<Score>());
+        assert!(!world.contains_resource::<Score>());
+        assert_eq!(None, world.remove_resource::<Score>());
+    }
+
+    #[test]
+    fn dynamic_components() {
+        use std::alloc::Layout;
+        use std::sync::atomic::{AtomicUsize, Ordering};
+
+        static DROPS: AtomicUsize = AtomicUsize::new(0);
+        unsafe fn count_drop(_ptr: *mut u8) {
+            DROPS.fetch_add(1, Ordering::Relaxed);
+        }
+
+        let mut world = World::new();
+        let position = world.register_dynamic("Position", Layout::new::<[f32; 2]>(), None);
+        let handle = world.register_dynamic("Handle", Layout::new::<u64>(), Some(count_drop));
+        assert_eq!(position, world.register_dynamic("Position", Layout::new::<[f32; 2]>(), None));
+        assert_eq!(Some(handle.clone()), world.get_components().get_dynamic_id("Handle"));
+
+        let a = world.spawn().insert(Score(1)).id();
+        let b = world.spawn().id();
+        unsafe {
+            let mut value = [1.0f32, 2.0];
+            assert!(world.insert_by_id(a, &position, value.as_mut_ptr().cast::<u8>()));
+            let mut value = 7u64;
+            world.insert_by_id(a, &handle, (&mut value as *mut u64).cast::<u8>());
+            let mut value = 8u64;
+            world.insert_by_id(b, &handle, (&mut value as *mut u64).cast::<u8>());
+
+            *world.get_mut_by_id(a, &position).unwrap().cast::<[f32; 2]>() = [3.0, 4.0];
+            assert_eq!([3.0, 4.0], *world.get_by_id(a, &position).unwrap().cast::<[f32; 2]>());
+            assert_eq!(8, *world.get_by_id(b, &handle).unwrap().cast::<u64>());
+        }
+        assert_eq!(Some(&Score(1)), world.get::<Score>(a));
+
+        assert!(world.remove_by_id(a, &handle));
+        assert!(!world.remove_by_id(a, &handle));
+        assert_eq!(1, DROPS.load(Ordering::Relaxed));
+        assert!(world.get_by_id(a, &position).is_some());
+
+        world.despawn(b);
+        assert_eq!(2, DROPS.load(Ordering::Relaxed));
+    }
+
+    #[test]
+    fn change_ticks() {
+        #[derive(Debug, PartialEq)]
+        struct Name(String);
+        struct Health(u32);
+
+        let mut world = World::new();
+        let a = world.spawn().insert(Name("a".to_string())).id();
+        let b = world.spawn().insert(Name("b".to_string())).insert(Health(1)).id();
+        assert_eq!(vec![a, b], world.changed_since::<Name>(0));
+
+        let last_run = world.increment_change_tick();
+        assert!(world.changed_since::<Name>(last_run).is_empty());
+        // moving to another table keeps the tick
+        world.entity_mut(a).insert(Health(2));
+        assert!(!world.is_changed_since::<Name>(a, last_run));
+        assert!(world.is_changed_since::<Health>(a, last_run));
+
+        world.get_mut::<Name>(b).unwrap().0.push('!');
+        assert_eq!(vec![b], world.changed_since::<Name>(last_run));
+        assert!(!world.is_changed_since::<Health>(b, last_run));
+        world.get::<Name>(a);
+        assert!(!world.is_changed_since::<Name>(a, last_run));
+    }
+
+    #[test]
+    fn compact_removes_empty_tables() {
+        #[derive(Debug, PartialEq)]
+        struct Name(String);
+        struct Health(#[allow(dead_code)] u32);
+
+        let mut world = World::new();
+        let spawned: Vec<_> = (0..1000)
+            .map(|i| world.spawn().insert(Health(i)).insert(Name(i.to_string())).id())
+            .collect();
+        let kept = world.spawn().insert(Name("kept".to_string())).id();
+        for entity in spawned {
+            world.despawn(entity);
+        }
+        assert!(world.stats().wasted_bytes > 0);
+
+        world.compact();
+        let stats = world.stats();
+        assert_eq!((1, 0, 0), (stats.table_count, stats.empty_table_count, stats.wasted_bytes));
+        assert_eq!(Some(&Name("kept".to_string())), world.get::<Name>(kept));
+
+        // tables are recreated on demand after compaction
+        let spawned = world.spawn().insert(Name("new".to_string())).insert(Health(1)).id();
+        world.entity_mut(kept).insert(Health(2));
+        assert_eq!(Some(&Name("new".to_string())), world.get::<Name>(spawned));
+        assert_eq!(2, world.stats().components.iter().find(|c| c.name.ends_with("Health")).unwrap().entity_count);
+    }
+
+    #[test]
+    fn spawn_batch() {
+        use super::component::StorageType;
+
+        #[derive(Debug, Clone, Copy, PartialEq)]
+        struct Position(f32, f32);
+        #[derive(Debug, PartialEq)]
+        struct Name(String);
+        #[derive(Debug, PartialEq)]
+        struct Selected(u8);
+
+        let mut world = World::new();
+        world.add_component_with_storage::<Selected>(StorageType::SparseSet);
+        let single = world.spawn_batch((0..100).map(|i| (Position(i as f32, 0.0),)));
+        let mixed = world.spawn_batch((0..3).map(|i| (Name(i.to_string()), Position(0.0, i as f32), Selected(i))));
+        assert!(world.spawn_batch(std::iter::empty::<(Position,)>()).is_empty());
+
+        assert_eq!(103, world.get_entities().len());
+        assert_eq!(Some(&Position(42.0, 0.0)), world.get::<Position>(single[42]));
+        assert_eq!(None, world.get::<Name>(single[0]));
+        for (i, entity) in mixed.iter().enumerate() {
+            assert_eq!(Some(&Name(i.to_string())), world.get::<Name>(*entity));
+            assert_eq!(Some(&Position(0.0, i as f32)), world.get::<Position>(*entity));
+            assert_eq!(Some(&Selected(i as u8)), world.get::<Selected>(*entity));
+        }
+
+        // rows behave like the ones of spawned entities
+        world.despawn(mixed[0]);
+        world.entity_mut(mixed[2]).remove::<Selected>();
+        assert_eq!(Some(&Name("2".to_string())), world.get::<Name>(mixed[2]));
+        assert_eq!(None, world.get::<Selected>(mixed[2]));
+    }
+
+    #[test]
+    #[should_panic]
+    fn spawn_batch_duplicate_component_panics() {
+        World::new().spawn_batch([(1u32, 2u32)]);
+    }
+
+    #[derive(Debug, Default)]
+    struct HookLog(Vec<(&'static str, super::entity::Entity)>);
+    struct Body(u32);
+    struct Marker;
+
+    fn log(world: &mut World, event: &'static str, entity: super::entity::Entity) {
+        world.get_resource_mut::<HookLog>().unwrap().0.push((event, entity));
+    }
+
+    #[test]
+    fn component_hooks() {
+        let mut world = World::new();
+        world.insert_resource(HookLog::default());
+        // removing a component from a hook is fine
+        world.register_component_hooks::<Marker>()
+            .on_remove(|world, entity, _| {
+                world.entity_mut(entity).remove::<Body>();
+            });
+        world.register_component_hooks::<Body>()
+            .on_add(|world, entity, _| log(world, "add", entity))
+            .on_insert(|world, entity, _| log(world, "insert", entity))
+            .on_remove(|world, entity, _| {
+                // the value is still there
+                assert_eq!(7, world.get::<Body>(entity).unwrap().0);
+                log(world, "remove", entity);
+            });
+
+        let a = world.spawn().insert(Body(1)).insert(Body(7)).id();
+        world.entity_mut(a).remove::<Body>();
+        let b = world.spawn_batch([(Marker, Body(7))])[0];
+        assert!(world.despawn(b));
+
+        assert_eq!(vec![("add", a), ("insert", a), ("insert", a), ("remove", a), ("add", b), ("insert", b), ("remove", b)],
+            world.get_resource::<HookLog>().unwrap().0);
+        assert!(!world.contains_entity(b));
+    }
+}
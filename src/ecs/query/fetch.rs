@@ -1,6 +1,6 @@
-use std::{marker::PhantomData, ptr::NonNull};
+use std::{marker::PhantomData, ops::Range, ptr::NonNull};
 
-use crate::ecs::{World, storage::table::Table, component::{Component, ComponentId}};
+use crate::ecs::{World, storage::{table::{Table, Column}, sparse_set::{SparseSets, ComponentSparseSet}}, component::{Component, ComponentId, StorageType}, entity::Entity};
 
 use super::AccessState;
 
@@ -10,10 +10,20 @@ pub trait FetchQuery {
     type Fetch: for<'w, 's> Fetch<'w, 's, State = Self::State>;
 }
 
+/// Fetches that never hand out mutable references, safe to run concurrently through `&Query`
+///
+/// # Safety
+/// `Fetch::Item` should only give shared access to the world
+pub unsafe trait ReadOnlyFetchQuery: FetchQuery {}
+
 pub trait FetchState: /*Send + Sync + */Sized {
     fn init(world: &mut World) -> Self;
     fn update_access(&self, access_state: &mut AccessState);
     fn matches_table(&self, table: &Table) -> bool;
+    /// True if every row of a matched table matches, no component lives in a sparse set
+    fn is_dense(&self) -> bool {
+        true
+    }
 }
 
 pub trait Fetch<'w, 's>: Sized {
@@ -22,17 +32,132 @@ pub trait Fetch<'w, 's>: Sized {
 
     fn init(world: &'w World) -> Self;
     unsafe fn set_table(&mut self, fetch_state: &'s Self::State, table: &'w Table);
+    /// False if the entity at `row` lacks a component that is not stored in the table
+    ///
+    /// # Safety
+    /// `set_table` should be called before, `row` should be a row of the table
+    unsafe fn matches_row(&self, _row: usize) -> bool {
+        true
+    }
     unsafe fn fetch_item_from_table(&mut self, row: usize) -> Self::Item;
 }
 
+/// Fetches handing out consecutive rows of the current table as a single slice
+pub trait SliceFetch<'w, 's>: Fetch<'w, 's> {
+    type Slice;
+
+    /// # Safety
+    /// - `set_table` should be called before, `matches_row` should be true for every row of `rows`
+    /// - `rows` should hold a single row if the fetch state is not dense
+    unsafe fn fetch_slice_from_table(&mut self, rows: Range<usize>) -> Self::Slice;
+}
+
+/// Location of a component for the rows of the current table, in its column or sparse set
+pub(crate) struct ComponentPtr<T> {
+    table_column: NonNull<T>,
+    column: Option<NonNull<Column>>,
+    entities: *const Entity,
+    sparse_sets: NonNull<SparseSets>,
+    sparse_set: Option<NonNull<ComponentSparseSet>>,
+}
+
+impl<T: Component> ComponentPtr<T> {
+    pub(crate) fn new(world: &World) -> Self {
+        ComponentPtr {
+            table_column: NonNull::dangling(),
+            column: None,
+            entities: std::ptr::null(),
+            sparse_sets: NonNull::from(world.get_sparse_sets()),
+            sparse_set: None,
+        }
+    }
+
+    /// # Safety
+    /// the table should have a column for the component if it uses `StorageType::Table`
+    pub(crate) unsafe fn set_table(&mut self, component_id: &ComponentId, storage_type: StorageType, table: &Table) {
+        match storage_type {
+            StorageType::Table => {
+                let column = table.get_column(component_id).unwrap();
+                self.table_column = column.get_ptr().cast::<T>();
+                self.column = Some(NonNull::from(column));
+            },
+            StorageType::SparseSet => {
+                self.entities = table.entities().as_ptr();
+                self.sparse_set = self.sparse_sets.as_ref().get(component_id).map(NonNull::from);
+            },
+        }
+    }
+
+    /// # Safety
+    /// `set_table` should be called before, `row` should be a row of the table
+    pub(crate) unsafe fn contains(&self, storage_type: StorageType, row: usize) -> bool {
+        match storage_type {
+            StorageType::Table => true,
+            StorageType::SparseSet => self.sparse_set
+                .map(|sparse_set| sparse_set.as_ref().contains(*self.entities.add(row)))
+                .unwrap_or(false),
+        }
+    }
+
+    /// Start of the values of `rows`, consecutive in the table column or a single one in the sparse set
+    ///
+    /// # Safety
+    /// `contains` should be true for every row, `rows` should hold a single row for `StorageType::SparseSet`
+    pub(crate) unsafe fn get_slice_start(&self, storage_type: StorageType, rows: &Range<usize>) -> *mut T {
+        debug_assert!(storage_type == StorageType::Table || rows.len() == 1);
+        self.get(storage_type, rows.start)
+    }
+
+    /// # Safety
+    /// `contains` should be true for `row`
+    pub(crate) unsafe fn get(&self, storage_type: StorageType, row: usize) -> *mut T {
+        match storage_type {
+            StorageType::Table => self.table_column.as_ptr().add(row),
+            StorageType::SparseSet => self.sparse_set.unwrap().as_ref()
+                .get(*self.entities.add(row))
+                .unwrap()
+                .cast::<T>(),
+        }
+    }
+
+    /// Stamps the value at `row` with `tick`
+    ///
+    /// # Safety
+    /// `contains` should be true for `row`
+    pub(crate) unsafe fn set_changed(&self, storage_type: StorageType, row: usize, tick: u32) {
+        match storage_type {
+            StorageType::Table => self.column.unwrap().as_ref().set_changed(row, tick),
+            StorageType::SparseSet => {
+                self.sparse_set.unwrap().as_ref().set_changed(*self.entities.add(row), tick);
+            },
+        }
+    }
+
+    /// # Safety
+    /// `contains` should be true for every row, `rows` should hold a single row for `StorageType::SparseSet`
+    pub(crate) unsafe fn set_changed_range(&self, storage_type: StorageType, rows: Range<usize>, tick: u32) {
+        match storage_type {
+            StorageType::Table => self.column.unwrap().as_ref().set_changed_range(rows, tick),
+            StorageType::SparseSet => self.set_changed(storage_type, rows.start, tick),
+        }
+    }
+}
+
+pub(crate) fn storage_type_of<T: Component>(world: &World) -> StorageType {
+    world.get_components().get_component::<T>().unwrap().storage_type
+}
+
 
 impl<T: Component> FetchQuery for &T {
     type State = RefFetchState<T>;
     type Fetch = RefFetch<T>;
 }
 
+unsafe impl<T: Component> ReadOnlyFetchQuery for &T {}
+
 pub struct RefFetchState<T> {
     component_id: ComponentId,
+    storage_type: StorageType,
     marker: PhantomData<T>,
 }
 
@@ -41,6 +166,7 @@ impl<T: Component> FetchState for RefFetchState<T> {
         let component_id = world.add_component::<T>();
         RefFetchState {
             component_id,
+            storage_type: storage_type_of::<T>(world),
             marker: PhantomData,
         }
     }
@@ -53,12 +179,17 @@ impl<T: Component> FetchState for RefFetchState<T> {
     }
 
     fn matches_table(&self, table: &Table) -> bool {
-        table.has_column(&self.component_id)
+        self.storage_type == StorageType::SparseSet || table.has_column(&self.component_id)
+    }
+
+    fn is_dense(&self) -> bool {
+        self.storage_type == StorageType::Table
     }
 }
 
 pub struct RefFetch<T> {
-    table_column: NonNull<T>,
+    storage_type: StorageType,
+    ptr: ComponentPtr<T>,
 }
 
 impl<'w, 's, T: Component> Fetch<'w, 's> for RefFetch<T> {
@@ -68,21 +199,33 @@ impl<'w, 's, T: Component> Fetch<'w, 's> for RefFetch<T> {
 
     fn init(world: &'w World) -> Self {
         RefFetch {
-            table_column: NonNull::dangling(),
+            storage_type: StorageType::Table,
+            ptr: ComponentPtr::new(world),
         }
     }
 
     unsafe fn set_table(&mut self, fetch_state: &'s Self::State, table: &'w Table) {
-        self.table_column = table.get_column(&fetch_state.component_id).unwrap()
-                                .get_ptr().cast::<T>();
+        self.storage_type = fetch_state.storage_type;
+        self.ptr.set_table(&fetch_state.component_id, fetch_state.storage_type, table);
+    }
+
+    unsafe fn matches_row(&self, row: usize) -> bool {
+        self.ptr.contains(self.storage_type, row)
     }
 
     /// # Safety
-    /// - `self.table_column` should not be dangling
     /// - call `set_table` method before calling this method
-    /// - row should be valid address for `self.table_column`
+    /// - `matches_row` should be true for `row`
     unsafe fn fetch_item_from_table(&mut self, row: usize) -> Self::Item {
-        &*self.table_column.as_ptr().add(row)
+        &*self.ptr.get(self.storage_type, row)
+    }
+}
+
+impl<'w, 's, T: Component> SliceFetch<'w, 's> for RefFetch<T> {
+    type Slice = &'w [T];
+
+    unsafe fn fetch_slice_from_table(&mut self, rows: Range<usize>) -> Self::Slice {
+        std::slice::from_raw_parts(self.ptr.get_slice_start(self.storage_type, &rows), rows.len())
     }
 }
 
@@ -94,6 +237,7 @@ impl<T: Component> FetchQuery for &mut T {
 
 pub struct RefMutFetchState<T> {
     component_id: ComponentId,
+    storage_type: StorageType,
     marker: PhantomData<T>,
 }
 
@@ -102,6 +246,7 @@ impl<T: Component> FetchState for RefMutFetchState<T> {
         let component_id = world.add_component::<T>();
         RefMutFetchState {
             component_id,
+            storage_type: storage_type_of::<T>(world),
             marker: PhantomData,
         }
     }
@@ -114,12 +259,18 @@ impl<T: Component> FetchState for RefMutFetchState<T> {
     }
 
     fn matches_table(&self, table: &Table) -> bool {
-        table.has_column(&self.component_id)
+        self.storage_type == StorageType::SparseSet || table.has_column(&self.component_id)
+    }
+
+    fn is_dense(&self) -> bool {
+        self.storage_type == StorageType::Table
     }
 }
 
 pub struct RefMutFetch<T> {
-    table_column: NonNull<T>,
+    storage_type: StorageType,
+    ptr: ComponentPtr<T>,
+    change_tick: u32,
 }
 
 impl<'w, 's, T: Component> Fetch<'w, 's> for RefMutFetch<T> {
@@ -128,20 +279,37 @@ impl<'w, 's, T: Component> Fetch<'w, 's> for RefMutFetch<T> {
 
     fn init(world: &'w World) -> Self {
         RefMutFetch {
-            table_column: NonNull::dangling(),
+            storage_type: StorageType::Table,
+            ptr: ComponentPtr::new(world),
+            change_tick: world.change_tick(),
         }
     }
 
     unsafe fn set_table(&mut self, fetch_state: &'s Self::State, table: &'w Table) {
-        self.table_column = table.get_column(&fetch_state.component_id).unwrap()
-                                .get_ptr().cast::<T>();
+        self.storage_type = fetch_state.storage_type;
+        self.ptr.set_table(&fetch_state.component_id, fetch_state.storage_type, table);
     }
 
+    unsafe fn matches_row(&self, row: usize) -> bool {
+        self.ptr.contains(self.storage_type, row)
+    }
+
+    /// Marks the value changed, see `World::change_tick`
+    ///
     /// # Safety
-    /// - `self.table_column` should not be dangling
     /// - call `set_table` method before calling this method
-    /// - row should be valid address for `self.table_column`
+    /// - `matches_row` should be true for `row`
     unsafe fn fetch_item_from_table(&mut self, row: usize) -> Self::Item {
-        &mut *self.table_column.as_ptr().add(row)
+        self.ptr.set_changed(self.storage_type, row, self.change_tick);
+        &mut *self.ptr.get(self.storage_type, row)
     }
-}
\ No newline at end of file
+}
+
+impl<'w, 's, T: Component> SliceFetch<'w, 's> for RefMutFetch<T> {
+    type Slice = &'w mut [T];
+
+    unsafe fn fetch_slice_from_table(&mut self, rows: Range<usize>) -> Self::Slice {
+        self.ptr.set_changed_range(self.storage_type, rows.clone(), self.change_tick);
+        std::slice::from_raw_parts_mut(self.ptr.get_slice_start(self.storage_type, &rows), rows.len())
+    }
+}
@@ -1,6 +1,8 @@
-use crate::ecs::{World, storage::table::Table};
+use std::marker::PhantomData;
 
-use super::AccessState;
+use crate::ecs::{World, storage::table::Table, component::{Component, ComponentId, StorageType}};
+
+use super::{AccessState, fetch::{ComponentPtr, storage_type_of}};
 
 
 pub trait FilterQuery {
@@ -12,6 +14,10 @@ pub trait FilterState {
     fn init(world: &mut World) -> Self;
     fn update_access(&self, access_state: &mut AccessState);
     fn matches_table(&self, table: &Table) -> bool;
+    /// True if the filter is decided by `matches_table` alone
+    fn is_dense(&self) -> bool {
+        true
+    }
 }
 
 pub trait Filter<'w, 's> {
@@ -19,7 +25,9 @@ pub trait Filter<'w, 's> {
 
     fn init(world: &'w World) -> Self;
     fn set_table(&mut self, filter_state: &'s Self::State, table: &'w Table);
-    fn matches(&self) -> bool;
+    /// # Safety
+    /// `set_table` should be called before, `row` should be a row of the table
+    unsafe fn matches(&self, row: usize) -> bool;
 }
 
 
@@ -40,5 +48,135 @@ impl<'w, 's> Filter<'w, 's> for UnitFilter {
     type State = UnitFilterState;
     fn init(_world: &'w World) -> Self { Self }
     fn set_table(&mut self, _filter_state: &'s Self::State, _table: &'w Table) {}
-    fn matches(&self) -> bool { true }
-}
\ No newline at end of file
+    unsafe fn matches(&self, _row: usize) -> bool { true }
+}
+
+
+/// Matches entities having `T`, does not access its value
+pub struct With<T>(PhantomData<T>);
+
+/// Matches entities not having `T`
+pub struct Without<T>(PhantomData<T>);
+
+pub struct ComponentFilterState<T> {
+    component_id: ComponentId,
+    storage_type: StorageType,
+    marker: PhantomData<T>,
+}
+
+impl<T: Component> ComponentFilterState<T> {
+    fn new(world: &mut World) -> Self {
+        let component_id = world.add_component::<T>();
+        ComponentFilterState {
+            component_id,
+            storage_type: storage_type_of::<T>(world),
+            marker: PhantomData,
+        }
+    }
+}
+
+/// Checks presence of `T` for the rows of the current table
+pub struct ComponentFilter<T> {
+    storage_type: StorageType,
+    ptr: ComponentPtr<T>,
+}
+
+impl<T: Component> ComponentFilter<T> {
+    fn new(world: &World) -> Self {
+        ComponentFilter {
+            storage_type: StorageType::Table,
+            ptr: ComponentPtr::new(world),
+        }
+    }
+
+    fn set_table(&mut self, filter_state: &ComponentFilterState<T>, table: &Table) {
+        self.storage_type = filter_state.storage_type;
+        if filter_state.storage_type == StorageType::SparseSet {
+            unsafe {
+                self.ptr.set_table(&filter_state.component_id, filter_state.storage_type, table);
+            }
+        }
+    }
+}
+
+impl<T: Component> FilterQuery for With<T> {
+    type State = WithState<T>;
+    type Filter = WithFilter<T>;
+}
+
+pub struct WithState<T>(ComponentFilterState<T>);
+
+impl<T: Component> FilterState for WithState<T> {
+    fn init(world: &mut World) -> Self {
+        WithState(ComponentFilterState::new(world))
+    }
+
+    fn update_access(&self, _access_state: &mut AccessState) {}
+
+    fn matches_table(&self, table: &Table) -> bool {
+        self.0.storage_type == StorageType::SparseSet || table.has_column(&self.0.component_id)
+    }
+
+    fn is_dense(&self) -> bool {
+        self.0.storage_type == StorageType::Table
+    }
+}
+
+pub struct WithFilter<T>(ComponentFilter<T>);
+
+impl<'w, 's, T: Component> Filter<'w, 's> for WithFilter<T> {
+    type State = WithState<T>;
+
+    fn init(world: &'w World) -> Self {
+        WithFilter(ComponentFilter::new(world))
+    }
+
+    fn set_table(&mut self, filter_state: &'s Self::State, table: &'w Table) {
+        self.0.set_table(&filter_state.0, table);
+    }
+
+    unsafe fn matches(&self, row: usize) -> bool {
+        self.0.ptr.contains(self.0.storage_type, row)
+    }
+}
+
+impl<T: Component> FilterQuery for Without<T> {
+    type State = WithoutState<T>;
+    type Filter = WithoutFilter<T>;
+}
+
+pub struct WithoutState<T>(ComponentFilterState<T>);
+
+impl<T: Component> FilterState for WithoutState<T> {
+    fn init(world: &mut World) -> Self {
+        WithoutState(ComponentFilterState::new(world))
+    }
+
+    fn update_access(&self, _access_state: &mut AccessState) {}
+
+    fn matches_table(&self, table: &Table) -> bool {
+        self.0.storage_type == StorageType::SparseSet || !table.has_column(&self.0.component_id)
+    }
+
+    fn is_dense(&self) -> bool {
+        self.0.storage_type == StorageType::Table
+    }
+}
+
+pub struct WithoutFilter<T>(ComponentFilter<T>);
+
+impl<'w, 's, T: Component> Filter<'w, 's> for WithoutFilter<T> {
+    type State = WithoutState<T>;
+
+    fn init(world: &'w World) -> Self {
+        WithoutFilter(ComponentFilter::new(world))
+    }
+
+    fn set_table(&mut self, filter_state: &'s Self::State, table: &'w Table) {
+        self.0.set_table(&filter_state.0, table);
+    }
+
+    unsafe fn matches(&self, row: usize) -> bool {
+        self.0.storage_type == StorageType::Table || !self.0.ptr.contains(self.0.storage_type, row)
+    }
+}
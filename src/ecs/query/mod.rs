@@ -37,4 +37,23 @@ impl AccessState {
     pub fn has_any(&self, component_id: &ComponentId) -> bool {
         self.read.contains(component_id) || self.write.contains(component_id)
     }
+
+    /// Union of both accesses, does not check for conflicts
+    pub fn extend(&mut self, other: &AccessState) {
+        self.read.extend(other.read.iter().cloned());
+        self.write.extend(other.write.iter().cloned());
+    }
+
+    pub fn reads(&self) -> impl Iterator<Item = &ComponentId> {
+        self.read.iter()
+    }
+
+    pub fn writes(&self) -> impl Iterator<Item = &ComponentId> {
+        self.write.iter()
+    }
+
+    pub fn is_compatible(&self, other: &AccessState) -> bool {
+        self.write.iter().all(|id| !other.has_any(id))
+            && other.write.iter().all(|id| !self.has_any(id))
+    }
 }
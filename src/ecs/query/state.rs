@@ -1,6 +1,6 @@
-use crate::ecs::World;
+use crate::ecs::{World, entity::Entity};
 
-use super::{fetch::{FetchQuery, FetchState, Fetch}, filter::{FilterQuery, FilterState, Filter}};
+use super::{AccessState, fetch::{FetchQuery, FetchState, Fetch, ReadOnlyFetchQuery, SliceFetch}, filter::{FilterQuery, FilterState, Filter}};
 
 
 /// Bundle of FetchQuery and FilterQuery
@@ -15,7 +15,41 @@ impl<Fe: FetchQuery, Fi: FilterQuery> QueryState<Fe, Fi> {
             fetch_state: <Fe as FetchQuery>::State::init(world),
             filter_state: <Fi as FilterQuery>::State::init(world),
         }
-    } 
+    }
+
+    pub fn update_access(&self, access_state: &mut AccessState) {
+        self.fetch_state.update_access(access_state);
+        self.filter_state.update_access(access_state);
+    }
+
+    /// Non-empty tables whose components match the fetch and the filter
+    pub fn matched_table_ids(&self, world: &World) -> Vec<usize> {
+        world.get_tables().iter()
+            .enumerate()
+            .filter(|(_, table)| !table.is_empty()
+                && self.fetch_state.matches_table(table)
+                && self.filter_state.matches_table(table))
+            .map(|(table_id, _)| table_id)
+            .collect()
+    }
+
+    /// Query over `world`, which should be the world the state was created with
+    pub fn query<'w, 's>(&'s self, world: &'w World) -> Query<'w, 's, Fe, Fi>
+    where
+        Fe: ReadOnlyFetchQuery
+    {
+        Query::new(world, self)
+    }
+
+    /// `query` for fetches writing components, the world is borrowed as long as the query lives
+    pub fn query_mut<'w, 's>(&'s self, world: &'w mut World) -> Query<'w, 's, Fe, Fi> {
+        Query::new(world, self)
+    }
+
+    /// True if every row of the matched tables is an item, see `FetchState::is_dense`
+    pub fn is_dense(&self) -> bool {
+        self.fetch_state.is_dense() && self.filter_state.is_dense()
+    }
 }
 
 /// Actual SystemParam Query
@@ -34,9 +68,269 @@ impl<'w, 's, Fe: FetchQuery, Fi: FilterQuery> Query<'w, 's, Fe, Fi> {
         }
     }
 
+    pub(crate) fn world(&self) -> &'w World {
+        self.world
+    }
+
     pub fn iter(&self) -> QueryIter<'w, 's, Fe, Fi> {
         QueryIter::new(self.world, self.query_state)
     }
+
+    /// `iter` as an `ExactSizeIterator`, None if the query is not dense
+    pub fn iter_dense(&self) -> Option<DenseQueryIter<'w, 's, Fe, Fi>> {
+        self.query_state.is_dense().then(|| DenseQueryIter(self.iter()))
+    }
+
+    /// Item of the entity, None if it does not exist or does not match
+    pub fn get(&self, entity: Entity) -> Option<<<Fe as FetchQuery>::Fetch as Fetch<'w, 's>>::Item>
+    where
+        Fe: ReadOnlyFetchQuery,
+    {
+        unsafe {
+            get_unchecked(self.world, self.query_state, entity)
+        }
+    }
+
+    pub fn get_mut(&mut self, entity: Entity) -> Option<<<Fe as FetchQuery>::Fetch as Fetch<'_, 's>>::Item> {
+        unsafe {
+            get_unchecked(self.world, self.query_state, entity)
+        }
+    }
+
+    /// Number of items, the sum of the matched table lengths when the query is dense,
+    /// otherwise the rows are checked without fetching the items
+    pub fn count(&self) -> usize {
+        self.iter().count_remaining()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.iter().next().is_none()
+    }
+
+    /// Every set of `K` distinct items, each set once: `[a, b]` but not also `[b, a]`
+    ///
+    /// `for [a, b] in query.iter_combinations() { collide(a, b) }`
+    pub fn iter_combinations<const K: usize>(&self) -> QueryCombinationIter<'w, 's, Fe, Fi, K>
+    where
+        Fe: ReadOnlyFetchQuery,
+    {
+        QueryCombinationIter::new(self.world, self.query_state)
+    }
+
+    /// Mutable `iter_combinations`, items are borrowed until the next `fetch_next` call
+    ///
+    /// `while let Some([a, b]) = combinations.fetch_next() { attract(a, b) }`
+    pub fn iter_combinations_mut<const K: usize>(&mut self) -> QueryCombinationIter<'_, 's, Fe, Fi, K> {
+        QueryCombinationIter::new(self.world, self.query_state)
+    }
+
+    /// Faster `iter().for_each(f)`, tables are walked in a tight loop
+    /// without per row checks when the query is dense
+    pub fn for_each<F>(&self, f: F)
+    where
+        Fe: ReadOnlyFetchQuery,
+        F: FnMut(<<Fe as FetchQuery>::Fetch as Fetch<'w, 's>>::Item),
+    {
+        unsafe {
+            for_each_unchecked(self.world, self.query_state, f);
+        }
+    }
+
+    pub fn for_each_mut<'q, F>(&'q mut self, f: F)
+    where
+        F: FnMut(<<Fe as FetchQuery>::Fetch as Fetch<'q, 's>>::Item),
+    {
+        unsafe {
+            for_each_unchecked(self.world, self.query_state, f);
+        }
+    }
+
+    /// Calls `f` once per matched table with the whole columns as slices,
+    /// `query.for_each_chunk(|positions: &[Vec3]| ..)`
+    ///
+    /// Queries that are not dense call `f` once per run of consecutive matching rows,
+    /// a run holds a single row when the fetched component lives in a sparse set
+    pub fn for_each_chunk<F>(&self, f: F)
+    where
+        Fe: ReadOnlyFetchQuery,
+        <Fe as FetchQuery>::Fetch: SliceFetch<'w, 's>,
+        F: FnMut(<<Fe as FetchQuery>::Fetch as SliceFetch<'w, 's>>::Slice),
+    {
+        unsafe {
+            for_each_chunk_unchecked(self.world, self.query_state, f);
+        }
+    }
+
+    /// `query.for_each_chunk_mut(|positions: &mut [Vec3]| ..)`
+    pub fn for_each_chunk_mut<'q, F>(&'q mut self, f: F)
+    where
+        <Fe as FetchQuery>::Fetch: SliceFetch<'q, 's>,
+        F: FnMut(<<Fe as FetchQuery>::Fetch as SliceFetch<'q, 's>>::Slice),
+    {
+        unsafe {
+            for_each_chunk_unchecked(self.world, self.query_state, f);
+        }
+    }
+}
+
+/// # Safety
+/// no other access to the fetched component of the entity should exist while the item lives
+unsafe fn get_unchecked<'w, 's, Fe, Fi>(world: &'w World, query_state: &'s QueryState<Fe, Fi>, entity: Entity)
+    -> Option<<<Fe as FetchQuery>::Fetch as Fetch<'w, 's>>::Item>
+where
+    Fe: FetchQuery,
+    Fi: FilterQuery,
+{
+    let location = world.get_entities().get(entity)?;
+    let table = world.get_tables().get_table(location.table_id)?;
+    if !query_state.fetch_state.matches_table(table) || !query_state.filter_state.matches_table(table) {
+        return None;
+    }
+    let mut fetch = <Fe as FetchQuery>::Fetch::init(world);
+    let mut filter = <Fi as FilterQuery>::Filter::init(world);
+    fetch.set_table(&query_state.fetch_state, table);
+    filter.set_table(&query_state.filter_state, table);
+    if !filter.matches(location.row) || !fetch.matches_row(location.row) {
+        return None;
+    }
+    Some(fetch.fetch_item_from_table(location.row))
+}
+
+/// # Safety
+/// no other access to the fetched components should exist while `f` runs
+unsafe fn for_each_unchecked<'w, 's, Fe, Fi, F>(world: &'w World, query_state: &'s QueryState<Fe, Fi>, mut f: F)
+where
+    Fe: FetchQuery,
+    Fi: FilterQuery,
+    F: FnMut(<<Fe as FetchQuery>::Fetch as Fetch<'w, 's>>::Item),
+{
+    let mut fetch = <Fe as FetchQuery>::Fetch::init(world);
+    let mut filter = <Fi as FilterQuery>::Filter::init(world);
+    let is_dense = query_state.is_dense();
+    for table_id in query_state.matched_table_ids(world) {
+        let table = world.get_tables().get_table(table_id).unwrap();
+        fetch.set_table(&query_state.fetch_state, table);
+        filter.set_table(&query_state.filter_state, table);
+        if is_dense {
+            for row in 0..table.len() {
+                f(fetch.fetch_item_from_table(row));
+            }
+        }
+        else {
+            for row in 0..table.len() {
+                if filter.matches(row) && fetch.matches_row(row) {
+                    f(fetch.fetch_item_from_table(row));
+                }
+            }
+        }
+    }
+}
+
+/// # Safety
+/// no other access to the fetched components should exist while `f` runs
+unsafe fn for_each_chunk_unchecked<'w, 's, Fe, Fi, F>(world: &'w World, query_state: &'s QueryState<Fe, Fi>, mut f: F)
+where
+    Fe: FetchQuery,
+    Fi: FilterQuery,
+    <Fe as FetchQuery>::Fetch: SliceFetch<'w, 's>,
+    F: FnMut(<<Fe as FetchQuery>::Fetch as SliceFetch<'w, 's>>::Slice),
+{
+    let mut fetch = <Fe as FetchQuery>::Fetch::init(world);
+    let mut filter = <Fi as FilterQuery>::Filter::init(world);
+    let is_dense = query_state.is_dense();
+    let fetch_is_dense = query_state.fetch_state.is_dense();
+    for table_id in query_state.matched_table_ids(world) {
+        let table = world.get_tables().get_table(table_id).unwrap();
+        fetch.set_table(&query_state.fetch_state, table);
+        if is_dense {
+            f(fetch.fetch_slice_from_table(0..table.len()));
+            continue;
+        }
+
+        filter.set_table(&query_state.filter_state, table);
+        let mut row = 0;
+        while row < table.len() {
+            let start = row;
+            row += 1;
+            if !filter.matches(start) || !fetch.matches_row(start) {
+                continue;
+            }
+            while fetch_is_dense && row < table.len() && filter.matches(row) && fetch.matches_row(row) {
+                row += 1;
+            }
+            f(fetch.fetch_slice_from_table(start..row));
+        }
+    }
+}
+
+impl<'w, 's, Fe: FetchQuery, Fi: FilterQuery> Query<'w, 's, Fe, Fi>
+where
+    <Fe as FetchQuery>::State: Sync,
+    <Fi as FilterQuery>::State: Sync,
+{
+    /// Calls `f` on every item from the rayon thread pool,
+    /// each task handles up to `batch_size` rows of a single table
+    ///
+    /// Read-only queries only, `par_for_each_mut` takes the query mutably
+    ///
+    /// # Panics
+    /// if `batch_size` is 0
+    pub fn par_for_each<F>(&self, batch_size: usize, f: F)
+    where
+        Fe: ReadOnlyFetchQuery,
+        F: Fn(<<Fe as FetchQuery>::Fetch as Fetch<'w, 's>>::Item) + Send + Sync,
+    {
+        // shared items can alias freely
+        unsafe {
+            par_for_each_unchecked(self.world, self.query_state, batch_size, f);
+        }
+    }
+
+    /// `par_for_each` for queries with mutable fetches, batches never share a row
+    /// and `&mut self` keeps the items from outliving the call
+    pub fn par_for_each_mut<'q, F>(&'q mut self, batch_size: usize, f: F)
+    where
+        F: Fn(<<Fe as FetchQuery>::Fetch as Fetch<'q, 's>>::Item) + Send + Sync,
+    {
+        unsafe {
+            par_for_each_unchecked(self.world, self.query_state, batch_size, f);
+        }
+    }
+}
+
+/// # Safety
+/// items of different rows should not alias, and no other access to the fetched
+/// components should exist while `f` runs
+unsafe fn par_for_each_unchecked<'w, 's, Fe, Fi, F>(world: &'w World, query_state: &'s QueryState<Fe, Fi>, batch_size: usize, f: F)
+where
+    Fe: FetchQuery,
+    Fi: FilterQuery,
+    <Fe as FetchQuery>::State: Sync,
+    <Fi as FilterQuery>::State: Sync,
+    F: Fn(<<Fe as FetchQuery>::Fetch as Fetch<'w, 's>>::Item) + Send + Sync,
+{
+    assert!(batch_size > 0, "batch_size should be positive");
+    let f = &f;
+    rayon::scope(|scope| {
+        for table_id in query_state.matched_table_ids(world) {
+            let table_len = world.get_tables().get_table(table_id).unwrap().len();
+            for start in (0..table_len).step_by(batch_size) {
+                let end = table_len.min(start + batch_size);
+                scope.spawn(move |_| {
+                    let table = world.get_tables().get_table(table_id).unwrap();
+                    let mut fetch = <Fe as FetchQuery>::Fetch::init(world);
+                    let mut filter = <Fi as FilterQuery>::Filter::init(world);
+                    fetch.set_table(&query_state.fetch_state, table);
+                    filter.set_table(&query_state.filter_state, table);
+                    for row in start..end {
+                        if filter.matches(row) && fetch.matches_row(row) {
+                            f(fetch.fetch_item_from_table(row));
+                        }
+                    }
+                });
+            }
+        }
+    });
 }
 
 pub struct QueryIter<'w, 's, Fe: FetchQuery, Fi: FilterQuery> {
@@ -44,7 +338,8 @@ pub struct QueryIter<'w, 's, Fe: FetchQuery, Fi: FilterQuery> {
     query_state: &'s QueryState<Fe, Fi>,
     fetch: <Fe as FetchQuery>::Fetch,
     filter: <Fi as FilterQuery>::Filter,
-    matched_table_ids: Vec<usize>, // TODO: implement something
+    matched_table_ids: Vec<usize>,
+    is_dense: bool,
     current_table_index: usize,
     current_row: usize,
     current_table_len: usize,
@@ -54,18 +349,57 @@ impl<'w, 's, Fe: FetchQuery, Fi: FilterQuery> QueryIter<'w, 's, Fe, Fi> {
     pub fn new(world: &'w World, query_state: &'s QueryState<Fe, Fi>) -> Self {
         let fetch = <Fe as FetchQuery>::Fetch::init(world);
         let filter = <Fi as FilterQuery>::Filter::init(world);
+        let matched_table_ids = query_state.matched_table_ids(world);
 
         QueryIter {
             world,
             query_state,
             fetch,
             filter,
-            matched_table_ids: Vec::new(),
+            matched_table_ids,
+            is_dense: query_state.is_dense(),
             current_table_index: 0,
             current_row: 0,
             current_table_len: 0,
         }
     }
+
+    /// Number of rows left in the matched tables, the number of items left if the query is dense
+    fn remaining_rows(&self) -> usize {
+        let tables = self.world.get_tables();
+        (self.current_row..self.current_table_len).len() + self.matched_table_ids[self.current_table_index..].iter()
+            .map(|table_id| tables.get_table(*table_id).unwrap().len())
+            .sum::<usize>()
+    }
+
+    /// Number of items left, counts the matching rows if the query is not dense
+    fn count_remaining(&self) -> usize {
+        if self.is_dense {
+            return self.remaining_rows();
+        }
+
+        let tables = self.world.get_tables();
+        let next_tables = self.matched_table_ids[self.current_table_index..].iter()
+            .map(|table_id| tables.get_table(*table_id).unwrap());
+        let current_rows = self.current_row..self.current_table_len;
+
+        unsafe {
+            let current = current_rows
+                .filter(|row| self.filter.matches(*row) && self.fetch.matches_row(*row))
+                .count();
+            let mut fetch = <Fe as FetchQuery>::Fetch::init(self.world);
+            let mut filter = <Fi as FilterQuery>::Filter::init(self.world);
+            current + next_tables
+                .map(|table| {
+                    fetch.set_table(&self.query_state.fetch_state, table);
+                    filter.set_table(&self.query_state.filter_state, table);
+                    (0..table.len())
+                        .filter(|row| filter.matches(*row) && fetch.matches_row(*row))
+                        .count()
+                })
+                .sum::<usize>()
+        }
+    }
 }
 
 impl<'w, 's, Fe: FetchQuery, Fi: FilterQuery> Iterator for QueryIter<'w, 's, Fe, Fi> {
@@ -81,13 +415,6 @@ impl<'w, 's, Fe: FetchQuery, Fi: FilterQuery> Iterator for QueryIter<'w, 's, Fe,
 
                     self.current_table_index += 1;
 
-                    // I dont think this ever happens
-                    // or at least should prevent it from happening
-                    // by not having it in self.matched_table_ids
-                    if table.is_empty() {
-                        continue;
-                    }
-                    
                     self.fetch.set_table(&self.query_state.fetch_state, table);
                     self.filter.set_table(&self.query_state.filter_state, table);
 
@@ -95,8 +422,7 @@ impl<'w, 's, Fe: FetchQuery, Fi: FilterQuery> Iterator for QueryIter<'w, 's, Fe,
                     self.current_table_len = table.len();
                 }
 
-                if !self.filter.matches() {
-                    println!("Filter does not match");
+                if !self.filter.matches(self.current_row) || !self.fetch.matches_row(self.current_row) {
                     self.current_row += 1;
                     continue;
                 }
@@ -108,4 +434,293 @@ impl<'w, 's, Fe: FetchQuery, Fi: FilterQuery> Iterator for QueryIter<'w, 's, Fe,
             }
         }
     }
-}
\ No newline at end of file
+
+    /// Exact for dense queries, otherwise bounded by the rows left in the matched tables
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let rows = self.remaining_rows();
+        if self.is_dense {
+            (rows, Some(rows))
+        }
+        else {
+            (0, Some(rows))
+        }
+    }
+}
+
+/// `QueryIter` of a dense query, see `Query::iter_dense`
+pub struct DenseQueryIter<'w, 's, Fe: FetchQuery, Fi: FilterQuery>(QueryIter<'w, 's, Fe, Fi>);
+
+impl<'w, 's, Fe: FetchQuery, Fi: FilterQuery> Iterator for DenseQueryIter<'w, 's, Fe, Fi> {
+    type Item = <<Fe as FetchQuery>::Fetch as Fetch<'w, 's>>::Item;
+
+    #[inline]
+    fn next(&mut self) -> Option<Self::Item> {
+        self.0.next()
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.0.size_hint()
+    }
+}
+
+impl<'w, 's, Fe: FetchQuery, Fi: FilterQuery> ExactSizeIterator for DenseQueryIter<'w, 's, Fe, Fi> {}
+
+/// See `Query::iter_combinations`
+pub struct QueryCombinationIter<'w, 's, Fe: FetchQuery, Fi: FilterQuery, const K: usize> {
+    world: &'w World,
+    query_state: &'s QueryState<Fe, Fi>,
+    /// (table id, row) of every item
+    rows: Vec<(usize, usize)>,
+    /// Indices into `rows` of the next combination, increasing
+    cursors: [usize; K],
+    done: bool,
+}
+
+impl<'w, 's, Fe: FetchQuery, Fi: FilterQuery, const K: usize> QueryCombinationIter<'w, 's, Fe, Fi, K> {
+    fn new(world: &'w World, query_state: &'s QueryState<Fe, Fi>) -> Self {
+        let mut rows = Vec::new();
+        let mut fetch = <Fe as FetchQuery>::Fetch::init(world);
+        let mut filter = <Fi as FilterQuery>::Filter::init(world);
+        for table_id in query_state.matched_table_ids(world) {
+            let table = world.get_tables().get_table(table_id).unwrap();
+            unsafe {
+                fetch.set_table(&query_state.fetch_state, table);
+                filter.set_table(&query_state.filter_state, table);
+                rows.extend((0..table.len())
+                    .filter(|row| filter.matches(*row) && fetch.matches_row(*row))
+                    .map(|row| (table_id, row)));
+            }
+        }
+
+        QueryCombinationIter {
+            world,
+            query_state,
+            done: K == 0 || rows.len() < K,
+            rows,
+            cursors: std::array::from_fn(|i| i),
+        }
+    }
+
+    /// Next combination, borrowing the iterator so that mutable items cannot alias
+    pub fn fetch_next(&mut self) -> Option<[<<Fe as FetchQuery>::Fetch as Fetch<'_, 's>>::Item; K]> {
+        if self.done {
+            return None;
+        }
+        let items = unsafe { self.fetch_unchecked(self.world) };
+        self.advance();
+        Some(items)
+    }
+
+    /// Items of the current combination, the cursors are distinct so are the rows
+    ///
+    /// # Safety
+    /// mutable items should not outlive the next call
+    unsafe fn fetch_unchecked<'a>(&self, world: &'a World) -> [<<Fe as FetchQuery>::Fetch as Fetch<'a, 's>>::Item; K] {
+        std::array::from_fn(|i| {
+            let (table_id, row) = self.rows[self.cursors[i]];
+            let mut fetch = <Fe as FetchQuery>::Fetch::init(world);
+            fetch.set_table(&self.query_state.fetch_state, world.get_tables().get_table(table_id).unwrap());
+            fetch.fetch_item_from_table(row)
+        })
+    }
+
+    /// Moves the cursors to the next combination in lexicographic order
+    fn advance(&mut self) {
+        let n = self.rows.len();
+        match (0..K).rev().find(|i| self.cursors[*i] < n - K + *i) {
+            Some(i) => {
+                self.cursors[i] += 1;
+                for j in i + 1..K {
+                    self.cursors[j] = self.cursors[j - 1] + 1;
+                }
+            },
+            None => self.done = true,
+        }
+    }
+}
+
+impl<'w, 's, Fe: ReadOnlyFetchQuery, Fi: FilterQuery, const K: usize> Iterator for QueryCombinationIter<'w, 's, Fe, Fi, K> {
+    type Item = [<<Fe as FetchQuery>::Fetch as Fetch<'w, 's>>::Item; K];
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.done {
+            return None;
+        }
+        // shared items can outlive the borrow of the iterator
+        let items = unsafe { self.fetch_unchecked(self.world) };
+        self.advance();
+        Some(items)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::ecs::World;
+    use crate::ecs::component::StorageType;
+    use crate::ecs::query::filter::{FilterQuery, With, Without};
+
+    use super::{Query, QueryState};
+
+    #[derive(Debug, PartialEq)]
+    struct Name(&'static str);
+    struct Health(u32);
+    struct Selected;
+
+    fn names<Fi: FilterQuery>(state: &QueryState<&Name, Fi>, world: &World) -> Vec<&'static str> {
+        let mut names: Vec<_> = Query::new(world, state).iter().map(|name| name.0).collect();
+        names.sort_unstable();
+        names
+    }
+
+    #[test]
+    fn sparse_set_components() {
+        let mut world = World::new();
+        world.add_component_with_storage::<Selected>(StorageType::SparseSet);
+
+        let a = world.spawn().insert(Name("a")).insert(Health(1)).id();
+        let b = world.spawn().insert(Name("b")).id();
+        let c = world.spawn().insert(Name("c")).insert(Health(3)).id();
+        let table_of = |world: &World, entity| world.get_entities().get(entity).unwrap().table_id;
+        let table_a = table_of(&world, a);
+
+        // toggling a sparse component does not move the entity
+        world.entity_mut(a).insert(Selected);
+        world.entity_mut(b).insert(Selected);
+        assert_eq!(table_a, table_of(&world, a));
+        assert!(world.entity(a).contains::<Selected>());
+        assert!(world.entity_mut(b).remove::<Selected>().is_some());
+        world.entity_mut(c).insert(Selected);
+
+        let selected = QueryState::<&Name, With<Selected>>::new(&mut world);
+        assert_eq!(vec!["a", "c"], names(&selected, &world));
+        let not_selected = QueryState::<&Name, Without<Selected>>::new(&mut world);
+        assert_eq!(vec!["b"], names(&not_selected, &world));
+        let without_health = QueryState::<&Name, Without<Health>>::new(&mut world);
+        assert_eq!(vec!["b"], names(&without_health, &world));
+
+        let selected_fetch = QueryState::<&Selected>::new(&mut world);
+        assert_eq!(2, Query::new(&world, &selected_fetch).iter().count());
+
+        world.despawn(c);
+        assert_eq!(vec!["a"], names(&selected, &world));
+        assert_eq!(1, world.get_sparse_sets().iter().map(|(_, set)| set.len()).sum::<usize>());
+    }
+
+    #[test]
+    fn par_for_each() {
+        use std::sync::atomic::{AtomicU32, AtomicUsize, Ordering};
+
+        let mut world = World::new();
+        world.spawn_batch((0..1000).map(|i| (Health(i),)));
+        world.spawn_batch((0..500).map(|i| (Health(i), Name("named"))));
+
+        let health = QueryState::<&Health>::new(&mut world);
+        let sum = AtomicU32::new(0);
+        Query::new(&world, &health).par_for_each(64, |health| {
+            sum.fetch_add(health.0, Ordering::Relaxed);
+        });
+        assert_eq!((0..1000).sum::<u32>() + (0..500).sum::<u32>(), sum.into_inner());
+
+        let named_health = QueryState::<&mut Health, With<Name>>::new(&mut world);
+        let visited = AtomicUsize::new(0);
+        Query::new(&world, &named_health).par_for_each_mut(7, |health| {
+            health.0 = 0;
+            visited.fetch_add(1, Ordering::Relaxed);
+        });
+        assert_eq!(500, visited.into_inner());
+        let sum: u32 = Query::new(&world, &health).iter().map(|health| health.0).sum();
+        assert_eq!((0..1000).sum::<u32>(), sum);
+    }
+
+    #[test]
+    fn for_each_and_chunks() {
+        use glam::Vec3;
+
+        let mut world = World::new();
+        world.add_component_with_storage::<Selected>(StorageType::SparseSet);
+        world.spawn_batch((0..10).map(|i| (Vec3::splat(i as f32),)));
+        let named = world.spawn_batch((0..5).map(|_| (Vec3::ONE, Name("named"))));
+        world.entity_mut(named[0]).insert(Selected);
+
+        let positions = QueryState::<&mut Vec3>::new(&mut world);
+        let mut query = Query::new(&world, &positions);
+        let mut chunk_lens = Vec::new();
+        query.for_each_chunk_mut(|positions: &mut [Vec3]| {
+            chunk_lens.push(positions.len());
+            for position in positions.iter_mut() {
+                *position += Vec3::X;
+            }
+        });
+        chunk_lens.sort_unstable();
+        assert_eq!(vec![5, 10], chunk_lens);
+
+        let mut sum = Vec3::ZERO;
+        query.for_each_mut(|position| sum += *position);
+        assert_eq!(Vec3::new(55.0 + 10.0, 45.0 + 5.0, 45.0 + 5.0), sum);
+
+        let selected = QueryState::<&Vec3, With<Selected>>::new(&mut world);
+        let mut count = 0;
+        Query::new(&world, &selected).for_each(|_| count += 1);
+        assert_eq!(1, count);
+    }
+
+    #[test]
+    fn chunks_of_sparse_query() {
+        let mut world = World::new();
+        world.add_component_with_storage::<Selected>(StorageType::SparseSet);
+        let entities = world.spawn_batch([(Name("a"),), (Name("b"),), (Name("c"),), (Name("d"),), (Name("e"),)]);
+        for entity in [entities[0], entities[2], entities[3]] {
+            world.entity_mut(entity).insert(Selected);
+        }
+
+        let state = QueryState::<&Name, With<Selected>>::new(&mut world);
+        let mut chunks = Vec::new();
+        Query::new(&world, &state).for_each_chunk(|names| chunks.push(names.iter().map(|name| name.0).collect::<Vec<_>>()));
+        assert_eq!(vec![vec!["a"], vec!["c", "d"]], chunks);
+
+        // sparse set values are not consecutive in the table
+        let state = QueryState::<&Selected>::new(&mut world);
+        let mut lens = Vec::new();
+        Query::new(&world, &state).for_each_chunk(|selected| lens.push(selected.len()));
+        assert_eq!(vec![1, 1, 1], lens);
+    }
+
+    #[test]
+    fn count_and_combinations() {
+        let mut world = World::new();
+        world.add_component_with_storage::<Selected>(StorageType::SparseSet);
+        let empty = QueryState::<&Name, With<Selected>>::new(&mut world);
+        assert!(Query::new(&world, &empty).is_empty());
+
+        let entities = world.spawn_batch([(Name("a"), Health(1)), (Name("b"), Health(2))]);
+        world.spawn_batch([(Name("c"),), (Name("d"),)]);
+        world.entity_mut(entities[1]).insert(Selected);
+
+        let all = QueryState::<&Name>::new(&mut world);
+        let query = Query::new(&world, &all);
+        assert_eq!(4, query.count());
+        let mut iter = query.iter_dense().unwrap();
+        iter.next();
+        assert_eq!(3, iter.len());
+        let selected = Query::new(&world, &empty);
+        assert!(selected.iter_dense().is_none());
+        assert_eq!((0, Some(4)), selected.iter().size_hint());
+        assert_eq!(1, selected.count());
+
+        let pairs: Vec<_> = query.iter_combinations::<2>()
+            .map(|[a, b]| format!("{}{}", a.0, b.0))
+            .collect();
+        assert_eq!(6, pairs.len());
+        assert!(pairs.iter().all(|pair| pair.as_bytes()[0] != pair.as_bytes()[1]));
+        assert_eq!(4, query.iter_combinations::<3>().count());
+        assert_eq!(0, query.iter_combinations::<5>().count());
+
+        let health = QueryState::<&mut Health>::new(&mut world);
+        let mut query = Query::new(&world, &health);
+        let mut combinations = query.iter_combinations_mut::<2>();
+        while let Some([a, b]) = combinations.fetch_next() {
+            std::mem::swap(&mut a.0, &mut b.0);
+        }
+        assert_eq!(2, world.get::<Health>(entities[0]).unwrap().0);
+    }
+}
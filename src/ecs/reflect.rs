@@ -0,0 +1,433 @@
+use std::{any::{Any, TypeId}, collections::HashMap};
+
+use super::{World, component::Component, entity::Entity, entity_ref::EntityMut, error::ReflectError, registry::short_type_name};
+
+pub use light_macros::Reflect;
+
+
+/// Name and type of a struct field
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FieldInfo {
+    pub name: &'static str,
+    pub type_name: &'static str,
+    pub typeid: TypeId,
+}
+
+impl FieldInfo {
+    pub fn new<T: Reflect>(name: &'static str) -> Self {
+        FieldInfo {
+            name,
+            type_name: std::any::type_name::<T>(),
+            typeid: TypeId::of::<T>(),
+        }
+    }
+}
+
+/// Static description of a reflected type, fields are empty for leaf values
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TypeInfo {
+    pub type_name: &'static str,
+    pub typeid: TypeId,
+    pub fields: Vec<FieldInfo>,
+}
+
+impl TypeInfo {
+    pub fn new<T: Reflect>(fields: Vec<FieldInfo>) -> Self {
+        TypeInfo {
+            type_name: std::any::type_name::<T>(),
+            typeid: TypeId::of::<T>(),
+            fields,
+        }
+    }
+
+    pub fn field(&self, name: &str) -> Option<&FieldInfo> {
+        self.fields.iter().find(|field| field.name == name)
+    }
+}
+
+/// Conversions implemented for every `Reflect` type
+pub trait ReflectAny: Any + Send + Sync {
+    fn as_any(&self) -> &dyn Any;
+    fn as_any_mut(&mut self) -> &mut dyn Any;
+    fn into_any(self: Box<Self>) -> Box<dyn Any>;
+    /// Replaces the value, gives `value` back if it is not of the same type
+    fn set(&mut self, value: Box<dyn Reflect>) -> Result<(), Box<dyn Reflect>>;
+}
+
+impl<T: Reflect> ReflectAny for T {
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn as_any_mut(&mut self) -> &mut dyn Any {
+        self
+    }
+
+    fn into_any(self: Box<Self>) -> Box<dyn Any> {
+        self
+    }
+
+    fn set(&mut self, value: Box<dyn Reflect>) -> Result<(), Box<dyn Reflect>> {
+        if !value.as_any().is::<T>() {
+            return Err(value);
+        }
+        *self = *value.into_any().downcast::<T>().unwrap();
+        Ok(())
+    }
+}
+
+/// Runtime access to the type name and fields of a value
+/// Structs get it with `#[derive(Reflect)]`
+pub trait Reflect: ReflectAny {
+    fn type_name(&self) -> &'static str {
+        std::any::type_name::<Self>()
+    }
+
+    fn type_info() -> TypeInfo where Self: Sized {
+        TypeInfo::new::<Self>(Vec::new())
+    }
+
+    fn field_names(&self) -> &'static [&'static str] {
+        &[]
+    }
+
+    fn field(&self, _name: &str) -> Option<&dyn Reflect> {
+        None
+    }
+
+    fn field_mut(&mut self, _name: &str) -> Option<&mut dyn Reflect> {
+        None
+    }
+}
+
+impl dyn Reflect {
+    pub fn is<T: Reflect>(&self) -> bool {
+        self.as_any().is::<T>()
+    }
+
+    pub fn downcast_ref<T: Reflect>(&self) -> Option<&T> {
+        self.as_any().downcast_ref::<T>()
+    }
+
+    pub fn downcast_mut<T: Reflect>(&mut self) -> Option<&mut T> {
+        self.as_any_mut().downcast_mut::<T>()
+    }
+
+    /// Field at a `.` separated path: `"transform.position.x"`
+    pub fn path(&self, path: &str) -> Result<&dyn Reflect, ReflectError> {
+        let mut value = self;
+        for (depth, name) in path.split('.').enumerate() {
+            value = value.field(name)
+                .ok_or_else(|| ReflectError::NoField { path: path.to_string(), depth })?;
+        }
+        Ok(value)
+    }
+
+    pub fn path_mut(&mut self, path: &str) -> Result<&mut dyn Reflect, ReflectError> {
+        let mut value = self;
+        for (depth, name) in path.split('.').enumerate() {
+            value = value.field_mut(name)
+                .ok_or_else(|| ReflectError::NoField { path: path.to_string(), depth })?;
+        }
+        Ok(value)
+    }
+
+    pub fn get_path<T: Reflect>(&self, path: &str) -> Result<&T, ReflectError> {
+        let value = self.path(path)?;
+        value.downcast_ref::<T>()
+            .ok_or_else(|| ReflectError::TypeMismatch {
+                path: path.to_string(),
+                expected: std::any::type_name::<T>(),
+                found: value.type_name(),
+            })
+    }
+
+    pub fn set_path<T: Reflect>(&mut self, path: &str, value: T) -> Result<(), ReflectError> {
+        self.path_mut(path)?
+            .set(Box::new(value))
+            .map_err(|value| ReflectError::TypeMismatch {
+                path: path.to_string(),
+                expected: value.type_name(),
+                found: self.path(path).map(|field| field.type_name()).unwrap_or_default(),
+            })
+    }
+}
+
+impl std::fmt::Debug for dyn Reflect {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Reflect({})", self.type_name())
+    }
+}
+
+macro_rules! impl_reflect_value {
+    ($($ty:ty),*) => {
+        $(impl Reflect for $ty {})*
+    };
+}
+
+impl_reflect_value!(bool, char, u8, u16, u32, u64, u128, usize, i8, i16, i32, i64, i128, isize, f32, f64, String);
+impl_reflect_value!(glam::Quat);
+
+macro_rules! impl_reflect_glam {
+    ($($ty:ty: $scalar:ty { $($field:ident),* }),*) => {
+        $(impl Reflect for $ty {
+            fn type_info() -> TypeInfo {
+                TypeInfo::new::<Self>(vec![$(FieldInfo::new::<$scalar>(stringify!($field))),*])
+            }
+
+            fn field_names(&self) -> &'static [&'static str] {
+                &[$(stringify!($field)),*]
+            }
+
+            fn field(&self, name: &str) -> Option<&dyn Reflect> {
+                match name {
+                    $(stringify!($field) => Some(&self.$field),)*
+                    _ => None,
+                }
+            }
+
+            fn field_mut(&mut self, name: &str) -> Option<&mut dyn Reflect> {
+                match name {
+                    $(stringify!($field) => Some(&mut self.$field),)*
+                    _ => None,
+                }
+            }
+        })*
+    };
+}
+
+impl_reflect_glam!(
+    glam::Vec2: f32 { x, y },
+    glam::Vec3: f32 { x, y, z },
+    glam::Vec4: f32 { x, y, z, w }
+);
+
+
+type InsertFn = fn(&mut EntityMut, Box<dyn Reflect>) -> Result<(), Box<dyn Reflect>>;
+
+/// Type erased access to a reflected component of an entity
+#[derive(Clone, Copy)]
+pub struct ReflectComponent {
+    insert: InsertFn,
+    reflect: fn(&World, Entity) -> Option<&dyn Reflect>,
+    reflect_mut: fn(&mut World, Entity) -> Option<&mut dyn Reflect>,
+}
+
+impl ReflectComponent {
+    pub fn of<T: Reflect + Component>() -> Self {
+        ReflectComponent {
+            insert: |entity, value| {
+                if !value.is::<T>() {
+                    return Err(value);
+                }
+                entity.insert(*value.into_any().downcast::<T>().unwrap());
+                Ok(())
+            },
+            reflect: |world, entity| world.get::<T>(entity).map(|value| value as &dyn Reflect),
+            reflect_mut: |world, entity| world.get_mut::<T>(entity).map(|value| value as &mut dyn Reflect),
+        }
+    }
+
+    /// Adds the component to the entity, gives `value` back if it is of another type
+    pub fn insert(&self, entity: &mut EntityMut, value: Box<dyn Reflect>) -> Result<(), Box<dyn Reflect>> {
+        (self.insert)(entity, value)
+    }
+
+    pub fn reflect<'w>(&self, world: &'w World, entity: Entity) -> Option<&'w dyn Reflect> {
+        (self.reflect)(world, entity)
+    }
+
+    pub fn reflect_mut<'w>(&self, world: &'w mut World, entity: Entity) -> Option<&'w mut dyn Reflect> {
+        (self.reflect_mut)(world, entity)
+    }
+}
+
+pub struct TypeRegistration {
+    info: TypeInfo,
+    short_name: &'static str,
+    component: Option<ReflectComponent>,
+}
+
+impl TypeRegistration {
+    pub fn of<T: Reflect>() -> Self {
+        TypeRegistration {
+            info: T::type_info(),
+            short_name: short_type_name::<T>(),
+            component: None,
+        }
+    }
+
+    pub fn of_component<T: Reflect + Component>() -> Self {
+        TypeRegistration {
+            component: Some(ReflectComponent::of::<T>()),
+            ..Self::of::<T>()
+        }
+    }
+
+    pub fn info(&self) -> &TypeInfo {
+        &self.info
+    }
+
+    pub fn short_name(&self) -> &'static str {
+        self.short_name
+    }
+
+    /// None if the type was not registered as a component
+    pub fn component(&self) -> Option<&ReflectComponent> {
+        self.component.as_ref()
+    }
+}
+
+/// Reflected types looked up by `TypeId` or name
+/// Names can be full (`light::ecs::Foo`) or without the module path (`Foo`)
+///
+/// Scene files do not go through it but through `ComponentRegistry`: `Reflect` reads and writes
+/// the fields of existing values, while loading builds whole values with serde,
+/// including components that do not implement `Reflect`
+#[derive(Default)]
+pub struct TypeRegistry {
+    registrations: Vec<TypeRegistration>,
+    names: HashMap<&'static str, usize>,
+    types: HashMap<TypeId, usize>,
+}
+
+impl TypeRegistry {
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    pub fn register<T: Reflect>(&mut self) -> &mut Self {
+        self.add_registration(TypeRegistration::of::<T>())
+    }
+
+    pub fn register_component<T: Reflect + Component>(&mut self) -> &mut Self {
+        self.add_registration(TypeRegistration::of_component::<T>())
+    }
+
+    fn add_registration(&mut self, registration: TypeRegistration) -> &mut Self {
+        let names = [registration.info.type_name, registration.short_name];
+        let typeid = registration.info.typeid;
+        let index = match self.types.get(&typeid) {
+            Some(index) => {
+                let old = &self.registrations[*index];
+                for name in [old.info.type_name, old.short_name] {
+                    // another type may have taken the short name since
+                    if self.names.get(name) == Some(index) {
+                        self.names.remove(name);
+                    }
+                }
+                self.registrations[*index] = registration;
+                *index
+            },
+            None => {
+                self.registrations.push(registration);
+                self.registrations.len() - 1
+            }
+        };
+        for name in names {
+            self.names.insert(name, index);
+        }
+        self.types.insert(typeid, index);
+        self
+    }
+
+    pub fn get(&self, typeid: TypeId) -> Option<&TypeRegistration> {
+        self.registrations.get(*self.types.get(&typeid)?)
+    }
+
+    pub fn get_by_name(&self, name: &str) -> Option<&TypeRegistration> {
+        self.registrations.get(*self.names.get(name)?)
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &TypeRegistration> {
+        self.registrations.iter()
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use std::any::TypeId;
+
+    use crate::ecs::World;
+    use crate::ecs::error::ReflectError;
+
+    use super::{Reflect, TypeRegistry};
+
+    #[derive(Debug, PartialEq, Reflect)]
+    struct Transform {
+        position: glam::Vec3,
+        scale: f32,
+    }
+
+    #[derive(Debug, PartialEq, Reflect)]
+    struct Player {
+        name: String,
+        transform: Transform,
+    }
+
+    #[derive(Debug, PartialEq, Reflect)]
+    struct Health(u32, u32);
+
+    #[test]
+    fn fields_and_paths() {
+        let mut player = Player {
+            name: "player".to_string(),
+            transform: Transform { position: glam::Vec3::new(1.0, 2.0, 3.0), scale: 1.0 },
+        };
+        let value: &mut dyn Reflect = &mut player;
+
+        assert_eq!(&["name", "transform"], value.field_names());
+        assert_eq!(Ok(&2.0), value.get_path::<f32>("transform.position.y"));
+        value.set_path("transform.position.x", 5.0f32).unwrap();
+        value.set_path("name", "renamed".to_string()).unwrap();
+
+        assert_eq!(Err(ReflectError::NoField { path: "transform.rotation.x".to_string(), depth: 1 }),
+            value.set_path("transform.rotation.x", 0.0f32));
+        assert!(matches!(value.set_path("transform.scale", 2u32), Err(ReflectError::TypeMismatch { .. })));
+
+        assert_eq!(glam::Vec3::new(5.0, 2.0, 3.0), player.transform.position);
+        assert_eq!("renamed", player.name);
+
+        let info = <Player as Reflect>::type_info();
+        assert_eq!(TypeId::of::<Transform>(), info.field("transform").unwrap().typeid);
+        assert_eq!(std::any::type_name::<String>(), info.field("name").unwrap().type_name);
+    }
+
+    #[test]
+    fn registering_again_replaces_names() {
+        mod other {
+            #[derive(crate::ecs::reflect::Reflect)]
+            pub struct Health(pub u32);
+        }
+
+        let mut registry = TypeRegistry::new();
+        registry.register::<Health>()
+            .register::<other::Health>();
+        assert_eq!(TypeId::of::<other::Health>(), registry.get_by_name("Health").unwrap().info().typeid);
+
+        registry.register_component::<Health>();
+        assert_eq!(2, registry.iter().count());
+        assert!(registry.get_by_name("Health").unwrap().component().is_some());
+        assert!(registry.get_by_name(std::any::type_name::<other::Health>()).is_some());
+    }
+
+    #[test]
+    fn reflect_component() {
+        let mut registry = TypeRegistry::new();
+        registry.register_component::<Health>()
+            .register::<Player>();
+
+        let mut world = World::new();
+        let entity = world.spawn().id();
+
+        let health = registry.get_by_name("Health").unwrap().component().unwrap();
+        assert!(registry.get(TypeId::of::<Player>()).unwrap().component().is_none());
+        assert!(health.insert(&mut world.entity_mut(entity), Box::new(3u32)).is_err());
+        health.insert(&mut world.entity_mut(entity), Box::new(Health(10, 20))).unwrap();
+
+        health.reflect_mut(&mut world, entity).unwrap().set_path("1", 30u32).unwrap();
+        assert_eq!(Ok(&10), health.reflect(&world, entity).unwrap().get_path::<u32>("0"));
+        assert_eq!(Some(&Health(10, 30)), world.get::<Health>(entity));
+    }
+}
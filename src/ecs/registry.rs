@@ -0,0 +1,147 @@
+use std::{any::{Any, TypeId}, collections::HashMap};
+
+use serde::{Serialize, de::DeserializeOwned};
+
+use super::{component::Component, entity_ref::EntityMut};
+
+
+type SerializeFn = unsafe fn(*const u8) -> Result<serde_yaml::Value, serde_yaml::Error>;
+
+/// Type erased constructors of a registered component
+pub struct ComponentRegistration {
+    name: String,
+    typeid: TypeId,
+    deserialize_yaml: fn(serde_yaml::Value) -> Result<Box<dyn Any>, serde_yaml::Error>,
+    insert_boxed: fn(&mut EntityMut, Box<dyn Any>),
+    serialize_yaml: Option<SerializeFn>,
+}
+
+/// # Safety
+/// `ptr` should point to a valid `T`
+unsafe fn serialize_ptr<T: Serialize>(ptr: *const u8) -> Result<serde_yaml::Value, serde_yaml::Error> {
+    serde_yaml::to_value(&*ptr.cast::<T>())
+}
+
+impl ComponentRegistration {
+    pub fn of<T: Component + DeserializeOwned>(name: &str) -> Self {
+        ComponentRegistration {
+            name: name.to_string(),
+            typeid: TypeId::of::<T>(),
+            deserialize_yaml: |value| Ok(Box::new(serde_yaml::from_value::<T>(value)?)),
+            insert_boxed: |entity, value| {
+                entity.insert(*value.downcast::<T>().unwrap());
+            },
+            serialize_yaml: None,
+        }
+    }
+
+    pub fn of_serializable<T: Component + Serialize + DeserializeOwned>(name: &str) -> Self {
+        ComponentRegistration {
+            serialize_yaml: Some(serialize_ptr::<T>),
+            ..Self::of::<T>(name)
+        }
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn typeid(&self) -> TypeId {
+        self.typeid
+    }
+
+    pub fn deserialize_yaml(&self, value: serde_yaml::Value) -> Result<Box<dyn Any>, serde_yaml::Error> {
+        (self.deserialize_yaml)(value)
+    }
+
+    pub fn is_serializable(&self) -> bool {
+        self.serialize_yaml.is_some()
+    }
+
+    /// None if the component is not serializable
+    ///
+    /// # Safety
+    /// `ptr` should point to a valid value of the registered type
+    pub unsafe fn serialize_yaml(&self, ptr: *const u8) -> Option<Result<serde_yaml::Value, serde_yaml::Error>> {
+        self.serialize_yaml.map(|serialize| serialize(ptr))
+    }
+
+    /// `value` should be a box returned by `ComponentRegistration::deserialize_yaml`
+    pub fn insert_boxed(&self, entity: &mut EntityMut, value: Box<dyn Any>) {
+        (self.insert_boxed)(entity, value)
+    }
+}
+
+/// Name -> Component type lookup used by scene files
+/// Kept apart from `TypeRegistry`, which only reflects the fields of existing values
+#[derive(Default)]
+pub struct ComponentRegistry {
+    registrations: Vec<ComponentRegistration>,
+    names: HashMap<String, usize>,
+    types: HashMap<TypeId, usize>,
+}
+
+impl ComponentRegistry {
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    /// Registers `T` under its type name without the module path
+    pub fn register<T: Component + DeserializeOwned>(&mut self) -> &mut Self {
+        self.register_with_name::<T>(short_type_name::<T>())
+    }
+
+    pub fn register_with_name<T: Component + DeserializeOwned>(&mut self, name: &str) -> &mut Self {
+        self.add_registration(ComponentRegistration::of::<T>(name))
+    }
+
+    /// Registers `T` so that it can also be saved to scene files
+    pub fn register_serializable<T: Component + Serialize + DeserializeOwned>(&mut self) -> &mut Self {
+        self.register_serializable_with_name::<T>(short_type_name::<T>())
+    }
+
+    pub fn register_serializable_with_name<T: Component + Serialize + DeserializeOwned>(&mut self, name: &str) -> &mut Self {
+        self.add_registration(ComponentRegistration::of_serializable::<T>(name))
+    }
+
+    fn add_registration(&mut self, registration: ComponentRegistration) -> &mut Self {
+        let name = registration.name.clone();
+        let typeid = registration.typeid;
+        let index = match self.types.get(&registration.typeid) {
+            Some(index) => {
+                self.names.remove(&self.registrations[*index].name);
+                self.registrations[*index] = registration;
+                *index
+            },
+            None => {
+                self.registrations.push(registration);
+                self.registrations.len() - 1
+            }
+        };
+        self.names.insert(name, index);
+        self.types.insert(typeid, index);
+        self
+    }
+
+    pub fn get(&self, name: &str) -> Option<&ComponentRegistration> {
+        self.registrations.get(*self.names.get(name)?)
+    }
+
+    pub fn get_by_type(&self, typeid: TypeId) -> Option<&ComponentRegistration> {
+        self.registrations.get(*self.types.get(&typeid)?)
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &ComponentRegistration> {
+        self.registrations.iter()
+    }
+}
+
+/// `light::ecs::Foo<light::ecs::Bar>` -> `Foo<light::ecs::Bar>`
+pub fn short_type_name<T: ?Sized>() -> &'static str {
+    let full_name = std::any::type_name::<T>();
+    let end = full_name.find('<').unwrap_or(full_name.len());
+    match full_name[..end].rfind("::") {
+        Some(index) => &full_name[index + 2..],
+        None => full_name,
+    }
+}
@@ -0,0 +1,154 @@
+use std::{collections::HashMap, marker::PhantomData};
+
+use super::{component::{Component, ComponentId}, entity::Entity};
+
+
+/// Entities that lost a component, in removal order
+/// Entries are kept until the second `World::clear_trackers` after they were recorded,
+/// so readers running once per frame see every removal
+#[derive(Default)]
+struct RemovedLog {
+    entities: Vec<Entity>,
+    /// Number of entries dropped from the front since the log was created
+    start: usize,
+    /// Number of entries recorded before the last `clear`
+    previous_len: usize,
+}
+
+impl RemovedLog {
+    fn since(&self, last_read: usize) -> &[Entity] {
+        let skip = last_read.saturating_sub(self.start).min(self.entities.len());
+        &self.entities[skip..]
+    }
+
+    fn end(&self) -> usize {
+        self.start + self.entities.len()
+    }
+
+    fn clear(&mut self) {
+        self.entities.drain(..self.previous_len);
+        self.start += self.previous_len;
+        self.previous_len = self.entities.len();
+    }
+}
+
+/// Removal logs of every component, written by the world
+#[derive(Default)]
+pub struct RemovedComponentEvents {
+    logs: HashMap<ComponentId, RemovedLog>,
+}
+
+impl RemovedComponentEvents {
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    pub(crate) fn send(&mut self, component_id: &ComponentId, entity: Entity) {
+        self.logs.entry(component_id.clone())
+            .or_default()
+            .entities
+            .push(entity);
+    }
+
+    /// Entities that lost the component since the reader was at `last_read`,
+    /// and the position of the reader after them
+    pub fn since(&self, component_id: &ComponentId, last_read: usize) -> (&[Entity], usize) {
+        match self.logs.get(component_id) {
+            Some(log) => (log.since(last_read), log.end()),
+            None => (&[], last_read),
+        }
+    }
+
+    /// Drops the entries recorded before the previous call
+    pub fn clear(&mut self) {
+        for log in self.logs.values_mut() {
+            log.clear();
+        }
+    }
+}
+
+/// Entities that lost `T` since the system last ran, by removal or despawn
+/// An entity is listed once per removal and can be despawned or have `T` again
+/// The next runs of the system do not list them again, even if this run did not read them
+///
+/// `fn release_bodies(removed: RemovedComponents<RigidBody>) { for entity in removed.iter() { .. } }`
+pub struct RemovedComponents<'w, T: Component> {
+    removed: &'w [Entity],
+    marker: PhantomData<fn() -> T>,
+}
+
+impl<'w, T: Component> RemovedComponents<'w, T> {
+    pub(crate) fn new(removed: &'w [Entity]) -> Self {
+        RemovedComponents {
+            removed,
+            marker: PhantomData,
+        }
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = Entity> + 'w {
+        self.removed.iter().copied()
+    }
+
+    pub fn len(&self) -> usize {
+        self.removed.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.removed.is_empty()
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use crate::ecs::World;
+    use crate::ecs::entity::Entity;
+    use crate::ecs::system::{IntoSystem, System};
+
+    use super::RemovedComponents;
+
+    struct Body;
+
+    fn release(removed: RemovedComponents<Body>) -> Vec<Entity> {
+        removed.iter().collect()
+    }
+
+    fn any_removed(removed: RemovedComponents<Body>) -> bool {
+        !removed.is_empty()
+    }
+
+    #[test]
+    fn removed_since_last_run() {
+        let mut world = World::new();
+        let mut system = release.system();
+        system.initialize(&mut world);
+
+        let [a, b, c] = [(); 3].map(|_| world.spawn().insert(Body).id());
+        world.entity_mut(a).remove::<Body>();
+        world.despawn(b);
+        assert_eq!(vec![a, b], unsafe { system.run(&world, ()) });
+
+        // listed removals are not listed again, unlisted ones survive one clear
+        world.clear_trackers();
+        world.despawn(c);
+        world.clear_trackers();
+        assert_eq!(vec![c], unsafe { system.run(&world, ()) });
+
+        world.clear_trackers();
+        world.clear_trackers();
+        assert!(unsafe { system.run(&world, ()) }.is_empty());
+    }
+
+    #[test]
+    fn checking_is_empty_reads_the_removals() {
+        let mut world = World::new();
+        let mut system = any_removed.system();
+        system.initialize(&mut world);
+
+        let body = world.spawn().insert(Body).id();
+        assert!(!unsafe { system.run(&world, ()) });
+        world.entity_mut(body).remove::<Body>();
+        assert!(unsafe { system.run(&world, ()) });
+        assert!(!unsafe { system.run(&world, ()) });
+    }
+}
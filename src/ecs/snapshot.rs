@@ -0,0 +1,400 @@
+use std::collections::HashMap;
+
+use super::{World, component::{ComponentDescriptor, ComponentId, EqFn}, entity::{AllocatorState, Entity}, storage::table::Column};
+
+
+/// Cloned values of a component
+struct SnapshotColumn {
+    component_id: ComponentId,
+    eq: EqFn,
+    values: Column,
+}
+
+impl SnapshotColumn {
+    /// # Safety
+    /// `values` should yield valid pointers to values of the cloneable component
+    unsafe fn cloned(descriptor: &ComponentDescriptor, values: impl ExactSizeIterator<Item = *const u8>) -> Self {
+        let clone = descriptor.clone.unwrap();
+        let mut column = Column::with_capacity(descriptor, values.len());
+        for value in values {
+            let row = column.push_uninit();
+            clone(value, column.get_unchecked(row));
+        }
+        SnapshotColumn {
+            component_id: descriptor.id.clone(),
+            eq: descriptor.eq.unwrap(),
+            values: column,
+        }
+    }
+}
+
+/// Cloned rows of a table, or of a sparse set
+struct SnapshotTable {
+    entities: Vec<Entity>,
+    columns: Vec<SnapshotColumn>,
+}
+
+/// Values of the cloneable components and resources of a world at one point in time
+/// Components and resources are cloneable once registered with `World::register_cloneable`
+/// and `World::register_cloneable_resource`
+///
+/// Only valid for the world it was taken from, component ids are not portable
+pub struct Snapshot {
+    /// Alive entities in id order, with or without cloneable components
+    entities: Vec<Entity>,
+    tables: Vec<SnapshotTable>,
+    /// Table index and row of the values of each entity
+    locations: HashMap<Entity, Vec<(usize, usize)>>,
+    resources: Vec<SnapshotColumn>,
+    /// Restored as is, so that spawns after a restore get the handles they got after the snapshot
+    allocator: AllocatorState,
+}
+
+impl Snapshot {
+    /// See `World::snapshot`
+    pub fn of(world: &World) -> Self {
+        let components = world.get_components();
+        let cloneable = |component_id: &ComponentId| components.get_descriptor(component_id)
+            .filter(|descriptor| descriptor.is_cloneable());
+
+        let mut tables = Vec::new();
+        for table in world.get_tables().iter().filter(|table| !table.is_empty()) {
+            let columns: Vec<SnapshotColumn> = table.component_ids()
+                .iter()
+                .filter_map(&cloneable)
+                .map(|descriptor| {
+                    let column = table.get_column(&descriptor.id).unwrap();
+                    unsafe {
+                        SnapshotColumn::cloned(descriptor, (0..table.len()).map(|row| column.get_unchecked(row) as *const u8))
+                    }
+                })
+                .collect();
+            if !columns.is_empty() {
+                tables.push(SnapshotTable {
+                    entities: table.entities().to_vec(),
+                    columns,
+                });
+            }
+        }
+
+        let sparse_sets = world.get_sparse_sets().iter()
+            .filter(|(_, sparse_set)| !sparse_set.is_empty())
+            .filter_map(|(component_id, sparse_set)| Some((cloneable(component_id)?, sparse_set)));
+        for (descriptor, sparse_set) in sparse_sets {
+            let column = sparse_set.column();
+            tables.push(SnapshotTable {
+                entities: sparse_set.entities().to_vec(),
+                columns: vec![unsafe {
+                    SnapshotColumn::cloned(descriptor, (0..column.len()).map(|row| column.get_unchecked(row) as *const u8))
+                }],
+            });
+        }
+
+        let entities: Vec<Entity> = world.get_entities().iter().collect();
+        let mut locations: HashMap<Entity, Vec<(usize, usize)>> = entities.iter()
+            .map(|entity| (*entity, Vec::new()))
+            .collect();
+        for (table_index, table) in tables.iter().enumerate() {
+            for (row, entity) in table.entities.iter().enumerate() {
+                locations.get_mut(entity).unwrap().push((table_index, row));
+            }
+        }
+
+        let resource_table = world.get_resource_table();
+        let resources = components.descriptors()
+            .iter()
+            .filter(|descriptor| descriptor.is_cloneable())
+            .filter_map(|descriptor| Some((descriptor, resource_table.get_resource(&descriptor.id)?)))
+            .map(|(descriptor, value)| unsafe {
+                SnapshotColumn::cloned(descriptor, std::iter::once(value))
+            })
+            .collect();
+
+        Snapshot {
+            entities,
+            tables,
+            locations,
+            resources,
+            allocator: world.get_entities().allocator_state(),
+        }
+    }
+
+    /// Alive entities when the snapshot was taken, in id order
+    pub fn entities(&self) -> &[Entity] {
+        &self.entities
+    }
+
+    pub fn contains(&self, entity: Entity) -> bool {
+        self.locations.contains_key(&entity)
+    }
+
+    /// Cloned value of the component of the entity
+    pub fn get_by_id(&self, entity: Entity, component_id: &ComponentId) -> Option<*const u8> {
+        self.values_of(entity)
+            .find(|(column, _)| column.component_id == *component_id)
+            .map(|(_, value)| value)
+    }
+
+    pub fn get_resource_by_id(&self, resource_id: &ComponentId) -> Option<*const u8> {
+        self.resources.iter()
+            .find(|column| column.component_id == *resource_id)
+            .map(|column| unsafe { column.values.get_unchecked(0) as *const u8 })
+    }
+
+    fn values_of(&self, entity: Entity) -> impl Iterator<Item = (&SnapshotColumn, *const u8)> {
+        self.locations.get(&entity)
+            .map(|locations| &locations[..])
+            .unwrap_or_default()
+            .iter()
+            .flat_map(move |(table_index, row)| self.tables[*table_index].columns.iter()
+                .map(move |column| (column, unsafe { column.values.get_unchecked(*row) as *const u8 })))
+    }
+
+    /// What changed from `self` to `newer`
+    pub fn diff(&self, newer: &Snapshot) -> SnapshotDiff {
+        let mut diff = SnapshotDiff {
+            added: newer.entities.iter()
+                .filter(|entity| !self.contains(**entity))
+                .copied()
+                .collect(),
+            ..Default::default()
+        };
+
+        for entity in self.entities.iter().copied() {
+            if !newer.contains(entity) {
+                diff.removed.push(entity);
+                continue;
+            }
+            let mut old: Vec<_> = self.values_of(entity).collect();
+            let mut new: Vec<_> = newer.values_of(entity).collect();
+            old.sort_by_key(|(column, _)| column.component_id.clone());
+            new.sort_by_key(|(column, _)| column.component_id.clone());
+            diff.changed.extend(changes(&old, &new).map(|(component_id, kind)| ComponentChange {
+                entity,
+                component_id,
+                kind,
+            }));
+        }
+
+        let old: Vec<_> = self.resources.iter()
+            .map(|column| (column, unsafe { column.values.get_unchecked(0) as *const u8 }))
+            .collect();
+        let new: Vec<_> = newer.resources.iter()
+            .map(|column| (column, unsafe { column.values.get_unchecked(0) as *const u8 }))
+            .collect();
+        diff.changed_resources = changes(&old, &new).collect();
+        diff
+    }
+
+    /// See `World::restore`
+    pub(crate) fn restore(&self, world: &mut World) {
+        let alive: Vec<Entity> = world.entities.iter().collect();
+        for entity in alive {
+            if !self.contains(entity) {
+                world.despawn(entity);
+            }
+        }
+        for entity in self.entities.iter() {
+            if !world.contains_entity(*entity) {
+                world.entities.alloc_at(*entity);
+                world.add_to_empty_table(*entity);
+            }
+        }
+        world.entities.set_allocator_state(&self.allocator);
+
+        let cloneable: Vec<ComponentDescriptor> = world.components.descriptors()
+            .iter()
+            .filter(|descriptor| descriptor.is_cloneable())
+            .cloned()
+            .collect();
+        for entity in self.entities.iter().copied() {
+            for descriptor in cloneable.iter() {
+                let current = world.get_by_id(entity, &descriptor.id);
+                match self.get_by_id(entity, &descriptor.id) {
+                    Some(value) => unsafe {
+                        if current.map(|current| (descriptor.eq.unwrap())(current, value)).unwrap_or(false) {
+                            continue;
+                        }
+                        with_clone(descriptor, value, |clone| {
+                            world.insert_by_id(entity, &descriptor.id, clone);
+                        });
+                    },
+                    None if current.is_some() => {
+                        world.remove_by_id(entity, &descriptor.id);
+                    },
+                    None => {},
+                }
+            }
+        }
+
+        for descriptor in cloneable.iter() {
+            let current = world.resources.get_resource(&descriptor.id);
+            match self.get_resource_by_id(&descriptor.id) {
+                Some(value) => unsafe {
+                    if current.map(|current| (descriptor.eq.unwrap())(current, value)).unwrap_or(false) {
+                        continue;
+                    }
+                    with_clone(descriptor, value, |clone| {
+                        world.resources.insert_resource_unchecked(descriptor, clone);
+                    });
+                },
+                None if current.is_some() => unsafe {
+                    world.resources.remove_and_drop_unchecked(&descriptor.id);
+                    world.resources.remove_column(&descriptor.id);
+                },
+                None => {},
+            }
+        }
+    }
+}
+
+/// Clones `value` into a temporary buffer and hands it to `insert`, which takes ownership of the clone
+///
+/// # Safety
+/// `value` should point to a valid value of the cloneable component
+unsafe fn with_clone(descriptor: &ComponentDescriptor, value: *const u8, insert: impl FnOnce(*mut u8)) {
+    let mut buffer = Column::with_capacity(descriptor, 1);
+    let row = buffer.push_uninit();
+    (descriptor.clone.unwrap())(value, buffer.get_unchecked(row));
+    insert(buffer.get_unchecked(row));
+    buffer.swap_remove_and_forget_unchecked(row);
+}
+
+/// Differences of two lists of values sorted by component id
+fn changes<'a>(old: &'a [(&SnapshotColumn, *const u8)], new: &'a [(&SnapshotColumn, *const u8)]) -> impl Iterator<Item = (ComponentId, ChangeKind)> + 'a {
+    let removed = old.iter()
+        .filter(|(old_column, _)| !new.iter().any(|(new_column, _)| new_column.component_id == old_column.component_id))
+        .map(|(column, _)| (column.component_id.clone(), ChangeKind::Removed));
+    let inserted_or_modified = new.iter().filter_map(|(new_column, new_value)| {
+        match old.iter().find(|(old_column, _)| old_column.component_id == new_column.component_id) {
+            None => Some((new_column.component_id.clone(), ChangeKind::Inserted)),
+            Some((_, old_value)) if !unsafe { (new_column.eq)(*old_value, *new_value) } =>
+                Some((new_column.component_id.clone(), ChangeKind::Modified)),
+            Some(_) => None,
+        }
+    });
+    removed.chain(inserted_or_modified)
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ChangeKind {
+    Inserted,
+    Removed,
+    Modified,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ComponentChange {
+    pub entity: Entity,
+    pub component_id: ComponentId,
+    pub kind: ChangeKind,
+}
+
+/// Result of `Snapshot::diff`, entities in id order
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct SnapshotDiff {
+    pub added: Vec<Entity>,
+    pub removed: Vec<Entity>,
+    /// Cloneable components of entities alive in both snapshots
+    pub changed: Vec<ComponentChange>,
+    pub changed_resources: Vec<(ComponentId, ChangeKind)>,
+}
+
+impl SnapshotDiff {
+    pub fn is_empty(&self) -> bool {
+        self.added.is_empty() && self.removed.is_empty() && self.changed.is_empty() && self.changed_resources.is_empty()
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use crate::ecs::World;
+    use crate::ecs::component::StorageType;
+
+    use super::{ChangeKind, ComponentChange};
+
+    #[derive(Debug, Clone, PartialEq)]
+    struct Position(f32, f32);
+    #[derive(Debug, Clone, PartialEq)]
+    struct Name(String);
+    #[derive(Debug, Clone, PartialEq)]
+    struct Stunned;
+    #[derive(Debug, PartialEq)]
+    struct Handle(u32);
+    #[derive(Debug, Clone, PartialEq)]
+    struct Tick(u64);
+
+    #[test]
+    fn snapshot_diff_restore() {
+        let mut world = World::new();
+        let position = world.register_cloneable::<Position>();
+        let name = world.register_cloneable::<Name>();
+        world.add_component_with_storage::<Stunned>(StorageType::SparseSet);
+        let stunned = world.register_cloneable::<Stunned>();
+        let tick = world.register_cloneable_resource::<Tick>();
+        world.insert_resource(Tick(1));
+
+        let a = world.spawn().insert(Position(0.0, 0.0)).insert(Name("a".to_string())).insert(Handle(7)).id();
+        let b = world.spawn().insert(Position(1.0, 1.0)).insert(Stunned).id();
+        let c = world.spawn().id();
+        let before = world.snapshot();
+
+        world.get_mut::<Position>(a).unwrap().0 = 5.0;
+        world.entity_mut(a).remove::<Name>();
+        world.entity_mut(b).remove::<Stunned>();
+        world.despawn(c);
+        let d = world.spawn().insert(Position(2.0, 2.0)).id();
+        world.insert_resource(Tick(2));
+
+        let diff = before.diff(&world.snapshot());
+        assert_eq!((vec![d], vec![c]), (diff.added.clone(), diff.removed.clone()));
+        assert_eq!(vec![
+            ComponentChange { entity: a, component_id: name, kind: ChangeKind::Removed },
+            ComponentChange { entity: a, component_id: position.clone(), kind: ChangeKind::Modified },
+            ComponentChange { entity: b, component_id: stunned, kind: ChangeKind::Removed },
+        ], diff.changed);
+        assert_eq!(vec![(tick, ChangeKind::Modified)], diff.changed_resources);
+
+        world.restore(&before);
+        assert_eq!(Some(&Position(0.0, 0.0)), world.get::<Position>(a));
+        assert_eq!(Some(&Name("a".to_string())), world.get::<Name>(a));
+        // not cloneable, left as is
+        assert_eq!(Some(&Handle(7)), world.get::<Handle>(a));
+        assert_eq!(Some(&Stunned), world.get::<Stunned>(b));
+        assert!(world.contains_entity(c) && !world.contains_entity(d));
+        assert_eq!(Some(&Tick(1)), world.get_resource::<Tick>());
+        assert!(before.diff(&world.snapshot()).is_empty());
+
+        // restored entities keep working
+        world.entity_mut(c).insert(Position(3.0, 3.0));
+        let e = world.spawn().id();
+        assert_ne!(e, c);
+        assert_eq!(4, world.get_entities().len());
+    }
+
+    #[test]
+    fn spawns_after_restore_repeat() {
+        let mut world = World::new();
+        world.register_cloneable::<Position>();
+        let [a, b, c] = [(); 3].map(|_| world.spawn().insert(Tick(0)).id());
+        world.despawn(b);
+        let before = world.snapshot();
+
+        let simulate = |world: &mut World| {
+            world.despawn(a);
+            let d = world.spawn().insert(Tick(1)).id();
+            let e = world.spawn().insert(Position(1.0, 1.0)).insert(Tick(2)).id();
+            world.despawn(c);
+            let f = world.spawn().id();
+            ([d, e, f], world.checksum())
+        };
+        let first = simulate(&mut world);
+        world.restore(&before);
+        let second = simulate(&mut world);
+        assert_eq!(first, second);
+
+        world.restore(&before);
+        assert_eq!(b.id, world.spawn().id().id);
+    }
+}
@@ -0,0 +1,306 @@
+use glam::{Quat, Vec2, Vec3, Vec4};
+
+pub use light_macros::StableHash;
+
+use super::{World, component::ComponentDescriptor, entity::Entity};
+
+
+/// FNV-1a hasher giving the same result on every platform and run,
+/// unlike `DefaultHasher` which is randomly seeded and hashes `usize` natively
+pub struct StableHasher {
+    state: u64,
+}
+
+impl StableHasher {
+    const OFFSET_BASIS: u64 = 0xcbf2_9ce4_8422_2325;
+    const PRIME: u64 = 0x0000_0100_0000_01b3;
+
+    pub fn new() -> Self {
+        StableHasher {
+            state: Self::OFFSET_BASIS,
+        }
+    }
+
+    pub fn write(&mut self, bytes: &[u8]) {
+        for byte in bytes {
+            self.state ^= *byte as u64;
+            self.state = self.state.wrapping_mul(Self::PRIME);
+        }
+    }
+
+    #[inline]
+    pub fn write_u32(&mut self, value: u32) {
+        self.write(&value.to_le_bytes());
+    }
+
+    #[inline]
+    pub fn write_u64(&mut self, value: u64) {
+        self.write(&value.to_le_bytes());
+    }
+
+    pub fn finish(&self) -> u64 {
+        self.state
+    }
+}
+
+impl Default for StableHasher {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Hash that only depends on the value, for desync checks between machines
+/// Floats are hashed by their bits: `0.0` and `-0.0` differ, as do NaNs with different payloads
+///
+/// `#[derive(StableHash)]` hashes the fields in declaration order
+pub trait StableHash {
+    fn stable_hash(&self, hasher: &mut StableHasher);
+}
+
+/// # Safety
+/// `ptr` should point to a valid `T`
+pub unsafe fn stable_hash_ptr<T: StableHash>(ptr: *const u8, hasher: &mut StableHasher) {
+    (*ptr.cast::<T>()).stable_hash(hasher);
+}
+
+pub type StableHashFn = unsafe fn(*const u8, &mut StableHasher);
+
+macro_rules! impl_stable_hash_le_bytes {
+    ($($ty:ty),*) => {
+        $(
+            impl StableHash for $ty {
+                #[inline]
+                fn stable_hash(&self, hasher: &mut StableHasher) {
+                    hasher.write(&self.to_le_bytes());
+                }
+            }
+        )*
+    };
+}
+
+impl_stable_hash_le_bytes!(u8, u16, u32, u64, u128, i8, i16, i32, i64, i128);
+
+impl StableHash for usize {
+    #[inline]
+    fn stable_hash(&self, hasher: &mut StableHasher) {
+        hasher.write_u64(*self as u64);
+    }
+}
+
+impl StableHash for isize {
+    #[inline]
+    fn stable_hash(&self, hasher: &mut StableHasher) {
+        (*self as i64).stable_hash(hasher);
+    }
+}
+
+impl StableHash for bool {
+    #[inline]
+    fn stable_hash(&self, hasher: &mut StableHasher) {
+        hasher.write(&[*self as u8]);
+    }
+}
+
+impl StableHash for char {
+    #[inline]
+    fn stable_hash(&self, hasher: &mut StableHasher) {
+        hasher.write_u32(*self as u32);
+    }
+}
+
+impl StableHash for f32 {
+    #[inline]
+    fn stable_hash(&self, hasher: &mut StableHasher) {
+        hasher.write_u32(self.to_bits());
+    }
+}
+
+impl StableHash for f64 {
+    #[inline]
+    fn stable_hash(&self, hasher: &mut StableHasher) {
+        hasher.write_u64(self.to_bits());
+    }
+}
+
+impl StableHash for str {
+    fn stable_hash(&self, hasher: &mut StableHasher) {
+        hasher.write_u64(self.len() as u64);
+        hasher.write(self.as_bytes());
+    }
+}
+
+impl StableHash for String {
+    fn stable_hash(&self, hasher: &mut StableHasher) {
+        self.as_str().stable_hash(hasher);
+    }
+}
+
+impl<T: StableHash> StableHash for [T] {
+    fn stable_hash(&self, hasher: &mut StableHasher) {
+        hasher.write_u64(self.len() as u64);
+        for item in self {
+            item.stable_hash(hasher);
+        }
+    }
+}
+
+impl<T: StableHash, const N: usize> StableHash for [T; N] {
+    fn stable_hash(&self, hasher: &mut StableHasher) {
+        for item in self {
+            item.stable_hash(hasher);
+        }
+    }
+}
+
+impl<T: StableHash> StableHash for Vec<T> {
+    fn stable_hash(&self, hasher: &mut StableHasher) {
+        self.as_slice().stable_hash(hasher);
+    }
+}
+
+impl<T: StableHash> StableHash for Option<T> {
+    fn stable_hash(&self, hasher: &mut StableHasher) {
+        match self {
+            Some(value) => {
+                hasher.write(&[1]);
+                value.stable_hash(hasher);
+            },
+            None => hasher.write(&[0]),
+        }
+    }
+}
+
+impl<T: StableHash + ?Sized> StableHash for &T {
+    fn stable_hash(&self, hasher: &mut StableHasher) {
+        (**self).stable_hash(hasher);
+    }
+}
+
+impl StableHash for Entity {
+    fn stable_hash(&self, hasher: &mut StableHasher) {
+        self.id.stable_hash(hasher);
+        self.generation.stable_hash(hasher);
+    }
+}
+
+impl StableHash for Vec2 {
+    fn stable_hash(&self, hasher: &mut StableHasher) {
+        self.to_array().stable_hash(hasher);
+    }
+}
+
+impl StableHash for Vec3 {
+    fn stable_hash(&self, hasher: &mut StableHasher) {
+        self.to_array().stable_hash(hasher);
+    }
+}
+
+impl StableHash for Vec4 {
+    fn stable_hash(&self, hasher: &mut StableHasher) {
+        self.to_array().stable_hash(hasher);
+    }
+}
+
+impl StableHash for Quat {
+    fn stable_hash(&self, hasher: &mut StableHasher) {
+        <[f32; 4]>::from(*self).stable_hash(hasher);
+    }
+}
+
+/// See `World::checksum`
+pub fn checksum(world: &World) -> u64 {
+    // by name so that the registration order does not matter
+    let mut hashable: Vec<&ComponentDescriptor> = world.get_components().descriptors()
+        .iter()
+        .filter(|descriptor| descriptor.stable_hash.is_some())
+        .collect();
+    hashable.sort_by(|a, b| a.name.cmp(&b.name));
+
+    let mut hasher = StableHasher::new();
+    for entity in world.get_entities().iter() {
+        entity.stable_hash(&mut hasher);
+        for descriptor in hashable.iter() {
+            if let Some(value) = world.get_by_id(entity, &descriptor.id) {
+                descriptor.name.stable_hash(&mut hasher);
+                unsafe {
+                    (descriptor.stable_hash.unwrap())(value, &mut hasher);
+                }
+            }
+        }
+    }
+    for descriptor in hashable.iter() {
+        if let Some(value) = world.get_resource_table().get_resource(&descriptor.id) {
+            descriptor.name.stable_hash(&mut hasher);
+            unsafe {
+                (descriptor.stable_hash.unwrap())(value, &mut hasher);
+            }
+        }
+    }
+    hasher.finish()
+}
+
+
+#[cfg(test)]
+mod tests {
+    use crate::ecs::World;
+    use crate::transform::Transform;
+
+    use super::{StableHash, StableHasher};
+
+    #[derive(StableHash)]
+    struct Health(u32);
+
+    #[derive(StableHash)]
+    enum Team {
+        Red,
+        Blue { score: u32 },
+    }
+
+    struct Ignored(#[allow(dead_code)] u32);
+
+    fn hash_of<T: StableHash>(value: &T) -> u64 {
+        let mut hasher = StableHasher::new();
+        value.stable_hash(&mut hasher);
+        hasher.finish()
+    }
+
+    #[test]
+    fn stable_values() {
+        // FNV-1a of the empty input and of "a"
+        assert_eq!(0xcbf2_9ce4_8422_2325, StableHasher::new().finish());
+        assert_eq!(0xaf63_dc4c_8601_ec8c, hash_of(&[b'a']));
+        assert_ne!(hash_of(&Team::Red), hash_of(&Team::Blue { score: 0 }));
+        assert_ne!(hash_of(&vec![1u8, 2]), hash_of(&vec![vec![1u8], vec![2]]));
+    }
+
+    fn build(world: &mut World, register_health_first: bool) {
+        if register_health_first {
+            world.register_stable_hash::<Health>();
+            world.register_stable_hash::<Transform>();
+        }
+        else {
+            world.register_stable_hash::<Transform>();
+            world.register_stable_hash::<Health>();
+        }
+        world.register_stable_hash_resource::<u64>();
+        world.insert_resource(7u64);
+        world.spawn().insert(Health(10)).insert(Transform::from_xyz(1.0, 2.0, 3.0)).insert(Ignored(1));
+        world.spawn().insert(Health(5));
+    }
+
+    #[test]
+    fn checksum_detects_desync() {
+        let mut a = World::new();
+        let mut b = World::new();
+        build(&mut a, true);
+        build(&mut b, false);
+        assert_eq!(a.checksum(), b.checksum());
+
+        let entity = b.get_entities().iter().next().unwrap();
+        b.get_mut::<Ignored>(entity).unwrap().0 = 2;
+        assert_eq!(a.checksum(), b.checksum());
+
+        b.get_mut::<Transform>(entity).unwrap().translation.x += 0.001;
+        assert_ne!(a.checksum(), b.checksum());
+    }
+}
@@ -0,0 +1,200 @@
+use std::collections::BTreeMap;
+
+use serde::Serialize;
+
+use super::{World, component::{ComponentId, StorageType}, storage::table::Column};
+
+
+/// Memory usage of a single column or sparse set
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct ColumnStats {
+    pub component: String,
+    pub len: usize,
+    /// `usize::MAX` for zero sized components
+    pub capacity: usize,
+    pub item_size: usize,
+    pub used_bytes: usize,
+    /// Allocated but unused
+    pub wasted_bytes: usize,
+}
+
+impl ColumnStats {
+    fn of(component: &str, column: &Column) -> Self {
+        let used_bytes = column.items_len() * column.item_size();
+        ColumnStats {
+            component: component.to_string(),
+            len: column.items_len(),
+            capacity: column.item_capacity(),
+            item_size: column.item_size(),
+            used_bytes,
+            wasted_bytes: column.allocated_bytes() - used_bytes,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct TableStats {
+    pub id: usize,
+    pub entity_count: usize,
+    pub row_capacity: usize,
+    /// Sorted by component id
+    pub columns: Vec<ColumnStats>,
+    pub used_bytes: usize,
+    pub wasted_bytes: usize,
+}
+
+/// Totals of a component over every table, or its sparse set
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct ComponentStats {
+    pub name: String,
+    pub storage: &'static str,
+    pub item_size: usize,
+    pub entity_count: usize,
+    /// Tables having a column for the component, 0 for sparse set components
+    pub table_count: usize,
+    pub used_bytes: usize,
+    pub wasted_bytes: usize,
+}
+
+/// Snapshot of what the world stores, see `World::stats`
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct WorldStats {
+    pub entity_count: usize,
+    pub table_count: usize,
+    /// Tables without entities, left behind by entities that moved or despawned
+    pub empty_table_count: usize,
+    pub tables: Vec<TableStats>,
+    /// Sorted by component id
+    pub components: Vec<ComponentStats>,
+    pub used_bytes: usize,
+    pub wasted_bytes: usize,
+}
+
+fn storage_name(storage_type: StorageType) -> &'static str {
+    match storage_type {
+        StorageType::Table => "table",
+        StorageType::SparseSet => "sparse_set",
+    }
+}
+
+impl WorldStats {
+    pub fn of(world: &World) -> Self {
+        let components = world.get_components();
+        let name_of = |component_id: &ComponentId| components.get_descriptor(component_id)
+            .map(|descriptor| descriptor.name.as_str())
+            .unwrap_or("?");
+
+        let mut component_stats: BTreeMap<ComponentId, ComponentStats> = BTreeMap::new();
+        let mut add_column = |component_id: &ComponentId, column: &ColumnStats, in_table: bool| {
+            let stats = component_stats.entry(component_id.clone()).or_insert_with(|| ComponentStats {
+                name: column.component.clone(),
+                storage: storage_name(components.get_descriptor(component_id)
+                    .map(|descriptor| descriptor.storage_type)
+                    .unwrap_or_default()),
+                item_size: column.item_size,
+                entity_count: 0,
+                table_count: 0,
+                used_bytes: 0,
+                wasted_bytes: 0,
+            });
+            stats.entity_count += column.len;
+            stats.table_count += in_table as usize;
+            stats.used_bytes += column.used_bytes;
+            stats.wasted_bytes += column.wasted_bytes;
+        };
+
+        let mut tables = Vec::with_capacity(world.get_tables().len());
+        for (id, table) in world.get_tables().iter().enumerate() {
+            let columns: Vec<ColumnStats> = table.component_ids()
+                .iter()
+                .map(|component_id| {
+                    let column = ColumnStats::of(name_of(component_id), table.get_column(component_id).unwrap());
+                    add_column(component_id, &column, true);
+                    column
+                })
+                .collect();
+            tables.push(TableStats {
+                id,
+                entity_count: table.len(),
+                row_capacity: table.row_capacity(),
+                used_bytes: columns.iter().map(|column| column.used_bytes).sum(),
+                wasted_bytes: columns.iter().map(|column| column.wasted_bytes).sum(),
+                columns,
+            });
+        }
+        for (component_id, sparse_set) in world.get_sparse_sets().iter() {
+            add_column(component_id, &ColumnStats::of(name_of(component_id), sparse_set.column()), false);
+        }
+
+        let components: Vec<ComponentStats> = component_stats.into_values().collect();
+        WorldStats {
+            entity_count: world.get_entities().len(),
+            table_count: tables.len(),
+            empty_table_count: tables.iter().filter(|table| table.entity_count == 0).count(),
+            used_bytes: components.iter().map(|component| component.used_bytes).sum(),
+            wasted_bytes: components.iter().map(|component| component.wasted_bytes).sum(),
+            tables,
+            components,
+        }
+    }
+
+    pub fn to_json(&self) -> String {
+        serde_json::to_string_pretty(self).unwrap()
+    }
+}
+
+impl std::fmt::Display for WorldStats {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        writeln!(f, "{} entities, {} tables ({} empty), {} bytes used, {} bytes wasted",
+            self.entity_count, self.table_count, self.empty_table_count, self.used_bytes, self.wasted_bytes)?;
+        for table in self.tables.iter() {
+            let names: Vec<&str> = table.columns.iter().map(|column| column.component.as_str()).collect();
+            writeln!(f, "table {}: {} / {} rows, {} bytes used, {} bytes wasted [{}]",
+                table.id, table.entity_count, table.row_capacity, table.used_bytes, table.wasted_bytes, names.join(", "))?;
+        }
+        for component in self.components.iter() {
+            writeln!(f, "{} ({}, {} bytes): {} entities in {} tables, {} bytes used, {} bytes wasted",
+                component.name, component.storage, component.item_size, component.entity_count,
+                component.table_count, component.used_bytes, component.wasted_bytes)?;
+        }
+        Ok(())
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use crate::ecs::World;
+    use crate::ecs::component::StorageType;
+
+    struct Position(#[allow(dead_code)] [f32; 2]);
+    struct Selected;
+
+    #[test]
+    fn counts_and_bytes() {
+        let mut world = World::new();
+        world.add_component_with_storage::<Selected>(StorageType::SparseSet);
+        let entities: Vec<_> = (0..4)
+            .map(|i| world.spawn().insert(Position([i as f32, 0.0])).id())
+            .collect();
+        world.entity_mut(entities[0]).insert(Selected);
+        world.despawn(entities[3]);
+
+        let stats = world.stats();
+        assert_eq!(3, stats.entity_count);
+        assert_eq!(2, stats.table_count);
+        assert_eq!(1, stats.empty_table_count);
+
+        let position = stats.components.iter().find(|component| component.name.ends_with("Position")).unwrap();
+        assert_eq!((3, 1, 8, 24), (position.entity_count, position.table_count, position.item_size, position.used_bytes));
+        let table = stats.tables.iter().find(|table| table.entity_count == 3).unwrap();
+        assert_eq!(table.wasted_bytes, (table.columns[0].capacity - 3) * 8);
+
+        let selected = stats.components.iter().find(|component| component.name.ends_with("Selected")).unwrap();
+        assert_eq!(("sparse_set", 1, 0), (selected.storage, selected.entity_count, selected.wasted_bytes));
+
+        assert!(stats.to_string().starts_with("3 entities, 2 tables (1 empty)"));
+        let json: serde_json::Value = serde_json::from_str(&stats.to_json()).unwrap();
+        assert_eq!(3, json["entity_count"]);
+    }
+}
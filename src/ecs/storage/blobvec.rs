@@ -5,8 +5,8 @@ use std::{ptr::NonNull, alloc::Layout};
 pub struct BlobVec {
     item_layout: Layout, // layout of the item in the vec
     data: NonNull<u8>, // 1 byte blocks, layout is handled manually
-    capacity: usize, // capacity of the vec in bytes (index capacity)
-    len: usize, // number of used bytes in the vec (used indices) (an item has layout.size amount of bytes)
+    capacity: usize, // capacity of the vec in items (index capacity), usize::MAX for ZSTs
+    len: usize, // number of items in the vec (used indices), an item has layout.size amount of bytes
     swap_space: NonNull<u8>, // a single item space for swap operation
     drop: unsafe fn(*mut u8), // drop function to use when removing items, not the blobvec itself
 }
@@ -43,16 +43,27 @@ impl BlobVec {
         self.capacity
     }
 
+    /// Same as `capacity`, items not bytes
     pub fn item_capacity(&self) -> usize {
-        self.capacity / self.item_layout.size()
+        self.capacity
+    }
+
+    pub fn item_size(&self) -> usize {
+        self.item_layout.size()
+    }
+
+    /// Bytes allocated for items, 0 for ZSTs
+    pub fn allocated_bytes(&self) -> usize {
+        self.capacity.saturating_mul(self.item_layout.size())
     }
 
     pub fn len(&self) -> usize {
         self.len
     }
 
+    /// Same as `len`, items not bytes
     pub fn items_len(&self) -> usize {
-        self.len / self.item_layout.size()
+        self.len
     }
 
     pub fn get_ptr(&self) -> NonNull<u8> {
@@ -94,6 +105,28 @@ impl BlobVec {
         self.capacity = new_capacity;
     }
 
+    /// Releases the capacity beyond `len`
+    pub fn shrink_to_fit(&mut self) {
+        if self.item_layout.size() == 0 || self.capacity == self.len {
+            return;
+        }
+        let old_layout = array_layout(self.item_layout, self.capacity)
+                                    .expect("Invalid array layout");
+        unsafe {
+            if self.len == 0 {
+                std::alloc::dealloc(self.data.as_ptr(), old_layout);
+                self.data = NonNull::dangling();
+            }
+            else {
+                let new_layout = array_layout(self.item_layout, self.len)
+                                    .expect("Invalid array layout");
+                let shrunk_data = std::alloc::realloc(self.data.as_ptr(), old_layout, new_layout.size());
+                self.data = NonNull::new(shrunk_data).unwrap_or_else(|| std::alloc::handle_alloc_error(new_layout));
+            }
+        }
+        self.capacity = self.len;
+    }
+
     // push a 
     pub fn push_uninit(&mut self) -> usize {
         self.reserve_exact(1);
@@ -101,6 +134,13 @@ impl BlobVec {
         self.len()-1
     }
 
+    /// Pushes `additional` uninitialized items, returns the index of the first one
+    pub fn extend_uninit(&mut self, additional: usize) -> usize {
+        self.reserve_exact(additional);
+        self.len += additional;
+        self.len - additional
+    }
+
     pub unsafe fn init_unchecked(&mut self, index: usize, value: *mut u8) {
         debug_assert!(index < self.len);
         let data_i = self.get_unchecked(index);
@@ -162,13 +202,16 @@ impl BlobVec {
 impl Drop for BlobVec {
     fn drop(&mut self) {
         self.clear();
+        if self.item_layout.size() == 0 {
+            return;
+        }
         let arr_layout = array_layout(self.item_layout, self.capacity)
                                     .expect("Invalid array layout");
-        if arr_layout.size() > 0 {
-            unsafe {
+        unsafe {
+            if arr_layout.size() > 0 {
                 std::alloc::dealloc(self.data.as_ptr(), arr_layout);
-                std::alloc::dealloc(self.swap_space.as_ptr(), self.item_layout);
             }
+            std::alloc::dealloc(self.swap_space.as_ptr(), self.item_layout);
         }
     }
 }
@@ -2,3 +2,4 @@
 pub mod blobvec;
 pub mod table;
 pub mod resource_table;
+pub mod sparse_set;
@@ -9,12 +9,12 @@ pub struct ResourceTable {
 }
 
 impl ResourceTable {
-    pub const GLOBAL_ENTITY: Entity = Entity { id: 0, table_id: 0 };
+    pub const GLOBAL_ENTITY: Entity = Entity { id: 0, generation: 0 };
 
     #[inline]
     pub fn new() -> ResourceTable {
         let mut table = Table::new();
-        let row = table.add_row(Self::GLOBAL_ENTITY.clone());
+        let row = table.add_row(Self::GLOBAL_ENTITY);
         ResourceTable {
             entity_id: row,
             table,
@@ -48,6 +48,37 @@ impl ResourceTable {
                 .init_unchecked(self.entity_id, data);
     }
 
+    pub fn has_resource(&self, resource_id: &ComponentId) -> bool {
+        self.table.has_column(resource_id)
+    }
+
+    /// # Safety
+    /// - `descriptor` should describe the type `data` points to
+    /// - `data` is moved into the table, caller should forget the original value
+    pub unsafe fn insert_resource_unchecked(&mut self, descriptor: &ComponentDescriptor, data: *mut u8) {
+        match self.table.get_column_mut(&descriptor.id) {
+            Some(column) => column.replace_unchecked(self.entity_id, data),
+            None => {
+                self.table.add_column(descriptor);
+                let column = self.table.get_column_mut(&descriptor.id).unwrap();
+                column.push_uninit();
+                column.init_unchecked(self.entity_id, data);
+            }
+        }
+    }
+
+    /// # Safety
+    /// - resource should be stored, see `ResourceTable::has_resource`
+    /// - `T` should be the type of the resource
+    pub unsafe fn take_resource_unchecked<T: Resource>(&mut self, resource_id: &ComponentId) -> T {
+        let value = self.table.get_column_mut(resource_id).unwrap()
+                .swap_remove_and_forget_unchecked(self.entity_id)
+                .cast::<T>()
+                .read();
+        self.table.remove_column(resource_id);
+        value
+    }
+
     pub unsafe fn remove_and_drop_unchecked(&mut self, resource_id: &ComponentId) {
         self.table.get_column_mut(resource_id).unwrap()
                 .swap_remove_and_drop_unchecked(self.entity_id);
@@ -0,0 +1,220 @@
+use std::collections::BTreeMap;
+
+use crate::ecs::component::{ComponentDescriptor, ComponentId};
+use crate::ecs::entity::Entity;
+
+use super::table::Column;
+
+
+/// Values of a single component, packed densely and indexed by entity id
+/// Adding or removing the component does not move the entity between tables
+pub struct ComponentSparseSet {
+    dense: Column,
+    entities: Vec<Entity>, // Owner of each dense value
+    sparse: Vec<Option<usize>>, // entity id -> dense index
+}
+
+impl ComponentSparseSet {
+    pub fn new(descriptor: &ComponentDescriptor) -> Self {
+        ComponentSparseSet {
+            dense: Column::new(descriptor),
+            entities: Vec::new(),
+            sparse: Vec::new(),
+        }
+    }
+
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.entities.len()
+    }
+
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.entities.is_empty()
+    }
+
+    pub fn column(&self) -> &Column {
+        &self.dense
+    }
+
+    /// Owners of the values, in dense order
+    #[inline]
+    pub fn entities(&self) -> &[Entity] {
+        &self.entities
+    }
+
+    #[inline]
+    fn dense_index(&self, entity: Entity) -> Option<usize> {
+        let index = (*self.sparse.get(entity.id)?)?;
+        (self.entities[index] == entity).then_some(index)
+    }
+
+    #[inline]
+    pub fn contains(&self, entity: Entity) -> bool {
+        self.dense_index(entity).is_some()
+    }
+
+    #[inline]
+    pub fn get(&self, entity: Entity) -> Option<*mut u8> {
+        let index = self.dense_index(entity)?;
+        unsafe {
+            Some(self.dense.get_unchecked(index))
+        }
+    }
+
+    /// See `Column::changed_tick`
+    #[inline]
+    pub fn changed_tick(&self, entity: Entity) -> Option<u32> {
+        let index = self.dense_index(entity)?;
+        Some(self.dense.changed_tick(index))
+    }
+
+    /// Returns false if the entity has no value
+    #[inline]
+    pub fn set_changed(&self, entity: Entity, tick: u32) -> bool {
+        match self.dense_index(entity) {
+            Some(index) => {
+                self.dense.set_changed(index, tick);
+                true
+            },
+            None => false,
+        }
+    }
+
+    /// Moves `value` into the set, replaces the old value if the entity already has one
+    ///
+    /// # Safety
+    /// `value` should point to a valid value of the component type, the set owns it afterwards
+    pub unsafe fn insert(&mut self, entity: Entity, value: *mut u8) {
+        if let Some(index) = self.dense_index(entity) {
+            self.dense.replace_unchecked(index, value);
+            return;
+        }
+        // a stale value of a despawned entity with the same id should have been removed on despawn
+        let index = self.dense.push_uninit();
+        self.dense.init_unchecked(index, value);
+        self.entities.push(entity);
+        if self.sparse.len() <= entity.id {
+            self.sparse.resize(entity.id + 1, None);
+        }
+        self.sparse[entity.id] = Some(index);
+    }
+
+    /// Releases the capacity beyond `len`
+    pub fn shrink_to_fit(&mut self) {
+        self.dense.shrink_to_fit();
+        self.entities.shrink_to_fit();
+        let used = self.sparse.iter().rposition(Option::is_some).map_or(0, |index| index + 1);
+        self.sparse.truncate(used);
+        self.sparse.shrink_to_fit();
+    }
+
+    /// Removes the value of the entity without dropping it
+    /// The returned pointer is only valid until the set is changed
+    ///
+    /// # Safety
+    /// the caller should read or drop the value
+    pub unsafe fn remove_and_forget(&mut self, entity: Entity) -> Option<*mut u8> {
+        let index = self.dense_index(entity)?;
+        self.swap_remove_index(entity, index);
+        Some(self.dense.swap_remove_and_forget_unchecked(index))
+    }
+
+    /// Returns false if the entity has no value
+    pub fn remove_and_drop(&mut self, entity: Entity) -> bool {
+        match self.dense_index(entity) {
+            Some(index) => {
+                self.swap_remove_index(entity, index);
+                unsafe {
+                    self.dense.swap_remove_and_drop_unchecked(index);
+                }
+                true
+            },
+            None => false,
+        }
+    }
+
+    /// Updates the indices for the value at `index` being swap removed
+    fn swap_remove_index(&mut self, entity: Entity, index: usize) {
+        self.sparse[entity.id] = None;
+        self.entities.swap_remove(index);
+        if let Some(swapped) = self.entities.get(index) {
+            self.sparse[swapped.id] = Some(index);
+        }
+    }
+}
+
+/// Sparse sets of every component using `StorageType::SparseSet`
+#[derive(Default)]
+pub struct SparseSets {
+    sets: BTreeMap<ComponentId, ComponentSparseSet>,
+}
+
+impl SparseSets {
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    #[inline]
+    pub fn get(&self, component_id: &ComponentId) -> Option<&ComponentSparseSet> {
+        self.sets.get(component_id)
+    }
+
+    #[inline]
+    pub fn get_mut(&mut self, component_id: &ComponentId) -> Option<&mut ComponentSparseSet> {
+        self.sets.get_mut(component_id)
+    }
+
+    pub fn get_or_insert(&mut self, descriptor: &ComponentDescriptor) -> &mut ComponentSparseSet {
+        self.sets.entry(descriptor.id.clone())
+            .or_insert_with(|| ComponentSparseSet::new(descriptor))
+    }
+
+    /// Sorted by component id
+    pub fn iter(&self) -> impl Iterator<Item = (&ComponentId, &ComponentSparseSet)> {
+        self.sets.iter()
+    }
+
+    pub fn iter_mut(&mut self) -> impl Iterator<Item = (&ComponentId, &mut ComponentSparseSet)> {
+        self.sets.iter_mut()
+    }
+
+    /// Drops every value of the entity
+    pub fn remove_entity(&mut self, entity: Entity) {
+        for set in self.sets.values_mut() {
+            set.remove_and_drop(entity);
+        }
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use crate::ecs::component::{ComponentDescriptor, ComponentId};
+    use crate::ecs::entity::Entity;
+
+    use super::ComponentSparseSet;
+
+    #[test]
+    fn insert_replace_remove() {
+        let descriptor = ComponentDescriptor::of::<String>(ComponentId(0));
+        let mut set = ComponentSparseSet::new(&descriptor);
+        let (a, b, c) = (Entity::new(0, 0), Entity::new(5, 0), Entity::new(2, 0));
+
+        unsafe {
+            for (entity, name) in [(a, "a"), (b, "b"), (c, "c"), (b, "bb")] {
+                let mut value = std::mem::ManuallyDrop::new(name.to_string());
+                set.insert(entity, (&mut *value as *mut String).cast::<u8>());
+            }
+            assert_eq!(3, set.len());
+            assert_eq!("bb", &*set.get(b).unwrap().cast::<String>());
+            assert!(!set.contains(Entity::new(5, 1)));
+
+            assert_eq!("a", set.remove_and_forget(a).unwrap().cast::<String>().read());
+            assert!(set.remove_and_drop(c));
+            assert!(!set.remove_and_drop(c));
+            assert_eq!(&[b], set.entities());
+            assert_eq!("bb", &*set.get(b).unwrap().cast::<String>());
+        }
+    }
+}
@@ -1,7 +1,9 @@
 use std::{any::TypeId, alloc::Layout};
-use std::collections::{HashMap, hash_map::DefaultHasher};
+use std::collections::{BTreeMap, HashMap, hash_map::DefaultHasher};
 use std::ptr::NonNull;
 use std::hash::{Hash, Hasher};
+use std::ops::Range;
+use std::sync::atomic::{AtomicU32, Ordering};
 
 use crate::ecs::component::{ComponentDescriptor, Component, ComponentId};
 use crate::ecs::entity::Entity;
@@ -13,27 +15,31 @@ use super::blobvec::BlobVec;
 pub struct Column {
     component_id: ComponentId,
     column_data: BlobVec,
+    // change tick of each value, written through shared references by mutable query fetches
+    ticks: Vec<AtomicU32>,
 }
 
 impl Column {
-    const unsafe fn drop(ptr: *mut u8) {
-
-    }
-
     #[inline]
-    fn new(descriptor: &ComponentDescriptor) -> Column {
+    pub(crate) fn new(descriptor: &ComponentDescriptor) -> Column {
         Column::with_capacity(descriptor, 0)
     }
 
     #[inline]
-    fn with_capacity(descriptor: &ComponentDescriptor, capacity: usize) -> Column {
-        let layout = descriptor.layout.clone();
+    pub(crate) fn with_capacity(descriptor: &ComponentDescriptor, capacity: usize) -> Column {
+        let layout = descriptor.layout;
         Column {
             component_id: descriptor.id.clone(),
-            column_data: BlobVec::new(layout, capacity, Self::drop),
+            column_data: BlobVec::new(layout, capacity, descriptor.drop),
+            ticks: Vec::with_capacity(capacity),
         }
     }
 
+    #[inline]
+    pub fn component_id(&self) -> &ComponentId {
+        &self.component_id
+    }
+
     #[inline]
     pub fn capacity(&self) -> usize {
         self.column_data.capacity()
@@ -49,6 +55,21 @@ impl Column {
         self.column_data.len()
     }
 
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.column_data.len() == 0
+    }
+
+    #[inline]
+    pub fn item_size(&self) -> usize {
+        self.column_data.item_size()
+    }
+
+    #[inline]
+    pub fn allocated_bytes(&self) -> usize {
+        self.column_data.allocated_bytes()
+    }
+
     #[inline]
     pub fn items_len(&self) -> usize {
         self.column_data.items_len()
@@ -61,14 +82,46 @@ impl Column {
 
     #[inline]
     pub fn reserve_exact(&mut self, additional: usize) {
-        self.column_data.reserve_exact(additional)
+        self.column_data.reserve_exact(additional);
+        self.ticks.reserve_exact(additional);
     }
 
+    /// Adds an uninitialized value with change tick 0
     #[inline]
     pub fn push_uninit(&mut self) -> usize {
+        self.ticks.push(AtomicU32::new(0));
         self.column_data.push_uninit()
     }
 
+    #[inline]
+    pub fn extend_uninit(&mut self, additional: usize) -> usize {
+        self.ticks.extend((0..additional).map(|_| AtomicU32::new(0)));
+        self.column_data.extend_uninit(additional)
+    }
+
+    #[inline]
+    pub fn shrink_to_fit(&mut self) {
+        self.column_data.shrink_to_fit();
+        self.ticks.shrink_to_fit();
+    }
+
+    /// Tick of the last insert or mutable access of the value at `index`, see `World::change_tick`
+    #[inline]
+    pub fn changed_tick(&self, index: usize) -> u32 {
+        self.ticks[index].load(Ordering::Relaxed)
+    }
+
+    #[inline]
+    pub fn set_changed(&self, index: usize, tick: u32) {
+        self.ticks[index].store(tick, Ordering::Relaxed);
+    }
+
+    pub fn set_changed_range(&self, indices: Range<usize>, tick: u32) {
+        for changed in self.ticks[indices].iter() {
+            changed.store(tick, Ordering::Relaxed);
+        }
+    }
+
     #[inline]
     pub unsafe fn init_unchecked(&mut self, index: usize, value: *mut u8) {
         self.column_data.init_unchecked(index, value)
@@ -85,16 +138,30 @@ impl Column {
     }
 
     pub unsafe fn swap_remove_and_forget_unchecked(&mut self, index: usize) -> *mut u8 {
+        self.ticks.swap_remove(index);
         self.column_data.swap_remove_and_forget_unchecked(index)
     }
 
     pub unsafe fn swap_remove_and_drop_unchecked(&mut self, index: usize) {
+        self.ticks.swap_remove(index);
         self.column_data.swap_remove_and_drop_unchecked(index)
     }
 
+    /// Moves the value at `index` to the uninitialized `dst_index` of `dst`, keeping its change tick
+    ///
+    /// # Safety
+    /// `index` should be in bounds, `dst` should store the same component
+    pub unsafe fn swap_remove_into_unchecked(&mut self, index: usize, dst: &mut Column, dst_index: usize) {
+        let tick = self.changed_tick(index);
+        let cell_data = self.swap_remove_and_forget_unchecked(index);
+        dst.init_unchecked(dst_index, cell_data);
+        dst.set_changed(dst_index, tick);
+    }
+
     #[inline]
     pub fn clear(&mut self) {
-        self.column_data.clear()
+        self.column_data.clear();
+        self.ticks.clear();
     }
 }
 
@@ -112,7 +179,8 @@ pub struct TableMoveResult {
 
 #[derive(Default)]
 pub struct Table {
-    components: HashMap<ComponentId, Column>,
+    // ordered so that columns are visited in the same order on every run
+    components: BTreeMap<ComponentId, Column>,
     entities: Vec<Entity>,
     //bitmasks: HashMap<TypeId, u64>,
 }
@@ -141,6 +209,25 @@ impl Table {
         self.components.contains_key(component_id)
     }
 
+    /// Sorted ids of the components stored in the table
+    pub fn component_ids(&self) -> Vec<ComponentId> {
+        self.components.keys().cloned().collect()
+    }
+
+    pub fn entities(&self) -> &[Entity] {
+        &self.entities
+    }
+
+    /// Sorted by component id
+    pub fn columns(&self) -> impl Iterator<Item = &Column> {
+        self.components.values()
+    }
+
+    /// Number of rows that fit without reallocating
+    pub fn row_capacity(&self) -> usize {
+        self.entities.capacity()
+    }
+
     pub fn is_empty(&self) -> bool {
         self.entities.is_empty()
     }
@@ -170,6 +257,16 @@ impl Table {
         self.entities.len() - 1
     }
 
+    /// Adds a row per entity with uninitialized cells, returns the first new row
+    pub fn add_rows(&mut self, entities: &[Entity]) -> usize {
+        self.reserve_rows_exact(entities.len());
+        for column in self.components.values_mut() {
+            column.extend_uninit(entities.len());
+        }
+        self.entities.extend_from_slice(entities);
+        self.entities.len() - entities.len()
+    }
+
     pub fn reserve_rows_exact(&mut self, additional: usize) {
         if self.entities.capacity() - self.entities.len() < additional {
             self.entities.reserve(additional);
@@ -184,6 +281,26 @@ impl Table {
         }
     }
 
+    /// Releases the row capacity beyond `len`
+    pub fn shrink_to_fit(&mut self) {
+        self.entities.shrink_to_fit();
+        for column in self.components.values_mut() {
+            column.shrink_to_fit();
+        }
+    }
+
+    /// Removes the row without dropping its values, the caller should have moved them out
+    ///
+    /// # Safety
+    /// `row` should be in bounds
+    pub unsafe fn swap_remove_and_forget_unchecked(&mut self, row: usize) -> Option<Entity> {
+        for column in self.components.values_mut() {
+            column.swap_remove_and_forget_unchecked(row);
+        }
+        self.entities.swap_remove(row);
+        self.entities.get(row).copied()
+    }
+
     pub unsafe fn swap_remove_and_drop_unchecked(&mut self, row: usize) -> Option<Entity> {
         for column in self.components.values_mut() {
             column.swap_remove_and_drop_unchecked(row);
@@ -195,7 +312,7 @@ impl Table {
             None
         }
         else {
-            Some(self.entities[row].clone())
+            Some(self.entities[row])
         }
     }
 
@@ -206,8 +323,7 @@ impl Table {
         let moved_row = dst_table.add_row(self.entities.swap_remove(row));
         for column in self.components.values_mut() {
             let dst_column = dst_table.get_column_mut(&column.component_id).unwrap();
-            let cell_data = column.swap_remove_and_forget_unchecked(row);
-            dst_column.init_unchecked(moved_row, cell_data);
+            column.swap_remove_into_unchecked(row, dst_column, moved_row);
         }
 
         TableMoveResult {
@@ -216,7 +332,7 @@ impl Table {
                 None
             }
             else {
-                Some(self.entities[row].clone())
+                Some(self.entities[row])
             }
         }
     }
@@ -227,12 +343,13 @@ impl Table {
         let is_last = (row == self.entities.len() - 1);
         let moved_row = dst_table.add_row(self.entities.swap_remove(row));
         for column in self.components.values_mut() {
-            let dst_column = dst_table.get_column_mut(&column.component_id);
-            if let Some(dst_column) = dst_column {
-                let cell_data = column.swap_remove_and_forget_unchecked(row);
-                dst_column.init_unchecked(moved_row, cell_data);   
+            match dst_table.get_column_mut(&column.component_id) {
+                Some(dst_column) => column.swap_remove_into_unchecked(row, dst_column, moved_row),
+                // forget
+                None => {
+                    column.swap_remove_and_forget_unchecked(row);
+                },
             }
-            // if None => forget
         }
 
         TableMoveResult {
@@ -241,7 +358,7 @@ impl Table {
                 None
             }
             else {
-                Some(self.entities[row].clone())
+                Some(self.entities[row])
             }
         }
     }
@@ -255,8 +372,7 @@ impl Table {
             let dst_column = dst_table.get_column_mut(&column.component_id);
             match dst_column {
                 Some(dst_column) => {
-                    let cell_data = column.swap_remove_and_forget_unchecked(row);
-                    dst_column.init_unchecked(moved_row, cell_data);   
+                    column.swap_remove_into_unchecked(row, dst_column, moved_row);
                 },
                 None => {
                     column.swap_remove_and_drop_unchecked(row);
@@ -270,7 +386,7 @@ impl Table {
                 None
             }
             else {
-                Some(self.entities[row].clone())
+                Some(self.entities[row])
             }
         }
     }
@@ -470,15 +586,91 @@ impl Tables {
         self.tables_vec.get_mut(table_id)
     }
 
-    pub fn new_table<'a>(&'a mut self, components: &[ComponentDescriptor]) -> &'a mut Table {
+    pub fn len(&self) -> usize {
+        self.tables_vec.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.tables_vec.is_empty()
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &Table> {
+        self.tables_vec.iter()
+    }
+
+    pub fn iter_mut(&mut self) -> impl Iterator<Item = &mut Table> {
+        self.tables_vec.iter_mut()
+    }
+
+    /// Drops the tables without entities, the remaining tables keep their order
+    /// Returns the new id of every old table id, None for removed tables
+    /// Locations pointing into the tables should be updated by the caller
+    pub fn remove_empty(&mut self) -> Vec<Option<usize>> {
+        let mut remap = Vec::with_capacity(self.tables_vec.len());
+        let mut next_id = 0;
+        for table in self.tables_vec.iter() {
+            if table.is_empty() {
+                remap.push(None);
+            }
+            else {
+                remap.push(Some(next_id));
+                next_id += 1;
+            }
+        }
+
+        self.tables_vec.retain(|table| !table.is_empty());
+        self.ids = self.tables_vec.iter()
+            .enumerate()
+            .map(|(table_id, table)| (Self::signature(&table.component_ids()), table_id))
+            .collect();
+        remap
+    }
+
+    fn signature(component_ids: &[ComponentId]) -> u64 {
+        // ids are sorted so that permutations of the same components give the same table
+        let mut component_ids = component_ids.to_vec();
+        component_ids.sort();
+        let mut hasher = DefaultHasher::new();
+        component_ids.hash(&mut hasher);
+        hasher.finish()
+    }
+
+    pub fn get_id(&self, component_ids: &[ComponentId]) -> Option<usize> {
+        self.ids.get(&Self::signature(component_ids)).copied()
+    }
+
+    /// Id of the table storing exactly `components`, creates the table if there is none
+    pub fn get_or_insert(&mut self, components: &[ComponentDescriptor]) -> usize {
         let component_ids: Vec<ComponentId> = components.iter()
                 .map(|cd| cd.id.clone())
                 .collect();
+        match self.get_id(&component_ids) {
+            Some(table_id) => table_id,
+            None => {
+                self.new_table(components);
+                self.tables_vec.len() - 1
+            }
+        }
+    }
 
-        let mut hasher = DefaultHasher::new();
-        component_ids.hash(&mut hasher);
-        let table_signature = hasher.finish();
-        // TODO: what to do with permutations, assuming they produce different hash values
+    /// Mutable references to two different tables
+    pub fn get_two_mut(&mut self, a: usize, b: usize) -> (&mut Table, &mut Table) {
+        assert_ne!(a, b, "Cannot borrow the same table twice");
+        if a < b {
+            let (left, right) = self.tables_vec.split_at_mut(b);
+            (&mut left[a], &mut right[0])
+        }
+        else {
+            let (left, right) = self.tables_vec.split_at_mut(a);
+            (&mut right[0], &mut left[b])
+        }
+    }
+
+    pub fn new_table<'a>(&'a mut self, components: &[ComponentDescriptor]) -> &'a mut Table {
+        let component_ids: Vec<ComponentId> = components.iter()
+                .map(|cd| cd.id.clone())
+                .collect();
+        let table_signature = Self::signature(&component_ids);
 
         let mut new_table = Table::new();
         for cd in components {
@@ -0,0 +1,80 @@
+use crate::ecs::World;
+
+
+/// System with direct mutable access to the World
+/// Cannot run alongside other systems
+pub trait ExclusiveSystem {
+    fn initialize(&mut self, world: &mut World);
+    fn run(&mut self, world: &mut World);
+}
+
+pub type BoxedExclusiveSystem = Box<dyn ExclusiveSystem>;
+
+pub struct ExclusiveSystemFn<F>
+where
+    F: FnMut(&mut World)
+{
+    func: F,
+}
+
+impl<F> ExclusiveSystem for ExclusiveSystemFn<F>
+where
+    F: FnMut(&mut World)
+{
+    fn initialize(&mut self, _world: &mut World) {}
+
+    fn run(&mut self, world: &mut World) {
+        (self.func)(world)
+    }
+}
+
+/// Where in a stage an exclusive system runs
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ExclusivePosition {
+    /// Before every other system of the stage
+    AtStart,
+    /// In insertion order among the other systems of the stage,
+    /// every system added before it finishes before it runs
+    InOrder,
+    /// After every other system of the stage
+    AtEnd,
+}
+
+pub struct ExclusiveSystemDescriptor {
+    pub system: BoxedExclusiveSystem,
+    pub position: ExclusivePosition,
+}
+
+impl ExclusiveSystemDescriptor {
+    pub fn at_start(mut self) -> Self {
+        self.position = ExclusivePosition::AtStart;
+        self
+    }
+
+    pub fn at_end(mut self) -> Self {
+        self.position = ExclusivePosition::AtEnd;
+        self
+    }
+}
+
+pub trait IntoExclusiveSystem {
+    fn exclusive_system(self) -> ExclusiveSystemDescriptor;
+}
+
+impl<F> IntoExclusiveSystem for F
+where
+    F: FnMut(&mut World) + 'static
+{
+    fn exclusive_system(self) -> ExclusiveSystemDescriptor {
+        ExclusiveSystemDescriptor {
+            system: Box::new(ExclusiveSystemFn { func: self }),
+            position: ExclusivePosition::InOrder,
+        }
+    }
+}
+
+impl IntoExclusiveSystem for ExclusiveSystemDescriptor {
+    fn exclusive_system(self) -> ExclusiveSystemDescriptor {
+        self
+    }
+}
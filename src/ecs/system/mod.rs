@@ -1,8 +1,11 @@
 use std::marker::PhantomData;
 
-use super::World;
+use super::{World, query::AccessState};
 
 pub mod param;
+pub mod exclusive;
+pub mod pipe;
+pub mod system_state;
 
 
 pub trait System {
@@ -11,11 +14,17 @@ pub trait System {
 
     fn initialize(&mut self, world: &mut World);
 
+    /// Components and resources the system reads and writes,
+    /// filled by `System::initialize`
+    fn access(&self) -> &AccessState;
+
     /// # Safety
     /// call `System::initialize` first
     unsafe fn run(&mut self, world: &World, input: Self::In) -> Self::Out;
 }
 
+pub type BoxedSystem<In = (), Out = ()> = Box<dyn System<In = In, Out = Out>>;
+
 pub trait IntoSystem<In, Out, Marker> {
     type Sys: System<In = In, Out = Out>;
 
@@ -24,6 +33,7 @@ pub trait IntoSystem<In, Out, Marker> {
 
 pub trait SystemParamState {
     fn init(world: &mut World) -> Self;
+    fn update_access(&self, _access_state: &mut AccessState) {}
 }
 
 pub trait SystemParamFetch<'w, 's>: SystemParamState {
@@ -31,6 +41,13 @@ pub trait SystemParamFetch<'w, 's>: SystemParamState {
     unsafe fn get_param(state: &'s mut Self, world: &'w World) -> Self::Item;
 }
 
+/// Params that only read the world, they can be fetched through `&World` by `SystemState::get`
+///
+/// # Safety
+/// `get_param` should only give shared access to the world,
+/// `Local` is fine as its value lives in the state
+pub unsafe trait ReadOnlySystemParamFetch: for<'w, 's> SystemParamFetch<'w, 's> {}
+
 pub trait SystemParam {
     type Fetch: for<'w, 's> SystemParamFetch<'w, 's>;
 }
@@ -46,6 +63,7 @@ where
 {
     sfunc: F,
     param_state: Option<<Param as SystemParam>::Fetch>,
+    access: AccessState,
     marker: PhantomData<fn() -> (In, Out, Marker)> // For it to own In, Out, Marker ???
     // The purpose of the generic Marker is to allow
     // having colliding trait implementations
@@ -61,7 +79,14 @@ where
     type Out = Out;
 
     fn initialize(&mut self, world: &mut World) {
-        self.param_state = Some(<Param as SystemParam>::Fetch::init(world));
+        let param_state = <Param as SystemParam>::Fetch::init(world);
+        self.access = AccessState::empty();
+        param_state.update_access(&mut self.access);
+        self.param_state = Some(param_state);
+    }
+
+    fn access(&self) -> &AccessState {
+        &self.access
     }
 
     unsafe fn run(&mut self, world: &World, input: Self::In) -> Self::Out {
@@ -84,15 +109,63 @@ where
         FunctionSystem {
             sfunc: self,
             param_state: None,
+            access: AccessState::empty(),
             marker: PhantomData
         }
     }
 }
 
+pub struct IsSystem;
+
+impl<S: System> IntoSystem<S::In, S::Out, IsSystem> for S {
+    type Sys = S;
+
+    fn system(self) -> Self::Sys {
+        self
+    }
+}
+
 pub struct In<Inp> {
     pub data: Inp
 }
 pub struct InputMarker;
+pub struct NoParamMarker;
+pub struct InputNoParamMarker;
+
+// SystemParam of functions without parameters
+impl SystemParam for () {
+    type Fetch = ();
+}
+
+impl SystemParamState for () {
+    fn init(_world: &mut World) -> Self {}
+}
+
+impl<'w, 's> SystemParamFetch<'w, 's> for () {
+    type Item = ();
+
+    unsafe fn get_param(_state: &'s mut Self, _world: &'w World) -> Self::Item {}
+}
+
+unsafe impl ReadOnlySystemParamFetch for () {}
+
+impl<Out, F> SystemParamFunction<(), Out, (), NoParamMarker> for F
+where
+    F: FnMut() -> Out,
+{
+    unsafe fn run(&mut self, _world: &World, _state: &mut (), _input: ()) -> Out {
+        self()
+    }
+}
+
+impl<Inp, Out, F> SystemParamFunction<Inp, Out, (), InputNoParamMarker> for F
+where
+    F: FnMut(In<Inp>) -> Out,
+{
+    unsafe fn run(&mut self, _world: &World, _state: &mut (), input: Inp) -> Out {
+        self(In{data: input})
+    }
+}
 
 // Example Impl, Should implement this FnMut of varying number of inputs (SystemParam tuples)
 impl<Out, Param: SystemParam, F> SystemParamFunction<(), Out, Param, ()> for F
@@ -1,8 +1,8 @@
 use std::{ops::{Deref, DerefMut}, marker::PhantomData};
 
-use crate::ecs::{query::{state::{Query, QueryState}, fetch::{FetchQuery, FetchState}, filter::{FilterQuery, FilterState}}, World, component::{Resource, ComponentId}, event::{Events, EventReader}};
+use crate::ecs::{query::{AccessState, state::{Query, QueryState}, fetch::{FetchQuery, FetchState, ReadOnlyFetchQuery}, filter::{FilterQuery, FilterState}}, World, component::{Resource, Component, ComponentId}, event::{Events, EventReader}, removal_detection::RemovedComponents};
 
-use super::{SystemParam, SystemParamFetch, SystemParamState, System};
+use super::{SystemParam, SystemParamFetch, SystemParamState, ReadOnlySystemParamFetch, System};
 
 
 impl<'w, 's, Fe: 'static + FetchQuery, Fi: 'static + FilterQuery> SystemParam for Query<'w, 's, Fe, Fi> {
@@ -13,6 +13,10 @@ impl<Fe: 'static + FetchQuery, Fi: 'static + FilterQuery> SystemParamState for Q
     fn init(world: &mut World) -> Self {
         QueryState::new(world)
     }
+
+    fn update_access(&self, access_state: &mut AccessState) {
+        self.update_access(access_state);
+    }
 }
 
 impl<'w, 's, Fe: 'static + FetchQuery, Fi: 'static + FilterQuery> SystemParamFetch<'w, 's> for QueryState<Fe, Fi> {
@@ -23,6 +27,8 @@ impl<'w, 's, Fe: 'static + FetchQuery, Fi: 'static + FilterQuery> SystemParamFet
     }
 }
 
+unsafe impl<Fe: 'static + ReadOnlyFetchQuery, Fi: 'static + FilterQuery> ReadOnlySystemParamFetch for QueryState<Fe, Fi> {}
+
 pub struct Res<'w, T: Resource> {
     val: &'w T
 }
@@ -52,6 +58,10 @@ impl<T: Resource> SystemParamState for ResState<T> {
             marker: PhantomData,
         }
     }
+
+    fn update_access(&self, access_state: &mut AccessState) {
+        access_state.add_read(self.resource_id.clone());
+    }
 }
 
 impl<'w, 's, T: Resource> SystemParamFetch<'w, 's> for ResState<T> {
@@ -67,6 +77,8 @@ impl<'w, 's, T: Resource> SystemParamFetch<'w, 's> for ResState<T> {
     }
 }
 
+unsafe impl<T: Resource> ReadOnlySystemParamFetch for ResState<T> {}
+
 // ResMut
 pub struct ResMut<'w, T: Resource> {
     val: &'w mut T
@@ -104,6 +116,10 @@ impl<T: Resource> SystemParamState for ResMutState<T> {
             marker: PhantomData,
         }
     }
+
+    fn update_access(&self, access_state: &mut AccessState) {
+        access_state.add_write(self.resource_id.clone());
+    }
 }
 
 impl<'w, 's, T: Resource> SystemParamFetch<'w, 's> for ResMutState<T> {
@@ -136,6 +152,10 @@ impl<T: Resource> SystemParamState for OptionResState<T> {
             marker: PhantomData,
         }
     }
+
+    fn update_access(&self, access_state: &mut AccessState) {
+        access_state.add_read(self.resource_id.clone());
+    }
 }
 
 impl<'w, 's, T: Resource> SystemParamFetch<'w, 's> for OptionResState<T> {
@@ -150,6 +170,8 @@ impl<'w, 's, T: Resource> SystemParamFetch<'w, 's> for OptionResState<T> {
     }
 }
 
+unsafe impl<T: Resource> ReadOnlySystemParamFetch for OptionResState<T> {}
+
 impl<'w, T: Resource> SystemParam for Option<ResMut<'w, T>> {
     type Fetch = OptionResMutState<T>;
 }
@@ -167,6 +189,10 @@ impl<T: Resource> SystemParamState for OptionResMutState<T> {
             marker: PhantomData,
         }
     }
+
+    fn update_access(&self, access_state: &mut AccessState) {
+        access_state.add_write(self.resource_id.clone());
+    }
 }
 
 impl<'w, 's, T: Resource> SystemParamFetch<'w, 's> for OptionResMutState<T> {
@@ -222,6 +248,8 @@ impl<'w, 's, T: Resource + Default> SystemParamFetch<'w, 's> for LocalState<T> {
     }
 }
 
+unsafe impl<T: Resource + Default> ReadOnlySystemParamFetch for LocalState<T> {}
+
 
 // IMPORTANT NOTE:
 // This is how you combine SystemParam structs to get a new valid SystemParam
@@ -242,6 +270,10 @@ impl<T: Resource + Default> SystemParamState for EventReaderState<T> {
             last_event_count_state: LocalState::init(world),
         }
     }
+
+    fn update_access(&self, access_state: &mut AccessState) {
+        self.events_state.update_access(access_state);
+    }
 }
 
 impl<'w, 's, T: Resource + Default> SystemParamFetch<'w, 's> for EventReaderState<T> {
@@ -254,23 +286,103 @@ impl<'w, 's, T: Resource + Default> SystemParamFetch<'w, 's> for EventReaderStat
     }
 }
 
+unsafe impl<T: Resource + Default> ReadOnlySystemParamFetch for EventReaderState<T> {}
+
+
+impl<'w, T: Component> SystemParam for RemovedComponents<'w, T> {
+    type Fetch = RemovedComponentsState<T>;
+}
+
+pub struct RemovedComponentsState<T: Component> {
+    component_id: ComponentId,
+    last_read: usize,
+    marker: PhantomData<fn() -> T>,
+}
+
+// the removal logs are only written through `&mut World`, there is no access to declare
+impl<T: Component> SystemParamState for RemovedComponentsState<T> {
+    fn init(world: &mut World) -> Self {
+        RemovedComponentsState {
+            component_id: world.add_component::<T>(),
+            last_read: 0,
+            marker: PhantomData,
+        }
+    }
+}
+
+impl<'w, 's, T: Component> SystemParamFetch<'w, 's> for RemovedComponentsState<T> {
+    type Item = RemovedComponents<'w, T>;
+
+    /// The removals count as read once fetched, whether the system looks at them or not
+    unsafe fn get_param(state: &'s mut Self, world: &'w World) -> Self::Item {
+        let (removed, end) = world.removed_components().since(&state.component_id, state.last_read);
+        state.last_read = end;
+        RemovedComponents::new(removed)
+    }
+}
+
+unsafe impl<T: Component> ReadOnlySystemParamFetch for RemovedComponentsState<T> {}
+
+// Tuples of params, each param gets its own state
+// Accesses of the params are checked against each other when the system is initialized
+macro_rules! impl_system_param_tuple {
+    ($($name:ident),*) => {
+        impl<$($name: SystemParam),*> SystemParam for ($($name,)*) {
+            type Fetch = ($($name::Fetch,)*);
+        }
+
+        #[allow(non_snake_case)]
+        impl<$($name: SystemParamState),*> SystemParamState for ($($name,)*) {
+            fn init(world: &mut World) -> Self {
+                ($($name::init(world),)*)
+            }
+
+            fn update_access(&self, access_state: &mut AccessState) {
+                let ($($name,)*) = self;
+                $(
+                    let mut param_access = AccessState::empty();
+                    $name.update_access(&mut param_access);
+                    assert!(param_access.is_compatible(access_state),
+                        "Param {} conflicts with a previous param of the system", std::any::type_name::<$name>());
+                    access_state.extend(&param_access);
+                )*
+            }
+        }
+
+        #[allow(non_snake_case)]
+        impl<'w, 's, $($name: SystemParamFetch<'w, 's>),*> SystemParamFetch<'w, 's> for ($($name,)*) {
+            type Item = ($($name::Item,)*);
+
+            unsafe fn get_param(state: &'s mut Self, world: &'w World) -> Self::Item {
+                let ($($name,)*) = state;
+                ($($name::get_param($name, world),)*)
+            }
+        }
+
+        unsafe impl<$($name: ReadOnlySystemParamFetch),*> ReadOnlySystemParamFetch for ($($name,)*) {}
+    };
+}
+
+impl_system_param_tuple!(A);
+impl_system_param_tuple!(A, B);
+impl_system_param_tuple!(A, B, C);
+impl_system_param_tuple!(A, B, C, D);
+impl_system_param_tuple!(A, B, C, D, E);
+impl_system_param_tuple!(A, B, C, D, E, F);
+impl_system_param_tuple!(A, B, C, D, E, F, G);
+impl_system_param_tuple!(A, B, C, D, E, F, G, H);
+
 
 #[cfg(test)]
 mod tests {
     
-    use crate::ecs::component::{ComponentDescriptor, ComponentId};
-    use crate::ecs::entity::Entity;
     use crate::ecs::query::state::Query;
     use crate::ecs::system::{FunctionSystem, IntoSystem, System, In};
     use crate::ecs::{Component, World};
+    use crate::transform::Transform;
 
     use super::{Res, Local};
     
-    pub struct Transform {
-        position: (f64, f64, f64),
-        rotation: (f64, f64, f64),
-        scale:    (f64, f64, f64),
-    }
     pub struct Health(u64);
     pub struct Stamina(u64);
     pub struct Name(String);
@@ -289,15 +401,13 @@ mod tests {
     #[test]
     fn system_param_test() {
         let mut world = World::new();
-        let mut components = [
-            ComponentDescriptor::of::<Transform>(ComponentId(0)),
-            ComponentDescriptor::of::<Health>(ComponentId(1)),
-            ComponentDescriptor::of::<Stamina>(ComponentId(2)),
-            ComponentDescriptor::of::<Name>(ComponentId(3)),
-            ComponentDescriptor::of::<Dead>(ComponentId(4)),
-        ];
-        let table = world.get_tables_mut().new_table(&mut components);
-        let row0 = table.add_row(Entity { id: 0, table_id: 0 });
+        world.spawn()
+            .insert(Transform::default())
+            .insert(Health(100))
+            .insert(Stamina(100))
+            .insert(Name("name".to_string()))
+            .insert(Dead);
+        world.insert_resource(FPS(60));
 
         let mut system_query = query_test_func.system();
         let mut system_res = res_test_func.system();
@@ -0,0 +1,121 @@
+use crate::ecs::{World, query::AccessState};
+
+use super::{System, IntoSystem};
+
+
+/// Runs `system_a` and feeds its output as the input of `system_b`
+pub struct PipeSystem<SysA, SysB> {
+    system_a: SysA,
+    system_b: SysB,
+    access: AccessState,
+}
+
+impl<SysA, SysB> System for PipeSystem<SysA, SysB>
+where
+    SysA: System,
+    SysB: System<In = SysA::Out>
+{
+    type In = SysA::In;
+    type Out = SysB::Out;
+
+    fn initialize(&mut self, world: &mut World) {
+        self.system_a.initialize(world);
+        self.system_b.initialize(world);
+
+        self.access = AccessState::empty();
+        self.access.extend(self.system_a.access());
+        self.access.extend(self.system_b.access());
+    }
+
+    fn access(&self) -> &AccessState {
+        &self.access
+    }
+
+    unsafe fn run(&mut self, world: &World, input: Self::In) -> Self::Out {
+        let payload = self.system_a.run(world, input);
+        self.system_b.run(world, payload)
+    }
+}
+
+/// `load_level.pipe(log_errors)`
+pub trait IntoPipeSystem<In, Payload, Out, SysB, MarkerA, MarkerB>: IntoSystem<In, Payload, MarkerA> + Sized
+where
+    SysB: IntoSystem<Payload, Out, MarkerB>
+{
+    fn pipe(self, system: SysB) -> PipeSystem<Self::Sys, SysB::Sys>;
+}
+
+impl<In, Payload, Out, SysA, SysB, MarkerA, MarkerB> IntoPipeSystem<In, Payload, Out, SysB, MarkerA, MarkerB> for SysA
+where
+    SysA: IntoSystem<In, Payload, MarkerA>,
+    SysB: IntoSystem<Payload, Out, MarkerB>
+{
+    fn pipe(self, system: SysB) -> PipeSystem<SysA::Sys, SysB::Sys> {
+        PipeSystem {
+            system_a: self.system(),
+            system_b: system.system(),
+            access: AccessState::empty(),
+        }
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use crate::app::App;
+    use crate::ecs::system::{System, In, IntoSystem, param::{Res, ResMut}};
+    use crate::ecs::World;
+
+    use super::IntoPipeSystem;
+
+    struct Level(u32);
+    #[derive(Default)]
+    struct Errors(Vec<String>);
+
+    fn load_level(level: Res<Level>) -> Result<u32, String> {
+        match level.0 {
+            0 => Err("level 0 does not exist".to_string()),
+            n => Ok(n),
+        }
+    }
+
+    fn log_errors(input: In<Result<u32, String>>, mut errors: ResMut<Errors>) {
+        if let Err(err) = input.data {
+            errors.0.push(err);
+        }
+    }
+
+    fn double(input: In<u32>) -> u32 {
+        input.data * 2
+    }
+
+    #[test]
+    fn pipe_feeds_output_to_input() {
+        let mut app = App::new();
+        app.insert_resource(Level(0))
+            .insert_resource(Errors::default())
+            .add_system(load_level.pipe(log_errors));
+        app.update();
+
+        assert_eq!(vec!["level 0 does not exist".to_string()], app.world().get_resource::<Errors>().unwrap().0);
+    }
+
+    #[test]
+    fn pipe_chains_and_merges_access() {
+        let mut world = World::new();
+        world.insert_resource(Level(4));
+        let mut system = load_level
+            .pipe(|input: In<Result<u32, String>>| input.data.unwrap())
+            .pipe(double)
+            .system();
+        system.initialize(&mut world);
+
+        assert_eq!(8, unsafe { system.run(&world, ()) });
+        assert!(system.access().has_read(&world.add_resource::<Level>()));
+
+        let mut system = load_level.pipe(log_errors);
+        system.initialize(&mut world);
+        assert!(system.access().has_read(&world.add_resource::<Level>()));
+        assert!(system.access().has_write(&world.add_resource::<Errors>()));
+    }
+}
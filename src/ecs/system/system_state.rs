@@ -0,0 +1,101 @@
+use crate::ecs::{World, query::AccessState};
+
+use super::{SystemParam, SystemParamFetch, SystemParamState, ReadOnlySystemParamFetch};
+
+
+/// Params of a system used outside of any system, by tests and tools
+/// The param states are initialized once, keep the `SystemState` to reuse them
+///
+/// `let mut state = SystemState::<(Query<&Health>, Res<Time>)>::new(&mut world);`
+/// `let (health, time) = state.get(&world);`
+pub struct SystemState<P: SystemParam> {
+    param_state: <P as SystemParam>::Fetch,
+    access: AccessState,
+}
+
+impl<P: SystemParam> SystemState<P> {
+    /// # Panics
+    /// if the params have conflicting accesses, like `(Query<&mut T>, Query<&T>)`
+    pub fn new(world: &mut World) -> Self {
+        let param_state = <P as SystemParam>::Fetch::init(world);
+        let mut access = AccessState::empty();
+        param_state.update_access(&mut access);
+        SystemState {
+            param_state,
+            access,
+        }
+    }
+
+    /// Components and resources the params read and write
+    pub fn access(&self) -> &AccessState {
+        &self.access
+    }
+
+    /// Params that only read the world
+    pub fn get<'w, 's>(&'s mut self, world: &'w World) -> <<P as SystemParam>::Fetch as SystemParamFetch<'w, 's>>::Item
+    where
+        <P as SystemParam>::Fetch: ReadOnlySystemParamFetch
+    {
+        unsafe {
+            <P as SystemParam>::Fetch::get_param(&mut self.param_state, world)
+        }
+    }
+
+    /// Any params, the world is borrowed as long as they live
+    pub fn get_mut<'w, 's>(&'s mut self, world: &'w mut World) -> <<P as SystemParam>::Fetch as SystemParamFetch<'w, 's>>::Item {
+        unsafe {
+            <P as SystemParam>::Fetch::get_param(&mut self.param_state, world)
+        }
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use crate::ecs::World;
+    use crate::ecs::query::state::Query;
+    use crate::ecs::system::param::{Res, ResMut, Local};
+
+    use super::SystemState;
+
+    struct Health(u32);
+    struct Poisoned;
+    struct Tick(u32);
+
+    #[test]
+    fn params_outside_systems() {
+        let mut world = World::new();
+        world.insert_resource(Tick(3));
+        world.spawn().insert(Health(10));
+        world.spawn().insert(Health(20)).insert(Poisoned);
+
+        let mut read = SystemState::<(Query<&Health>, Res<Tick>, Local<u32>)>::new(&mut world);
+        {
+            let (health, tick, mut runs) = read.get(&world);
+            assert_eq!(30, health.iter().map(|health| health.0).sum::<u32>());
+            assert_eq!(3, tick.0);
+            *runs += 1;
+        }
+        // the states are kept between calls
+        let (_, _, runs) = read.get(&world);
+        assert_eq!(1, *runs);
+
+        let mut write = SystemState::<(Query<&mut Health>, ResMut<Tick>)>::new(&mut world);
+        let (health, mut tick) = write.get_mut(&mut world);
+        for health in health.iter() {
+            health.0 -= tick.0;
+        }
+        tick.0 += 1;
+
+        let poisoned = world.query_filtered::<&Health, crate::ecs::query::filter::With<Poisoned>>();
+        assert_eq!(vec![17], poisoned.query(&world).iter().map(|health| health.0).collect::<Vec<_>>());
+        assert_eq!(4, world.get_resource::<Tick>().unwrap().0);
+    }
+
+    #[test]
+    #[should_panic]
+    fn conflicting_params_panic() {
+        let mut world = World::new();
+        SystemState::<(Query<&mut Health>, Query<&Health>)>::new(&mut world);
+    }
+}
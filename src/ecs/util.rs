@@ -1,14 +1,490 @@
-use super::{storage::table::{Table, Tables}, World};
+use std::any::Any;
+use std::collections::BTreeMap;
 
+use super::{World, entity::Entity, component::ComponentId, registry::{ComponentRegistry, ComponentRegistration}, error::{SceneError, SceneErrorKind}, storage::table::Table};
 
-pub fn table_from_file(filename: &str) -> Table {
-    todo!()
+use self::yaml::MarkedNode;
+
+mod yaml;
+
+// Table file format, a file can hold multiple tables as YAML documents separated by `---`
+//
+// components:          <- every entity of the table has these components
+//     - Health
+//     - Name
+// defaults:            <- value used when an entity does not give one
+//     Health: 100
+// entities:
+//     - Name: "player"
+//     - Name: "enemy"
+//       Health: 50
+
+
+struct TableColumn<'r> {
+    registration: &'r ComponentRegistration,
+    default: Option<&'r MarkedNode>,
+}
+
+/// Values of a single table file document, deserialized but not yet spawned
+struct LoadedTable<'r> {
+    columns: Vec<&'r ComponentRegistration>,
+    rows: Vec<Vec<Box<dyn Any>>>,
+}
+
+impl<'r> LoadedTable<'r> {
+    fn spawn(self, world: &mut World) -> Vec<Entity> {
+        let mut entities = Vec::with_capacity(self.rows.len());
+        for row in self.rows {
+            let mut entity = world.spawn();
+            for (registration, value) in self.columns.iter().zip(row) {
+                registration.insert_boxed(&mut entity, value);
+            }
+            entities.push(entity.id());
+        }
+        entities
+    }
+}
+
+fn format_error(line: usize, message: &str) -> SceneError {
+    SceneError::new(line, SceneErrorKind::InvalidFormat(message.to_string()))
+}
+
+fn resolve<'r>(registry: &'r ComponentRegistry, name_node: &MarkedNode) -> Result<&'r ComponentRegistration, SceneError> {
+    let name = name_node.as_str()
+        .ok_or_else(|| format_error(name_node.line, "component name should be a string"))?;
+    registry.get(name)
+        .ok_or_else(|| SceneError::new(name_node.line, SceneErrorKind::UnknownComponent(name.to_string())))
+}
+
+fn deserialize(registration: &ComponentRegistration, node: &MarkedNode) -> Result<Box<dyn Any>, SceneError> {
+    registration.deserialize_yaml(node.to_value())
+        .map_err(|err| SceneError::new(node.line, SceneErrorKind::BadValue {
+            component: registration.name().to_string(),
+            message: err.to_string(),
+        }))
+}
+
+fn load_table<'r>(registry: &'r ComponentRegistry, document: &'r MarkedNode) -> Result<LoadedTable<'r>, SceneError> {
+    if document.as_mapping().is_none() {
+        return Err(format_error(document.line, "table should be a mapping"));
+    }
+
+    let mut columns: Vec<TableColumn> = Vec::new();
+    if let Some(components) = document.get("components") {
+        let names = components.as_sequence()
+            .ok_or_else(|| format_error(components.line, "`components` should be a sequence"))?;
+        for name_node in names {
+            let registration = resolve(registry, name_node)?;
+            if columns.iter().all(|column| column.registration.typeid() != registration.typeid()) {
+                columns.push(TableColumn { registration, default: None });
+            }
+        }
+    }
+
+    if let Some(defaults) = document.get("defaults").filter(|node| !node.is_null()) {
+        let entries = defaults.as_mapping()
+            .ok_or_else(|| format_error(defaults.line, "`defaults` should be a mapping"))?;
+        for (name_node, value) in entries {
+            let registration = resolve(registry, name_node)?;
+            let column = columns.iter_mut()
+                .find(|column| column.registration.typeid() == registration.typeid())
+                .ok_or_else(|| SceneError::new(name_node.line, SceneErrorKind::NotAColumn(registration.name().to_string())))?;
+            column.default = Some(value);
+        }
+    }
+
+    let mut rows = Vec::new();
+    if let Some(entities) = document.get("entities").filter(|node| !node.is_null()) {
+        let entity_nodes = entities.as_sequence()
+            .ok_or_else(|| format_error(entities.line, "`entities` should be a sequence"))?;
+        for entity_node in entity_nodes {
+            let overrides: &[(MarkedNode, MarkedNode)] = if entity_node.is_null() {
+                &[]
+            }
+            else {
+                entity_node.as_mapping()
+                    .ok_or_else(|| format_error(entity_node.line, "entity should be a mapping"))?
+            };
+
+            let mut values: Vec<Option<&MarkedNode>> = columns.iter().map(|column| column.default).collect();
+            for (name_node, value) in overrides {
+                let registration = resolve(registry, name_node)?;
+                let index = columns.iter()
+                    .position(|column| column.registration.typeid() == registration.typeid())
+                    .ok_or_else(|| SceneError::new(name_node.line, SceneErrorKind::NotAColumn(registration.name().to_string())))?;
+                values[index] = Some(value);
+            }
+
+            let mut row = Vec::with_capacity(columns.len());
+            for (column, value) in columns.iter().zip(values) {
+                let value = value
+                    .ok_or_else(|| SceneError::new(entity_node.line, SceneErrorKind::MissingValue(column.registration.name().to_string())))?;
+                row.push(deserialize(column.registration, value)?);
+            }
+            rows.push(row);
+        }
+    }
+
+    Ok(LoadedTable {
+        columns: columns.iter().map(|column| column.registration).collect(),
+        rows,
+    })
+}
+
+fn load_tables<'r>(registry: &'r ComponentRegistry, documents: &'r [MarkedNode]) -> Result<Vec<LoadedTable<'r>>, SceneError> {
+    documents.iter()
+        .map(|document| load_table(registry, document))
+        .collect()
+}
+
+fn read_file(filename: &str) -> Result<String, SceneError> {
+    std::fs::read_to_string(filename)
+        .map_err(|err| SceneError::new(0, SceneErrorKind::Io(format!("{}: {}", filename, err))))
+}
+
+/// Spawns the entities of every table in `source` into `world`
+/// Nothing is spawned if any of the tables has an error
+pub fn tables_from_str(source: &str, registry: &ComponentRegistry, world: &mut World) -> Result<Vec<Entity>, SceneError> {
+    let documents = yaml::parse_documents(source)?;
+    let tables = load_tables(registry, &documents)?;
+    Ok(tables.into_iter()
+        .flat_map(|table| table.spawn(world))
+        .collect())
+}
+
+/// Spawns the entities of a file holding a single table into `world`
+pub fn table_from_file(filename: &str, registry: &ComponentRegistry, world: &mut World) -> Result<Vec<Entity>, SceneError> {
+    let source = read_file(filename)?;
+    let documents = yaml::parse_documents(&source)?;
+    if documents.len() > 1 {
+        return Err(format_error(documents[1].line, "file should hold a single table"));
+    }
+    let tables = load_tables(registry, &documents)?;
+    Ok(tables.into_iter()
+        .flat_map(|table| table.spawn(world))
+        .collect())
 }
 
-pub fn tables_from_file(filename: &str) -> Tables {
-    todo!()
+/// Spawns the entities of every table in the file into `world`
+pub fn tables_from_file(filename: &str, registry: &ComponentRegistry, world: &mut World) -> Result<Vec<Entity>, SceneError> {
+    let source = read_file(filename)?;
+    tables_from_str(&source, registry, world)
 }
 
-pub fn world_from_file(filename: &str) -> World {
-    todo!()
-}
\ No newline at end of file
+pub fn world_from_file(filename: &str, registry: &ComponentRegistry) -> Result<World, SceneError> {
+    let mut world = World::new();
+    tables_from_file(filename, registry, &mut world)?;
+    Ok(world)
+}
+
+fn write_file(filename: &str, source: &str) -> Result<(), SceneError> {
+    std::fs::write(filename, source)
+        .map_err(|err| SceneError::new(0, SceneErrorKind::Io(format!("{}: {}", filename, err))))
+}
+
+/// # Safety
+/// `ptr` should point to a valid value of the registered component
+unsafe fn serialize_value(registration: &ComponentRegistration, ptr: *mut u8) -> Result<serde_yaml::Value, SceneError> {
+    registration.serialize_yaml(ptr).unwrap()
+        .map_err(|err| SceneError::new(0, SceneErrorKind::BadValue {
+            component: registration.name().to_string(),
+            message: err.to_string(),
+        }))
+}
+
+/// Builds the table file documents of `table`, one per set of sparse set components its entities have,
+/// so that every entity of a document has the same components
+/// Components without a serializable registration are skipped, rows with no component left give no document
+fn table_documents(world: &World, table: &Table, registry: &ComponentRegistry) -> Result<Vec<serde_yaml::Value>, SceneError> {
+    let serializable = |component_id: &ComponentId| {
+        let descriptor = world.get_components().get_descriptor(component_id)?;
+        registry.get_by_type(descriptor.typeid?)
+            .filter(|registration| registration.is_serializable())
+    };
+    let columns: Vec<_> = table.component_ids()
+        .into_iter()
+        .filter_map(|component_id| {
+            let registration = serializable(&component_id)?;
+            Some((component_id, registration))
+        })
+        .collect();
+    let sparse_sets: Vec<_> = world.get_sparse_sets().iter()
+        .filter_map(|(component_id, sparse_set)| Some((sparse_set, serializable(component_id)?)))
+        .collect();
+
+    // rows keyed by the indices into `sparse_sets` of the components their entity has
+    let mut groups: BTreeMap<Vec<usize>, Vec<usize>> = BTreeMap::new();
+    for (row, entity) in table.entities().iter().enumerate() {
+        let sparse_indices = sparse_sets.iter()
+            .enumerate()
+            .filter(|(_, (sparse_set, _))| sparse_set.contains(*entity))
+            .map(|(index, _)| index)
+            .collect();
+        groups.entry(sparse_indices).or_default().push(row);
+    }
+
+    let mut documents = Vec::new();
+    for (sparse_indices, rows) in groups {
+        if columns.is_empty() && sparse_indices.is_empty() {
+            continue;
+        }
+        let mut entities = Vec::with_capacity(rows.len());
+        for row in rows {
+            let mut entity = serde_yaml::Mapping::new();
+            for (component_id, registration) in columns.iter() {
+                let value = unsafe {
+                    serialize_value(registration, table.get_column(component_id).unwrap().get_unchecked(row))?
+                };
+                entity.insert(registration.name().into(), value);
+            }
+            for index in sparse_indices.iter() {
+                let (sparse_set, registration) = sparse_sets[*index];
+                let value = unsafe {
+                    serialize_value(registration, sparse_set.get(table.entities()[row]).unwrap())?
+                };
+                entity.insert(registration.name().into(), value);
+            }
+            entities.push(serde_yaml::Value::Mapping(entity));
+        }
+
+        let names = columns.iter()
+            .map(|(_, registration)| *registration)
+            .chain(sparse_indices.iter().map(|index| sparse_sets[*index].1))
+            .map(|registration| registration.name().into())
+            .collect();
+        let mut document = serde_yaml::Mapping::new();
+        document.insert("components".into(), serde_yaml::Value::Sequence(names));
+        document.insert("entities".into(), serde_yaml::Value::Sequence(entities));
+        documents.push(serde_yaml::Value::Mapping(document));
+    }
+    Ok(documents)
+}
+
+fn document_to_string(document: &serde_yaml::Value) -> Result<String, SceneError> {
+    // serde_yaml starts every document with `---`, so documents can simply be concatenated
+    serde_yaml::to_string(document)
+        .map_err(|err| SceneError::new(0, SceneErrorKind::InvalidFormat(err.to_string())))
+}
+
+/// Writes every table of `world` holding serializable components, one document per table
+/// The output can be read back with `tables_from_str`
+/// Components stored in sparse sets are written with the table of their entity
+pub fn tables_to_string(world: &World, registry: &ComponentRegistry) -> Result<String, SceneError> {
+    let mut source = String::new();
+    for table in world.get_tables().iter() {
+        for document in table_documents(world, table, registry)? {
+            source.push_str(&document_to_string(&document)?);
+        }
+    }
+    Ok(source)
+}
+
+/// Writes a single table of `world`, the file is empty if the table has nothing to save
+/// Fails if the entities of the table have different sparse set components, they do not fit a single document
+pub fn table_to_file(filename: &str, world: &World, table_id: usize, registry: &ComponentRegistry) -> Result<(), SceneError> {
+    let table = world.get_tables().get_table(table_id)
+        .ok_or_else(|| SceneError::new(0, SceneErrorKind::InvalidFormat(format!("no table with id {}", table_id))))?;
+    let documents = table_documents(world, table, registry)?;
+    let source = match documents.as_slice() {
+        [] => String::new(),
+        [document] => document_to_string(document)?,
+        _ => return Err(SceneError::new(0, SceneErrorKind::InvalidFormat(
+            format!("entities of table {} have different sparse set components", table_id)
+        ))),
+    };
+    write_file(filename, &source)
+}
+
+pub fn world_to_file(filename: &str, world: &World, registry: &ComponentRegistry) -> Result<(), SceneError> {
+    write_file(filename, &tables_to_string(world, registry)?)
+}
+
+
+#[cfg(test)]
+mod tests {
+    use serde::{Deserialize, Serialize};
+
+    use glam::Vec3;
+
+    use crate::ecs::World;
+    use crate::ecs::component::StorageType;
+    use crate::ecs::error::SceneErrorKind;
+    use crate::ecs::registry::ComponentRegistry;
+    use crate::transform::Transform;
+
+    use super::{tables_from_str, tables_to_string, table_to_file, world_from_file};
+
+    #[derive(Debug, PartialEq, Serialize, Deserialize)]
+    struct Name(String);
+    #[derive(Debug, PartialEq, Serialize, Deserialize)]
+    struct Health(u64);
+    #[derive(Debug, PartialEq, Deserialize)]
+    struct Mana(u64);
+    #[derive(Debug, PartialEq, Deserialize)]
+    struct Stamina(u64);
+    #[derive(Debug, PartialEq, Deserialize)]
+    struct Human(bool);
+    #[derive(Debug, PartialEq, Deserialize)]
+    struct Monster(bool);
+    #[derive(Debug, PartialEq, Serialize, Deserialize)]
+    struct Selected(u32);
+
+    fn registry() -> ComponentRegistry {
+        let mut registry = ComponentRegistry::new();
+        registry.register_serializable::<Transform>()
+            .register_serializable::<Name>()
+            .register_serializable::<Health>()
+            .register::<Mana>()
+            .register::<Stamina>()
+            .register::<Human>()
+            .register::<Monster>()
+            .register_serializable::<Selected>();
+        registry
+    }
+
+    #[test]
+    fn load_test_table_file() {
+        let world = world_from_file("res/test.table.yaml", &registry()).unwrap();
+        let entities: Vec<_> = world.get_entities().iter().collect();
+        assert_eq!(3, entities.len());
+
+        let player = entities[0];
+        assert_eq!(Some(&Name("player".to_string())), world.get::<Name>(player));
+        assert_eq!(Some(&Health(250)), world.get::<Health>(player));
+        assert_eq!(Some(&Mana(200)), world.get::<Mana>(player));
+        assert_eq!(Some(&Human(true)), world.get::<Human>(player));
+        let transform = world.get::<Transform>(player).unwrap();
+        assert_eq!(Vec3::new(0.0, 1.0, 0.0), transform.translation);
+        assert_eq!(Vec3::ONE, transform.scale);
+
+        let orc = entities[1];
+        assert_eq!(Some(&Name("orc".to_string())), world.get::<Name>(orc));
+        assert_eq!(Some(&Human(false)), world.get::<Human>(orc));
+        assert_eq!(Some(&Monster(true)), world.get::<Monster>(orc));
+        assert_eq!(Some(&Stamina(150)), world.get::<Stamina>(orc));
+
+        let nameless = entities[2];
+        assert_eq!(Some(&Name("nameless".to_string())), world.get::<Name>(nameless));
+        assert_eq!(Some(&Health(100)), world.get::<Health>(nameless));
+        assert_eq!(Some(&Monster(false)), world.get::<Monster>(nameless));
+    }
+
+    #[test]
+    fn defaults_and_overrides() {
+        let source = "
+components: [Name, Health, Transform]
+defaults:
+    Name: nameless
+    Health: 100
+    Transform:
+        translation: [0, 0, 0]
+        rotation: [0, 0, 0, 1]
+        scale:    [1, 1, 1]
+entities:
+    - Name: player
+      Transform: { translation: [1, 2.5, 3] }
+    - Health: 50
+    - ~
+---
+components: [Monster]
+entities:
+    - Monster: true
+";
+        let mut world = World::new();
+        let entities = tables_from_str(source, &registry(), &mut world).unwrap();
+        assert_eq!(4, entities.len());
+
+        assert_eq!(Some(&Name("player".to_string())), world.get::<Name>(entities[0]));
+        assert_eq!(Some(&Health(100)), world.get::<Health>(entities[0]));
+        assert_eq!(Vec3::new(1.0, 2.5, 3.0), world.get::<Transform>(entities[0]).unwrap().translation);
+        assert_eq!(Some(&Health(50)), world.get::<Health>(entities[1]));
+        assert_eq!(Some(&Name("nameless".to_string())), world.get::<Name>(entities[2]));
+        assert_eq!(Some(&Monster(true)), world.get::<Monster>(entities[3]));
+        assert_eq!(None, world.get::<Name>(entities[3]));
+    }
+
+    #[test]
+    fn errors_have_line_numbers() {
+        let mut world = World::new();
+
+        let err = tables_from_str("components: [Name]\nentities:\n    - Nmae: x\n", &registry(), &mut world).unwrap_err();
+        assert_eq!((3, SceneErrorKind::UnknownComponent("Nmae".to_string())), (err.line, err.kind));
+
+        let err = tables_from_str("components: [Health]\nentities:\n    - Health: 10\n    - Health: many\n", &registry(), &mut world).unwrap_err();
+        assert_eq!(4, err.line);
+        assert!(matches!(err.kind, SceneErrorKind::BadValue { .. }));
+
+        let err = tables_from_str("components: [Health]\nentities:\n    - {}\n", &registry(), &mut world).unwrap_err();
+        assert_eq!((3, SceneErrorKind::MissingValue("Health".to_string())), (err.line, err.kind));
+
+        let err = tables_from_str("components: [Health]\ndefaults:\n    Mana: 1\n", &registry(), &mut world).unwrap_err();
+        assert_eq!((3, SceneErrorKind::NotAColumn("Mana".to_string())), (err.line, err.kind));
+
+        // nothing is spawned on error
+        assert!(world.get_entities().is_empty());
+    }
+
+    #[test]
+    fn save_and_load_round_trip() {
+        let mut world = World::new();
+        world.spawn()
+            .insert(Name("player".to_string()))
+            .insert(Health(80))
+            .insert(Transform::from_xyz(1.0, 2.5, -3.0));
+        world.spawn().insert(Name("with mana".to_string())).insert(Mana(5));
+        world.spawn().insert(Health(7));
+
+        let source = tables_to_string(&world, &registry()).unwrap();
+
+        let mut loaded = World::new();
+        let entities = tables_from_str(&source, &registry(), &mut loaded).unwrap();
+        assert_eq!(3, entities.len());
+
+        let player = entities.iter().copied()
+            .find(|entity| loaded.get::<Name>(*entity) == Some(&Name("player".to_string())))
+            .unwrap();
+        assert_eq!(Some(&Health(80)), loaded.get::<Health>(player));
+        assert_eq!(Transform::from_xyz(1.0, 2.5, -3.0), *loaded.get::<Transform>(player).unwrap());
+
+        // Mana is not serializable so only the name is saved
+        let with_mana = entities.iter().copied()
+            .find(|entity| loaded.get::<Name>(*entity) == Some(&Name("with mana".to_string())))
+            .unwrap();
+        assert_eq!(None, loaded.get::<Mana>(with_mana));
+
+        assert!(entities.iter().any(|entity| loaded.get::<Health>(*entity) == Some(&Health(7)) && loaded.get::<Name>(*entity).is_none()));
+
+        // a loaded file saves back unchanged
+        let saved = tables_to_string(&loaded, &registry()).unwrap();
+        let mut reloaded = World::new();
+        tables_from_str(&saved, &registry(), &mut reloaded).unwrap();
+        assert_eq!(saved, tables_to_string(&reloaded, &registry()).unwrap());
+    }
+
+    #[test]
+    fn sparse_set_components_are_saved() {
+        let mut world = World::new();
+        world.add_component_with_storage::<Selected>(StorageType::SparseSet);
+        let a = world.spawn().insert(Name("a".to_string())).insert(Selected(1)).id();
+        world.spawn().insert(Name("b".to_string()));
+        world.spawn().insert(Selected(3));
+
+        let source = tables_to_string(&world, &registry()).unwrap();
+        let mut loaded = World::new();
+        let entities = tables_from_str(&source, &registry(), &mut loaded).unwrap();
+        assert_eq!(3, entities.len());
+
+        let find = |name: &str| entities.iter().copied()
+            .find(|entity| loaded.get::<Name>(*entity) == Some(&Name(name.to_string())))
+            .unwrap();
+        assert_eq!(Some(&Selected(1)), loaded.get::<Selected>(find("a")));
+        assert_eq!(None, loaded.get::<Selected>(find("b")));
+        assert!(entities.iter().any(|entity| loaded.get::<Selected>(*entity) == Some(&Selected(3)) && loaded.get::<Name>(*entity).is_none()));
+
+        // a and b share a table but not their components
+        let table_id = world.get_entities().get(a).unwrap().table_id;
+        let filename = std::env::temp_dir().join("sparse_set_components_are_saved.table.yaml");
+        let err = table_to_file(filename.to_str().unwrap(), &world, table_id, &registry()).unwrap_err();
+        assert!(matches!(err.kind, SceneErrorKind::InvalidFormat(_)));
+    }
+}
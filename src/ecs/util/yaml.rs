@@ -0,0 +1,152 @@
+use std::collections::HashMap;
+
+use yaml_rust::{Yaml, parser::{Parser, Event, MarkedEventReceiver}, scanner::{Marker, TScalarStyle}};
+
+use crate::ecs::error::{SceneError, SceneErrorKind};
+
+
+/// YAML node remembering the line it starts on
+#[derive(Debug, Clone)]
+pub struct MarkedNode {
+    pub line: usize,
+    pub value: MarkedValue,
+}
+
+#[derive(Debug, Clone)]
+pub enum MarkedValue {
+    Scalar(String, TScalarStyle),
+    Sequence(Vec<MarkedNode>),
+    Mapping(Vec<(MarkedNode, MarkedNode)>),
+}
+
+impl MarkedNode {
+    pub fn is_null(&self) -> bool {
+        matches!(&self.value, MarkedValue::Scalar(s, TScalarStyle::Plain) if s == "~" || s == "null" || s.is_empty())
+    }
+
+    pub fn as_str(&self) -> Option<&str> {
+        match &self.value {
+            MarkedValue::Scalar(s, _) => Some(s),
+            _ => None,
+        }
+    }
+
+    pub fn as_sequence(&self) -> Option<&[MarkedNode]> {
+        match &self.value {
+            MarkedValue::Sequence(nodes) => Some(nodes),
+            _ => None,
+        }
+    }
+
+    pub fn as_mapping(&self) -> Option<&[(MarkedNode, MarkedNode)]> {
+        match &self.value {
+            MarkedValue::Mapping(entries) => Some(entries),
+            _ => None,
+        }
+    }
+
+    pub fn get(&self, key: &str) -> Option<&MarkedNode> {
+        self.as_mapping()?
+            .iter()
+            .find(|(k, _)| k.as_str() == Some(key))
+            .map(|(_, v)| v)
+    }
+
+    pub fn to_value(&self) -> serde_yaml::Value {
+        match &self.value {
+            MarkedValue::Scalar(s, TScalarStyle::Plain) => scalar_to_value(s),
+            MarkedValue::Scalar(s, _) => serde_yaml::Value::String(s.clone()),
+            MarkedValue::Sequence(nodes) => serde_yaml::Value::Sequence(
+                nodes.iter().map(|node| node.to_value()).collect()
+            ),
+            MarkedValue::Mapping(entries) => serde_yaml::Value::Mapping(
+                entries.iter().map(|(k, v)| (k.to_value(), v.to_value())).collect()
+            ),
+        }
+    }
+}
+
+fn scalar_to_value(s: &str) -> serde_yaml::Value {
+    match Yaml::from_str(s) {
+        Yaml::Integer(i) => serde_yaml::Value::Number(i.into()),
+        Yaml::Real(r) => match r.parse::<f64>() {
+            Ok(f) => serde_yaml::Value::Number(f.into()),
+            Err(_) => serde_yaml::Value::String(r),
+        },
+        Yaml::Boolean(b) => serde_yaml::Value::Bool(b),
+        Yaml::Null => serde_yaml::Value::Null,
+        _ if s.is_empty() => serde_yaml::Value::Null,
+        _ => serde_yaml::Value::String(s.to_string()),
+    }
+}
+
+struct PendingNode {
+    node: MarkedNode,
+    anchor: usize,
+    key: Option<MarkedNode>,
+}
+
+#[derive(Default)]
+struct MarkedLoader {
+    documents: Vec<MarkedNode>,
+    stack: Vec<PendingNode>,
+    anchors: HashMap<usize, MarkedNode>,
+}
+
+impl MarkedLoader {
+    fn push_node(&mut self, node: MarkedNode, anchor: usize) {
+        if anchor > 0 {
+            self.anchors.insert(anchor, node.clone());
+        }
+        match self.stack.last_mut() {
+            None => self.documents.push(node),
+            Some(parent) => match &mut parent.node.value {
+                MarkedValue::Sequence(nodes) => nodes.push(node),
+                MarkedValue::Mapping(entries) => match parent.key.take() {
+                    None => parent.key = Some(node),
+                    Some(key) => entries.push((key, node)),
+                },
+                MarkedValue::Scalar(..) => unreachable!(),
+            },
+        }
+    }
+}
+
+impl MarkedEventReceiver for MarkedLoader {
+    fn on_event(&mut self, event: Event, mark: Marker) {
+        match event {
+            Event::Scalar(value, style, anchor, _) => {
+                self.push_node(MarkedNode { line: mark.line(), value: MarkedValue::Scalar(value, style) }, anchor);
+            },
+            Event::SequenceStart(anchor) => self.stack.push(PendingNode {
+                node: MarkedNode { line: mark.line(), value: MarkedValue::Sequence(Vec::new()) },
+                anchor,
+                key: None,
+            }),
+            Event::MappingStart(anchor) => self.stack.push(PendingNode {
+                node: MarkedNode { line: mark.line(), value: MarkedValue::Mapping(Vec::new()) },
+                anchor,
+                key: None,
+            }),
+            Event::SequenceEnd | Event::MappingEnd => {
+                let pending = self.stack.pop().unwrap();
+                self.push_node(pending.node, pending.anchor);
+            },
+            Event::Alias(anchor) => {
+                let node = self.anchors.get(&anchor).cloned()
+                    .unwrap_or(MarkedNode { line: mark.line(), value: MarkedValue::Scalar("~".to_string(), TScalarStyle::Plain) });
+                self.push_node(node, 0);
+            },
+            _ => {},
+        }
+    }
+}
+
+/// Parses every document of the source
+pub fn parse_documents(source: &str) -> Result<Vec<MarkedNode>, SceneError> {
+    let mut loader = MarkedLoader::default();
+    Parser::new(source.chars())
+        .load(&mut loader, true)
+        .map_err(|err| SceneError::new(err.marker().line(), SceneErrorKind::Syntax(err.to_string())))?;
+    Ok(loader.documents)
+}
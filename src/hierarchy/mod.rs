@@ -0,0 +1,295 @@
+use crate::ecs::{World, component::ComponentId, entity::Entity, entity_ref::EntityMut, entity_map::{EntityMap, MapEntities}};
+
+
+/// Parent of the entity, kept in sync with the `Children` of the parent by component hooks,
+/// registered by `register_hierarchy`, `BuildWorldChildren` and `App::new`
+/// - inserting it appends the entity to the `Children` of the parent
+/// - removing it or despawning the entity removes the entity from them
+///
+/// Inserting it over another `Parent` leaves the entity in the `Children` of the old parent,
+/// `BuildWorldChildren::set_parent` detaches it first
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Parent(pub Entity);
+
+/// Children of the entity in insertion order
+/// Modified through `BuildWorldChildren` so that it stays in sync with `Parent`
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Children(pub(crate) Vec<Entity>);
+
+impl Children {
+    pub fn iter(&self) -> impl Iterator<Item = Entity> + '_ {
+        self.0.iter().copied()
+    }
+}
+
+impl std::ops::Deref for Children {
+    type Target = [Entity];
+
+    fn deref(&self) -> &Self::Target {
+        &self.0
+    }
+}
+
+impl MapEntities for Parent {
+    fn map_entities(&mut self, entity_map: &EntityMap) {
+        self.0 = entity_map.map(self.0);
+    }
+}
+
+impl MapEntities for Children {
+    fn map_entities(&mut self, entity_map: &EntityMap) {
+        for child in self.0.iter_mut() {
+            *child = entity_map.map(*child);
+        }
+    }
+}
+
+fn parent_inserted(world: &mut World, child: Entity, _component_id: ComponentId) {
+    let Parent(parent) = *world.get::<Parent>(child).unwrap();
+    if !world.contains_entity(parent) {
+        return;
+    }
+    match world.get_mut::<Children>(parent) {
+        Some(children) => if !children.0.contains(&child) {
+            children.0.push(child);
+        },
+        None => {
+            world.entity_mut(parent).insert(Children(vec![child]));
+        },
+    }
+}
+
+fn parent_removed(world: &mut World, child: Entity, _component_id: ComponentId) {
+    let Parent(parent) = *world.get::<Parent>(child).unwrap();
+    let now_empty = match world.get_mut::<Children>(parent) {
+        Some(children) => match children.0.iter().position(|entity| *entity == child) {
+            Some(index) => {
+                children.0.remove(index);
+                children.0.is_empty()
+            },
+            None => false,
+        },
+        None => false,
+    };
+    if now_empty {
+        world.entity_mut(parent).remove::<Children>();
+    }
+}
+
+/// The children become roots
+fn children_removed(world: &mut World, parent: Entity, _component_id: ComponentId) {
+    // taken first, so that `parent_removed` does not look them up
+    let children = std::mem::take(&mut world.get_mut::<Children>(parent).unwrap().0);
+    for child in children {
+        if world.get::<Parent>(child) == Some(&Parent(parent)) {
+            world.entity_mut(child).remove::<Parent>();
+        }
+    }
+}
+
+/// Registers the hooks keeping `Parent` and `Children` in sync, and their entity remapping
+pub fn register_hierarchy(world: &mut World) {
+    world.register_map_entities::<Parent>();
+    world.register_map_entities::<Children>();
+    world.register_component_hooks::<Parent>()
+        .on_insert(parent_inserted)
+        .on_remove(parent_removed);
+    world.register_component_hooks::<Children>()
+        .on_remove(children_removed);
+}
+
+/// Removes the `Parent` of `child`, the hooks remove it from the `Children` of the parent
+fn detach(world: &mut World, child: Entity) {
+    world.entity_mut(child).remove::<Parent>();
+}
+
+/// # Panics
+/// if `parent` is `child` or one of its descendants
+fn attach(world: &mut World, child: Entity, parent: Entity) {
+    assert!(parent != child && !ancestors(world, parent).any(|ancestor| ancestor == child),
+        "Setting {:?} as parent of {:?} would create a cycle", parent, child);
+    assert!(world.contains_entity(parent), "Entity {:?} does not exist", parent);
+    register_hierarchy(world);
+    detach(world, child);
+    world.entity_mut(child).insert(Parent(parent));
+}
+
+/// Despawns the entity and all of its descendants, returns false if it does not exist
+pub fn despawn_recursive(world: &mut World, entity: Entity) -> bool {
+    if !world.contains_entity(entity) {
+        return false;
+    }
+    detach(world, entity);
+    let descendants: Vec<Entity> = descendants(world, entity).collect();
+    world.despawn(entity);
+    for descendant in descendants {
+        world.despawn(descendant);
+    }
+    true
+}
+
+/// Hierarchy edits on a single entity
+///
+/// `world.spawn().insert(Name("root")).push_children(&[a, b]).id()`
+pub trait BuildWorldChildren {
+    /// Appends `children`, taking them away from their previous parents
+    fn push_children(&mut self, children: &[Entity]) -> &mut Self;
+    fn set_parent(&mut self, parent: Entity) -> &mut Self;
+    fn remove_parent(&mut self) -> &mut Self;
+    fn despawn_recursive(self);
+}
+
+impl<'w> BuildWorldChildren for EntityMut<'w> {
+    fn push_children(&mut self, children: &[Entity]) -> &mut Self {
+        let parent = self.id();
+        for child in children {
+            attach(self.world_mut(), *child, parent);
+        }
+        self
+    }
+
+    fn set_parent(&mut self, parent: Entity) -> &mut Self {
+        let child = self.id();
+        attach(self.world_mut(), child, parent);
+        self
+    }
+
+    fn remove_parent(&mut self) -> &mut Self {
+        let child = self.id();
+        detach(self.world_mut(), child);
+        self
+    }
+
+    fn despawn_recursive(mut self) {
+        let entity = self.id();
+        despawn_recursive(self.world_mut(), entity);
+    }
+}
+
+/// Parent, grandparent... of the entity up to its root
+pub fn ancestors(world: &World, entity: Entity) -> AncestorIter<'_> {
+    AncestorIter {
+        world,
+        current: entity,
+    }
+}
+
+pub struct AncestorIter<'w> {
+    world: &'w World,
+    current: Entity,
+}
+
+impl<'w> Iterator for AncestorIter<'w> {
+    type Item = Entity;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let Parent(parent) = *self.world.get::<Parent>(self.current)?;
+        self.current = parent;
+        Some(parent)
+    }
+}
+
+/// Descendants of the entity in depth first pre-order, the entity itself is not included
+pub fn descendants(world: &World, entity: Entity) -> DescendantIter<'_> {
+    let mut stack = Vec::new();
+    if let Some(children) = world.get::<Children>(entity) {
+        stack.extend(children.0.iter().rev());
+    }
+    DescendantIter {
+        world,
+        stack,
+    }
+}
+
+pub struct DescendantIter<'w> {
+    world: &'w World,
+    stack: Vec<Entity>,
+}
+
+impl<'w> Iterator for DescendantIter<'w> {
+    type Item = Entity;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let entity = self.stack.pop()?;
+        if let Some(children) = self.world.get::<Children>(entity) {
+            self.stack.extend(children.0.iter().rev());
+        }
+        Some(entity)
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use crate::ecs::World;
+
+    use super::{BuildWorldChildren, Children, Parent, ancestors, descendants, despawn_recursive};
+
+    #[test]
+    fn build_and_despawn_recursive() {
+        let mut world = World::new();
+        let [a, b, c, d] = [(); 4].map(|_| world.spawn().id());
+        let root = world.spawn().push_children(&[a, b]).id();
+        world.entity_mut(c).set_parent(a);
+        world.entity_mut(d).set_parent(c);
+
+        assert_eq!(vec![a, c, d, b], descendants(&world, root).collect::<Vec<_>>());
+        assert_eq!(vec![c, a, root], ancestors(&world, d).collect::<Vec<_>>());
+
+        // moving c under b
+        world.entity_mut(b).push_children(&[c]);
+        assert_eq!(None, world.get::<Children>(a));
+        assert_eq!(&[c], &**world.get::<Children>(b).unwrap());
+
+        world.entity_mut(b).remove_parent();
+        assert_eq!(None, world.get::<Parent>(b));
+        assert_eq!(&[a], &**world.get::<Children>(root).unwrap());
+
+        assert!(despawn_recursive(&mut world, b));
+        assert!(!world.contains_entity(c) && !world.contains_entity(d));
+        world.entity_mut(root).despawn_recursive();
+        assert!(world.get_entities().is_empty());
+    }
+
+    #[test]
+    #[should_panic]
+    fn cycle_panics() {
+        let mut world = World::new();
+        let a = world.spawn().id();
+        let b = world.spawn().set_parent(a).id();
+        world.entity_mut(a).set_parent(b);
+    }
+
+    #[test]
+    fn hooks_keep_parent_and_children_in_sync() {
+        let mut world = World::new();
+        let [a, b, c] = [(); 3].map(|_| world.spawn().id());
+        let root = world.spawn().push_children(&[a, b]).id();
+
+        world.despawn(a);
+        assert_eq!(vec![b], world.get::<Children>(root).unwrap().iter().collect::<Vec<_>>());
+        world.entity_mut(c).insert(Parent(root));
+        assert_eq!(vec![b, c], world.get::<Children>(root).unwrap().iter().collect::<Vec<_>>());
+
+        let child = world.spawn().set_parent(b).id();
+        world.despawn(b);
+        assert_eq!(None, world.get::<Parent>(child));
+        assert_eq!(vec![c], world.get::<Children>(root).unwrap().iter().collect::<Vec<_>>());
+
+        world.entity_mut(c).remove::<Parent>();
+        assert_eq!(None, world.get::<Children>(root));
+    }
+
+    #[test]
+    fn unrelated_changes_leave_children_unchanged() {
+        let mut world = World::new();
+        let child = world.spawn().id();
+        let root = world.spawn().push_children(&[child]).id();
+        let tick = world.increment_change_tick();
+
+        let other = world.spawn().set_parent(child).id();
+        world.despawn(other);
+        assert!(!world.is_changed_since::<Children>(root, tick));
+        assert_eq!(None, world.get::<Children>(child));
+    }
+}
@@ -1,7 +1,15 @@
+// lets `light_macros` refer to `::light` from inside this crate
+extern crate self as light;
+
 
 pub mod log;
 pub mod ecs;
 pub mod app;
 pub mod input;
 pub mod render;
+pub mod time;
+pub mod hierarchy;
+pub mod relation;
+pub mod observer;
+pub mod transform;
 /*pub mod math;*/
\ No newline at end of file
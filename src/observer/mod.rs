@@ -0,0 +1,406 @@
+use std::{cell::{Ref, RefCell, RefMut}, collections::HashMap, marker::PhantomData, rc::Rc, sync::{Arc, Mutex, PoisonError}};
+
+use crate::ecs::{World, entity::Entity, entity_ref::EntityMut, component::{Component, ComponentId}, query::AccessState};
+use crate::ecs::system::{IntoSystem, System};
+use crate::hierarchy::Parent;
+
+
+struct TriggerState<E> {
+    event: E,
+    propagate: bool,
+}
+
+/// Input of the observers of `E`, the observers can change the event for the ones running after them
+/// The handle is not `Send`, it cannot outlive the run in a resource or a `Local`
+///
+/// `world.observe(|trigger: In<Trigger<Damage>>, mut healths: Query<&mut Health>| { .. })`
+pub struct Trigger<E> {
+    state: Rc<RefCell<TriggerState<E>>>,
+    target: Option<Entity>,
+    current: Option<Entity>,
+}
+
+impl<E> Trigger<E> {
+    fn new(event: E, target: Option<Entity>) -> Self {
+        Trigger {
+            state: Rc::new(RefCell::new(TriggerState {
+                event,
+                propagate: target.is_some(),
+            })),
+            target,
+            current: target,
+        }
+    }
+
+    /// Handle given to the next observer
+    fn handle(&self) -> Self {
+        Trigger {
+            state: self.state.clone(),
+            target: self.target,
+            current: self.current,
+        }
+    }
+
+    pub fn event(&self) -> Ref<'_, E> {
+        Ref::map(self.state.borrow(), |state| &state.event)
+    }
+
+    pub fn event_mut(&self) -> RefMut<'_, E> {
+        RefMut::map(self.state.borrow_mut(), |state| &mut state.event)
+    }
+
+    /// Entity the event was triggered on, None for `TriggerWorld::trigger`
+    pub fn target(&self) -> Option<Entity> {
+        self.target
+    }
+
+    /// Entity whose observers are running, an ancestor of `target` while propagating
+    pub fn current(&self) -> Option<Entity> {
+        self.current
+    }
+
+    /// The event does not go further up the hierarchy,
+    /// the remaining observers of the current entity still run
+    pub fn stop_propagation(&self) {
+        self.state.borrow_mut().propagate = false;
+    }
+
+    pub fn propagates(&self) -> bool {
+        self.state.borrow().propagate
+    }
+
+    /// # Panics
+    /// if an observer kept its handle
+    fn into_event(self) -> E {
+        match Rc::try_unwrap(self.state) {
+            Ok(state) => state.into_inner().event,
+            Err(_) => panic!("an observer of {} kept its Trigger", std::any::type_name::<E>()),
+        }
+    }
+}
+
+type ObserverSystem<E> = Arc<Mutex<Box<dyn System<In = Trigger<E>, Out = ()> + Send>>>;
+
+/// Observer systems of `E` in registration order, stored as a resource
+/// They are initialized on registration and run with exclusive access to the world
+pub struct Observers<E> {
+    global: Vec<ObserverSystem<E>>,
+    entities: HashMap<Entity, Vec<ObserverSystem<E>>>,
+}
+
+impl<E> Default for Observers<E> {
+    fn default() -> Self {
+        Observers {
+            global: Vec::new(),
+            entities: HashMap::new(),
+        }
+    }
+}
+
+impl<E> Observers<E> {
+    pub fn global_len(&self) -> usize {
+        self.global.len()
+    }
+
+    /// Number of observers watching `entity`
+    pub fn entity_len(&self, entity: Entity) -> usize {
+        self.entities.get(&entity).map(Vec::len).unwrap_or_default()
+    }
+
+    /// Components and resources the observers read and write
+    pub fn access(&self) -> AccessState {
+        let mut access = AccessState::empty();
+        for observer in self.global.iter().chain(self.entities.values().flatten()) {
+            access.extend(observer.lock().unwrap_or_else(PoisonError::into_inner).access());
+        }
+        access
+    }
+}
+
+/// Marks the entities having observers of `E`, removing it removes the observers,
+/// which also happens when the entity is despawned or moved to another world
+pub struct ObservedBy<E>(PhantomData<fn() -> E>);
+
+fn remove_entity_observers<E: Component>(world: &mut World, entity: Entity, _component_id: ComponentId) {
+    if let Some(observers) = world.get_resource_mut::<Observers<E>>() {
+        observers.entities.remove(&entity);
+    }
+}
+
+/// The observers were initialized with this world, they stay behind
+fn remove_observed_by<E: Component>(world: &mut World, entity: Entity, _component_id: ComponentId) {
+    world.entity_mut(entity).remove::<ObservedBy<E>>();
+}
+
+fn observers_mut<E: Component>(world: &mut World) -> &mut Observers<E> {
+    if !world.contains_resource::<Observers<E>>() {
+        world.insert_resource(Observers::<E>::default());
+    }
+    world.get_resource_mut::<Observers<E>>().unwrap()
+}
+
+fn observer_system<E, M, S>(world: &mut World, observer: S) -> ObserverSystem<E>
+where
+    E: Component,
+    S: IntoSystem<Trigger<E>, (), M>,
+    S::Sys: Send + 'static
+{
+    let mut system = observer.system();
+    system.initialize(world);
+    Arc::new(Mutex::new(Box::new(system)))
+}
+
+/// Runs `observer` for every `E` triggered in the world
+pub fn observe<E, M, S>(world: &mut World, observer: S)
+where
+    E: Component,
+    S: IntoSystem<Trigger<E>, (), M>,
+    S::Sys: Send + 'static
+{
+    let observer = observer_system(world, observer);
+    observers_mut::<E>(world).global.push(observer);
+}
+
+/// Runs `observer` for the `E` triggered on `entity` or on one of its descendants
+///
+/// # Panics
+/// if `entity` does not exist
+pub fn observe_entity<E, M, S>(world: &mut World, entity: Entity, observer: S)
+where
+    E: Component,
+    S: IntoSystem<Trigger<E>, (), M>,
+    S::Sys: Send + 'static
+{
+    assert!(world.contains_entity(entity), "Entity {:?} does not exist", entity);
+    let observer = observer_system(world, observer);
+    observers_mut::<E>(world).entities
+        .entry(entity)
+        .or_default()
+        .push(observer);
+    if world.get::<ObservedBy<E>>(entity).is_none() {
+        world.register_component_hooks::<ObservedBy<E>>()
+            .on_remove(remove_entity_observers::<E>)
+            .on_move(remove_observed_by::<E>);
+        world.entity_mut(entity).insert(ObservedBy::<E>(PhantomData));
+    }
+}
+
+fn run_observers<E: Component>(world: &mut World, trigger: &Trigger<E>, observers: Vec<ObserverSystem<E>>) {
+    for observer in observers {
+        let mut observer = observer.lock().unwrap_or_else(PoisonError::into_inner);
+        // Safety: initialized on registration, `&mut World` keeps any other access out
+        unsafe {
+            observer.run(world, trigger.handle());
+        }
+    }
+}
+
+fn entity_observers<E: Component>(world: &World, entity: Entity) -> Vec<ObserverSystem<E>> {
+    world.get_resource::<Observers<E>>()
+        .and_then(|observers| observers.entities.get(&entity))
+        .cloned()
+        .unwrap_or_default()
+}
+
+fn global_observers<E: Component>(world: &World) -> Vec<ObserverSystem<E>> {
+    world.get_resource::<Observers<E>>()
+        .map(|observers| observers.global.clone())
+        .unwrap_or_default()
+}
+
+/// Runs the global observers of `E`, returns the event as the observers left it
+pub fn trigger<E: Component>(world: &mut World, event: E) -> E {
+    let trigger = Trigger::new(event, None);
+    let observers = global_observers::<E>(world);
+    run_observers(world, &trigger, observers);
+    trigger.into_event()
+}
+
+/// Runs the observers of `target`, then the global observers of `E`,
+/// then the observers of each ancestor of `target` until one stops the propagation
+/// Returns the event as the observers left it
+///
+/// # Panics
+/// if `target` does not exist
+pub fn trigger_targets<E: Component>(world: &mut World, event: E, target: Entity) -> E {
+    assert!(world.contains_entity(target), "Entity {:?} does not exist", target);
+    let mut trigger = Trigger::new(event, Some(target));
+    let observers = entity_observers::<E>(world, target);
+    run_observers(world, &trigger, observers);
+    let observers = global_observers::<E>(world);
+    run_observers(world, &trigger, observers);
+
+    let mut current = target;
+    while trigger.propagates() {
+        current = match world.get::<Parent>(current) {
+            Some(Parent(parent)) if world.contains_entity(*parent) => *parent,
+            _ => break,
+        };
+        trigger.current = Some(current);
+        let observers = entity_observers::<E>(world, current);
+        run_observers(world, &trigger, observers);
+    }
+    trigger.into_event()
+}
+
+/// Observer registration and triggers on the world
+///
+/// `world.trigger_targets(Damage(10), entity)`
+pub trait TriggerWorld {
+    fn observe<E, M, S>(&mut self, observer: S) -> &mut Self
+    where
+        E: Component,
+        S: IntoSystem<Trigger<E>, (), M>,
+        S::Sys: Send + 'static;
+    fn trigger<E: Component>(&mut self, event: E) -> E;
+    fn trigger_targets<E: Component>(&mut self, event: E, target: Entity) -> E;
+}
+
+impl TriggerWorld for World {
+    fn observe<E, M, S>(&mut self, observer: S) -> &mut Self
+    where
+        E: Component,
+        S: IntoSystem<Trigger<E>, (), M>,
+        S::Sys: Send + 'static
+    {
+        observe(self, observer);
+        self
+    }
+
+    fn trigger<E: Component>(&mut self, event: E) -> E {
+        trigger(self, event)
+    }
+
+    fn trigger_targets<E: Component>(&mut self, event: E, target: Entity) -> E {
+        trigger_targets(self, event, target)
+    }
+}
+
+/// Observers watching a single entity, removed when it is despawned
+///
+/// `world.spawn().observe(|trigger: In<Trigger<Damage>>, mut healths: Query<&mut Health>| { .. }).id()`
+pub trait BuildObservers {
+    fn observe<E, M, S>(&mut self, observer: S) -> &mut Self
+    where
+        E: Component,
+        S: IntoSystem<Trigger<E>, (), M>,
+        S::Sys: Send + 'static;
+}
+
+impl<'w> BuildObservers for EntityMut<'w> {
+    fn observe<E, M, S>(&mut self, observer: S) -> &mut Self
+    where
+        E: Component,
+        S: IntoSystem<Trigger<E>, (), M>,
+        S::Sys: Send + 'static
+    {
+        let entity = self.id();
+        observe_entity(self.world_mut(), entity, observer);
+        self
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use crate::ecs::{World, entity::Entity, query::state::Query, system::{In, param::{Res, ResMut}}};
+    use crate::hierarchy::BuildWorldChildren;
+
+    use super::{BuildObservers, Observers, Trigger, TriggerWorld};
+
+    struct Damage(u32);
+    struct Health(u32);
+    /// Stops the propagation of damage
+    struct Shield;
+    #[derive(Default)]
+    struct Seen(Vec<(&'static str, Entity)>);
+
+    fn take_damage(trigger: In<Trigger<Damage>>, (mut healths, shields, mut seen): (Query<&mut Health>, Query<&Shield>, ResMut<Seen>)) {
+        let trigger = trigger.data;
+        let entity = trigger.current().unwrap();
+        seen.0.push(("entity", entity));
+        healths.get_mut(entity).unwrap().0 -= trigger.event().0;
+        if shields.get(entity).is_some() {
+            trigger.stop_propagation();
+        }
+    }
+
+    fn double_damage(trigger: In<Trigger<Damage>>, mut seen: ResMut<Seen>) {
+        let trigger = trigger.data;
+        seen.0.push(("global", trigger.target().unwrap()));
+        // doubles the damage for the next observers
+        trigger.event_mut().0 *= 2;
+    }
+
+    #[test]
+    fn propagation_up_the_hierarchy() {
+        let mut world = World::new();
+        world.insert_resource(Seen::default());
+        let root = world.spawn().insert(Health(100)).observe(take_damage).id();
+        let shielded = world.spawn().insert(Health(100)).insert(Shield).observe(take_damage).id();
+        let hand = world.spawn().id();
+        world.entity_mut(root).push_children(&[shielded]);
+        world.entity_mut(shielded).push_children(&[hand]);
+        world.observe(double_damage);
+
+        let event = world.trigger_targets(Damage(5), hand);
+        assert_eq!(10, event.0);
+        assert_eq!(vec![("global", hand), ("entity", shielded)], world.get_resource::<Seen>().unwrap().0);
+        assert_eq!(90, world.get::<Health>(shielded).unwrap().0);
+        assert_eq!(100, world.get::<Health>(root).unwrap().0);
+
+        world.entity_mut(shielded).remove::<Shield>();
+        world.trigger_targets(Damage(1), shielded);
+        // the global observer doubles the damage after the target took it
+        assert_eq!(89, world.get::<Health>(shielded).unwrap().0);
+        assert_eq!(98, world.get::<Health>(root).unwrap().0);
+
+        // entity observers go away with the entity
+        assert_eq!(1, world.get_resource::<Observers<Damage>>().unwrap().entity_len(root));
+        world.despawn(root);
+        assert_eq!(0, world.get_resource::<Observers<Damage>>().unwrap().entity_len(root));
+    }
+
+    #[test]
+    fn observers_declare_access() {
+        struct Multiplier(u32);
+
+        let mut world = World::new();
+        world.insert_resource(Seen::default());
+        world.insert_resource(Multiplier(3));
+        let target = world.spawn().insert(Health(100)).observe(take_damage).id();
+        world.observe(|trigger: In<Trigger<Damage>>, multiplier: Res<Multiplier>| {
+            trigger.data.event_mut().0 *= multiplier.0;
+        });
+
+        assert_eq!(6, world.trigger(Damage(2)).0);
+        // the target takes the damage before the global observer multiplies it
+        assert_eq!(6, world.trigger_targets(Damage(2), target).0);
+        assert_eq!(98, world.get::<Health>(target).unwrap().0);
+
+        let access = world.get_resource::<Observers<Damage>>().unwrap().access();
+        assert!(access.has_write(&world.add_component::<Health>()));
+        assert!(access.has_read(&world.add_component::<Shield>()));
+        assert!(access.has_write(&world.add_resource::<Seen>()));
+        assert!(access.has_read(&world.add_resource::<Multiplier>()));
+    }
+
+    #[test]
+    fn moved_entities_leave_their_observers() {
+        let mut world = World::new();
+        world.insert_resource(Seen::default());
+        let target = world.spawn().insert(Health(100)).observe(take_damage).id();
+
+        let mut other = World::new();
+        other.insert_resource(Seen::default());
+        let moved = other.move_entities_from(&mut world, &[target]).map(target);
+        assert_eq!(0, world.get_resource::<Observers<Damage>>().unwrap().entity_len(target));
+        assert!(other.get_resource::<Observers<Damage>>().is_none());
+
+        other.trigger_targets(Damage(10), moved);
+        assert_eq!(100, other.get::<Health>(moved).unwrap().0);
+        other.entity_mut(moved).observe(take_damage);
+        other.trigger_targets(Damage(10), moved);
+        assert_eq!(90, other.get::<Health>(moved).unwrap().0);
+    }
+}
@@ -0,0 +1,315 @@
+use std::marker::PhantomData;
+
+use crate::ecs::{World, entity::Entity, entity_ref::EntityMut, component::{Component, ComponentId}, entity_map::{EntityMap, MapEntities}};
+use crate::ecs::query::{state::Query, fetch::{FetchQuery, Fetch, ReadOnlyFetchQuery}, filter::FilterQuery};
+
+
+/// Typed edges from the entity to its targets, `R` is the kind of edge and holds its data
+/// Modified through `BuildRelations` so that it stays in sync with `RelationSources`,
+/// despawning a source or a target removes its edges through the hooks of `register_relation`
+///
+/// `world.entity_mut(ship).add_relation(DockedAt { slot: 2 }, station)`,
+/// `With<Relation<DockedAt>>` filters the entities having at least one edge
+#[derive(Debug, Clone, PartialEq)]
+pub struct Relation<R> {
+    edges: Vec<(Entity, R)>,
+}
+
+impl<R> Relation<R> {
+    pub fn get(&self, target: Entity) -> Option<&R> {
+        self.edges.iter()
+            .find(|(edge_target, _)| *edge_target == target)
+            .map(|(_, relation)| relation)
+    }
+
+    /// The data of an edge can be changed freely, its target cannot
+    pub fn get_mut(&mut self, target: Entity) -> Option<&mut R> {
+        self.edges.iter_mut()
+            .find(|(edge_target, _)| *edge_target == target)
+            .map(|(_, relation)| relation)
+    }
+
+    pub fn contains(&self, target: Entity) -> bool {
+        self.get(target).is_some()
+    }
+
+    /// Targets in insertion order
+    pub fn targets(&self) -> impl Iterator<Item = Entity> + '_ {
+        self.edges.iter().map(|(target, _)| *target)
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (Entity, &R)> {
+        self.edges.iter().map(|(target, relation)| (*target, relation))
+    }
+
+    pub fn len(&self) -> usize {
+        self.edges.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.edges.is_empty()
+    }
+}
+
+impl<R> MapEntities for Relation<R> {
+    fn map_entities(&mut self, entity_map: &EntityMap) {
+        for (target, _) in self.edges.iter_mut() {
+            *target = entity_map.map(*target);
+        }
+    }
+}
+
+/// Entities having a `Relation<R>` to the entity, the reverse index of `Relation<R>`
+pub struct RelationSources<R> {
+    sources: Vec<Entity>,
+    marker: PhantomData<fn() -> R>,
+}
+
+impl<R> RelationSources<R> {
+    pub fn iter(&self) -> impl Iterator<Item = Entity> + '_ {
+        self.sources.iter().copied()
+    }
+}
+
+impl<R> std::ops::Deref for RelationSources<R> {
+    type Target = [Entity];
+
+    fn deref(&self) -> &Self::Target {
+        &self.sources
+    }
+}
+
+impl<R> MapEntities for RelationSources<R> {
+    fn map_entities(&mut self, entity_map: &EntityMap) {
+        for source in self.sources.iter_mut() {
+            *source = entity_map.map(*source);
+        }
+    }
+}
+
+/// The edges of the source leave the `RelationSources` of their targets
+fn relation_removed<R: Component>(world: &mut World, source: Entity, _component_id: ComponentId) {
+    let targets: Vec<Entity> = world.get::<Relation<R>>(source).unwrap().targets().collect();
+    for target in targets {
+        let now_empty = match world.get_mut::<RelationSources<R>>(target) {
+            Some(sources) => {
+                sources.sources.retain(|entity| *entity != source);
+                sources.sources.is_empty()
+            },
+            None => false,
+        };
+        if now_empty {
+            world.entity_mut(target).remove::<RelationSources<R>>();
+        }
+    }
+}
+
+/// The edges to the target are removed, with their data
+fn relation_sources_removed<R: Component>(world: &mut World, target: Entity, _component_id: ComponentId) {
+    // taken first, so that `relation_removed` does not look them up
+    let sources = std::mem::take(&mut world.get_mut::<RelationSources<R>>(target).unwrap().sources);
+    for source in sources {
+        let now_empty = match world.get_mut::<Relation<R>>(source) {
+            Some(edges) => {
+                edges.edges.retain(|(edge_target, _)| *edge_target != target);
+                edges.is_empty()
+            },
+            None => false,
+        };
+        if now_empty {
+            world.entity_mut(source).remove::<Relation<R>>();
+        }
+    }
+}
+
+/// Registers the hooks cleaning the `R` edges of despawned entities, and their entity remapping
+pub fn register_relation<R: Component>(world: &mut World) {
+    world.register_map_entities::<Relation<R>>();
+    world.register_map_entities::<RelationSources<R>>();
+    world.register_component_hooks::<Relation<R>>()
+        .on_remove(relation_removed::<R>);
+    world.register_component_hooks::<RelationSources<R>>()
+        .on_remove(relation_sources_removed::<R>);
+}
+
+/// Adds the edge `source -R-> target`, replaces the data if the edge exists
+///
+/// # Panics
+/// if `source` or `target` does not exist
+pub fn add_relation<R: Component>(world: &mut World, source: Entity, relation: R, target: Entity) {
+    assert!(world.contains_entity(target), "Relation target {:?} does not exist", target);
+    register_relation::<R>(world);
+    match world.get_mut::<Relation<R>>(source) {
+        Some(edges) => match edges.get_mut(target) {
+            Some(data) => {
+                *data = relation;
+                return;
+            },
+            None => edges.edges.push((target, relation)),
+        },
+        None => {
+            world.entity_mut(source).insert(Relation { edges: vec![(target, relation)] });
+        },
+    }
+    match world.get_mut::<RelationSources<R>>(target) {
+        Some(sources) => sources.sources.push(source),
+        None => {
+            world.entity_mut(target).insert(RelationSources::<R> { sources: vec![source], marker: PhantomData });
+        },
+    }
+}
+
+/// Removes the edge `source -R-> target` and returns its data
+pub fn remove_relation<R: Component>(world: &mut World, source: Entity, target: Entity) -> Option<R> {
+    let edges = world.get_mut::<Relation<R>>(source)?;
+    let index = edges.edges.iter().position(|(edge_target, _)| *edge_target == target)?;
+    let (_, relation) = edges.edges.remove(index);
+    if edges.is_empty() {
+        world.entity_mut(source).remove::<Relation<R>>();
+    }
+
+    let now_empty = match world.get_mut::<RelationSources<R>>(target) {
+        Some(sources) => {
+            sources.sources.retain(|entity| *entity != source);
+            sources.sources.is_empty()
+        },
+        None => false,
+    };
+    if now_empty {
+        world.entity_mut(target).remove::<RelationSources<R>>();
+    }
+    Some(relation)
+}
+
+/// Removes every `R` edge of `source`
+pub fn clear_relations<R: Component>(world: &mut World, source: Entity) {
+    let targets: Vec<Entity> = match world.get::<Relation<R>>(source) {
+        Some(edges) => edges.targets().collect(),
+        None => return,
+    };
+    for target in targets {
+        remove_relation::<R>(world, source, target);
+    }
+}
+
+/// Entities having a `Relation<R>` to `target`
+pub fn sources<R: Component>(world: &World, target: Entity) -> impl Iterator<Item = Entity> + '_ {
+    world.get::<RelationSources<R>>(target)
+        .map(|sources| &sources.sources[..])
+        .unwrap_or_default()
+        .iter()
+        .copied()
+}
+
+/// Relation edits on a single entity, the entity is the source of the edges
+///
+/// `world.spawn().add_relation(Targets, enemy).add_relation(OwnedBy, player).id()`
+pub trait BuildRelations {
+    fn add_relation<R: Component>(&mut self, relation: R, target: Entity) -> &mut Self;
+    fn remove_relation<R: Component>(&mut self, target: Entity) -> &mut Self;
+    fn clear_relations<R: Component>(&mut self) -> &mut Self;
+}
+
+impl<'w> BuildRelations for EntityMut<'w> {
+    fn add_relation<R: Component>(&mut self, relation: R, target: Entity) -> &mut Self {
+        let source = self.id();
+        add_relation(self.world_mut(), source, relation, target);
+        self
+    }
+
+    fn remove_relation<R: Component>(&mut self, target: Entity) -> &mut Self {
+        let source = self.id();
+        remove_relation::<R>(self.world_mut(), source, target);
+        self
+    }
+
+    fn clear_relations<R: Component>(&mut self) -> &mut Self {
+        let source = self.id();
+        clear_relations::<R>(self.world_mut(), source);
+        self
+    }
+}
+
+impl<'w, 's, Fe: ReadOnlyFetchQuery, Fi: FilterQuery> Query<'w, 's, Fe, Fi> {
+    /// Items of the entities having a `Relation<R>` to `target`
+    ///
+    /// `for name in names.iter_related::<DockedAt>(station) { .. }`
+    pub fn iter_related<R: Component>(&self, target: Entity) -> impl Iterator<Item = <<Fe as FetchQuery>::Fetch as Fetch<'w, 's>>::Item> + '_ {
+        sources::<R>(self.world(), target).filter_map(move |source| self.get(source))
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use crate::ecs::World;
+    use crate::ecs::query::{state::{Query, QueryState}, filter::With};
+
+    use super::{BuildRelations, Relation, RelationSources, remove_relation, sources};
+
+    #[derive(Debug, PartialEq)]
+    struct DockedAt {
+        slot: u32,
+    }
+    struct Targets;
+    struct Name(&'static str);
+
+    #[test]
+    fn edges_and_reverse_lookup() {
+        let mut world = World::new();
+        let station = world.spawn().insert(Name("station")).id();
+        let ship = world.spawn().insert(Name("ship")).add_relation(DockedAt { slot: 1 }, station).id();
+        let shuttle = world.spawn().insert(Name("shuttle"))
+            .add_relation(DockedAt { slot: 2 }, station)
+            .add_relation(Targets, ship)
+            .id();
+
+        assert_eq!(vec![ship, shuttle], sources::<DockedAt>(&world, station).collect::<Vec<_>>());
+        assert_eq!(vec![shuttle], sources::<Targets>(&world, ship).collect::<Vec<_>>());
+        assert_eq!(Some(&DockedAt { slot: 2 }), world.get::<Relation<DockedAt>>(shuttle).unwrap().get(station));
+
+        // replacing the data does not duplicate the edge
+        world.entity_mut(ship).add_relation(DockedAt { slot: 3 }, station);
+        assert_eq!(2, sources::<DockedAt>(&world, station).count());
+
+        let names = QueryState::<&Name>::new(&mut world);
+        let query = Query::new(&world, &names);
+        let mut docked: Vec<_> = query.iter_related::<DockedAt>(station).map(|name| name.0).collect();
+        docked.sort_unstable();
+        assert_eq!(vec!["ship", "shuttle"], docked);
+        let targeting = QueryState::<&Name, With<Relation<Targets>>>::new(&mut world);
+        assert_eq!(vec!["shuttle"], Query::new(&world, &targeting).iter().map(|name| name.0).collect::<Vec<_>>());
+
+        assert_eq!(Some(DockedAt { slot: 3 }), remove_relation::<DockedAt>(&mut world, ship, station));
+        assert_eq!(None, world.get::<Relation<DockedAt>>(ship));
+        world.entity_mut(shuttle).clear_relations::<DockedAt>();
+        assert!(world.get::<RelationSources<DockedAt>>(station).is_none());
+    }
+
+    #[test]
+    fn despawn_removes_edges() {
+        let mut world = World::new();
+        let [a, b] = [(); 2].map(|_| world.spawn().id());
+        let source = world.spawn().add_relation(Targets, a).add_relation(Targets, b).id();
+        let other = world.spawn().add_relation(Targets, b).id();
+        let tick = world.increment_change_tick();
+
+        world.despawn(a);
+        world.despawn(other);
+        assert_eq!(vec![b], world.get::<Relation<Targets>>(source).unwrap().targets().collect::<Vec<_>>());
+        assert_eq!(&[source], &**world.get::<RelationSources<Targets>>(b).unwrap());
+        assert!(world.is_changed_since::<Relation<Targets>>(source, tick));
+
+        world.despawn(b);
+        assert!(world.get::<Relation<Targets>>(source).is_none());
+
+        // edges between entities left alone are not touched
+        let target = world.spawn().id();
+        world.entity_mut(source).add_relation(Targets, target);
+        let tick = world.increment_change_tick();
+        let unrelated = world.spawn().add_relation(Targets, source).id();
+        world.despawn(unrelated);
+        assert!(!world.is_changed_since::<Relation<Targets>>(source, tick));
+        assert!(!world.is_changed_since::<RelationSources<Targets>>(target, tick));
+    }
+}
@@ -0,0 +1,48 @@
+use std::{sync::{Arc, Mutex}, time::{Duration, Instant}};
+
+
+/// Source of the current instant for `Time`
+pub trait ClockSource: Send + Sync {
+    fn now(&self) -> Instant;
+}
+
+/// Wall clock, `Instant::now`
+#[derive(Debug, Clone, Copy, Default)]
+pub struct RealClock;
+
+impl ClockSource for RealClock {
+    fn now(&self) -> Instant {
+        Instant::now()
+    }
+}
+
+/// Clock that only moves when advanced
+/// Clones share the same instant, keep a clone to drive a `Time` resource from tests
+#[derive(Debug, Clone)]
+pub struct ManualClock {
+    now: Arc<Mutex<Instant>>,
+}
+
+impl Default for ManualClock {
+    fn default() -> Self {
+        ManualClock {
+            now: Arc::new(Mutex::new(Instant::now())),
+        }
+    }
+}
+
+impl ManualClock {
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    pub fn advance(&self, duration: Duration) {
+        *self.now.lock().unwrap() += duration;
+    }
+}
+
+impl ClockSource for ManualClock {
+    fn now(&self) -> Instant {
+        *self.now.lock().unwrap()
+    }
+}
@@ -0,0 +1,178 @@
+use std::time::{Duration, Instant};
+
+use crate::ecs::system::param::ResMut;
+
+pub use self::clock::{ClockSource, RealClock, ManualClock};
+pub use self::timer::{Timer, Stopwatch};
+
+pub mod clock;
+pub mod timer;
+
+
+/// Frame timing resource, updated once per frame by `time_system`
+///
+/// `delta` and `elapsed` are scaled by `time_scale` and stop while paused,
+/// `raw_delta` and `raw_elapsed` always follow the clock
+pub struct Time {
+    clock: Box<dyn ClockSource>,
+    delta: Duration,
+    raw_delta: Duration,
+    elapsed: Duration,
+    raw_elapsed: Duration,
+    frame_count: u64,
+    time_scale: f64,
+    paused: bool,
+    startup: Instant,
+    last_update: Option<Instant>,
+}
+
+impl Default for Time {
+    fn default() -> Self {
+        Time::with_clock(RealClock)
+    }
+}
+
+impl Time {
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    pub fn with_clock(clock: impl ClockSource + 'static) -> Self {
+        let startup = clock.now();
+        Time {
+            clock: Box::new(clock),
+            delta: Duration::ZERO,
+            raw_delta: Duration::ZERO,
+            elapsed: Duration::ZERO,
+            raw_elapsed: Duration::ZERO,
+            frame_count: 0,
+            time_scale: 1.0,
+            paused: false,
+            startup,
+            last_update: None,
+        }
+    }
+
+    /// Reads the clock source and advances the time
+    pub fn update(&mut self) {
+        let now = self.clock.now();
+        self.update_with_instant(now);
+    }
+
+    /// First update only sets the reference instant, delta stays zero
+    pub fn update_with_instant(&mut self, instant: Instant) {
+        if let Some(last_update) = self.last_update {
+            self.raw_delta = instant.saturating_duration_since(last_update);
+            self.raw_elapsed += self.raw_delta;
+
+            self.delta = if self.paused {
+                Duration::ZERO
+            }
+            else {
+                self.raw_delta.mul_f64(self.time_scale)
+            };
+            self.elapsed += self.delta;
+            self.frame_count += 1;
+        }
+        self.last_update = Some(instant);
+    }
+
+    pub fn delta(&self) -> Duration {
+        self.delta
+    }
+
+    pub fn delta_seconds(&self) -> f32 {
+        self.delta.as_secs_f32()
+    }
+
+    pub fn raw_delta(&self) -> Duration {
+        self.raw_delta
+    }
+
+    pub fn elapsed(&self) -> Duration {
+        self.elapsed
+    }
+
+    pub fn elapsed_seconds(&self) -> f64 {
+        self.elapsed.as_secs_f64()
+    }
+
+    pub fn raw_elapsed(&self) -> Duration {
+        self.raw_elapsed
+    }
+
+    /// Number of updates with a non-zero reference, the first update is not counted
+    pub fn frame_count(&self) -> u64 {
+        self.frame_count
+    }
+
+    pub fn time_scale(&self) -> f64 {
+        self.time_scale
+    }
+
+    pub fn set_time_scale(&mut self, time_scale: f64) {
+        assert!(time_scale >= 0.0, "Time scale cannot be negative");
+        self.time_scale = time_scale;
+    }
+
+    pub fn is_paused(&self) -> bool {
+        self.paused
+    }
+
+    pub fn pause(&mut self) {
+        self.paused = true;
+    }
+
+    pub fn unpause(&mut self) {
+        self.paused = false;
+    }
+
+    pub fn startup(&self) -> Instant {
+        self.startup
+    }
+
+    pub fn last_update(&self) -> Option<Instant> {
+        self.last_update
+    }
+}
+
+pub fn time_system(mut time: ResMut<Time>) {
+    time.update();
+}
+
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::{Time, ManualClock};
+
+    #[test]
+    fn delta_elapsed_scale_and_pause() {
+        let clock = ManualClock::new();
+        let mut time = Time::with_clock(clock.clone());
+
+        time.update();
+        assert_eq!(Duration::ZERO, time.delta());
+        assert_eq!(0, time.frame_count());
+
+        clock.advance(Duration::from_millis(100));
+        time.update();
+        assert_eq!(Duration::from_millis(100), time.delta());
+        assert_eq!(1, time.frame_count());
+
+        time.set_time_scale(0.5);
+        clock.advance(Duration::from_millis(100));
+        time.update();
+        assert_eq!(Duration::from_millis(50), time.delta());
+        assert_eq!(Duration::from_millis(150), time.elapsed());
+
+        time.pause();
+        clock.advance(Duration::from_millis(100));
+        time.update();
+        assert_eq!(Duration::ZERO, time.delta());
+        assert_eq!(Duration::from_millis(150), time.elapsed());
+        assert_eq!(Duration::from_millis(300), time.raw_elapsed());
+        assert_eq!(3, time.frame_count());
+    }
+}
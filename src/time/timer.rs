@@ -0,0 +1,221 @@
+use std::time::Duration;
+
+
+/// Counts time up from zero while not paused
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Stopwatch {
+    elapsed: Duration,
+    paused: bool,
+}
+
+impl Stopwatch {
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    pub fn tick(&mut self, delta: Duration) -> &Self {
+        if !self.paused {
+            self.elapsed += delta;
+        }
+        self
+    }
+
+    pub fn elapsed(&self) -> Duration {
+        self.elapsed
+    }
+
+    pub fn elapsed_secs(&self) -> f32 {
+        self.elapsed.as_secs_f32()
+    }
+
+    pub fn set_elapsed(&mut self, elapsed: Duration) {
+        self.elapsed = elapsed;
+    }
+
+    pub fn pause(&mut self) {
+        self.paused = true;
+    }
+
+    pub fn unpause(&mut self) {
+        self.paused = false;
+    }
+
+    pub fn is_paused(&self) -> bool {
+        self.paused
+    }
+
+    pub fn reset(&mut self) {
+        self.elapsed = Duration::ZERO;
+    }
+}
+
+/// Counts down `duration`, optionally repeating
+///
+/// Tick it with `Time::delta` every frame and check `just_finished`
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Timer {
+    stopwatch: Stopwatch,
+    duration: Duration,
+    repeating: bool,
+    finished: bool,
+    times_finished: u32,
+}
+
+impl Timer {
+    pub fn new(duration: Duration, repeating: bool) -> Self {
+        Timer {
+            duration,
+            repeating,
+            ..Default::default()
+        }
+    }
+
+    pub fn from_seconds(seconds: f32, repeating: bool) -> Self {
+        Self::new(Duration::from_secs_f32(seconds), repeating)
+    }
+
+    pub fn tick(&mut self, delta: Duration) -> &Self {
+        if self.stopwatch.is_paused() {
+            self.times_finished = 0;
+            return self;
+        }
+        if !self.repeating && self.finished {
+            self.times_finished = 0;
+            return self;
+        }
+
+        self.stopwatch.tick(delta);
+        self.finished = self.stopwatch.elapsed() >= self.duration;
+
+        if self.finished {
+            if self.repeating {
+                self.times_finished = if self.duration.is_zero() {
+                    1
+                }
+                else {
+                    (self.stopwatch.elapsed().as_nanos() / self.duration.as_nanos()) as u32
+                };
+                let left = if self.duration.is_zero() {
+                    Duration::ZERO
+                }
+                else {
+                    Duration::from_nanos((self.stopwatch.elapsed().as_nanos() % self.duration.as_nanos()) as u64)
+                };
+                self.stopwatch.set_elapsed(left);
+            }
+            else {
+                self.times_finished = 1;
+                self.stopwatch.set_elapsed(self.duration);
+            }
+        }
+        else {
+            self.times_finished = 0;
+        }
+        self
+    }
+
+    /// Finished at any point, stays true for non-repeating timers
+    pub fn finished(&self) -> bool {
+        self.finished
+    }
+
+    /// Finished during the last tick
+    pub fn just_finished(&self) -> bool {
+        self.times_finished > 0
+    }
+
+    /// Number of times finished during the last tick, can exceed one for repeating timers
+    pub fn times_finished(&self) -> u32 {
+        self.times_finished
+    }
+
+    pub fn elapsed(&self) -> Duration {
+        self.stopwatch.elapsed()
+    }
+
+    pub fn duration(&self) -> Duration {
+        self.duration
+    }
+
+    pub fn set_duration(&mut self, duration: Duration) {
+        self.duration = duration;
+    }
+
+    pub fn repeating(&self) -> bool {
+        self.repeating
+    }
+
+    pub fn set_repeating(&mut self, repeating: bool) {
+        self.repeating = repeating;
+    }
+
+    pub fn percent(&self) -> f32 {
+        if self.duration.is_zero() {
+            1.0
+        }
+        else {
+            self.elapsed().as_secs_f32() / self.duration.as_secs_f32()
+        }
+    }
+
+    pub fn percent_left(&self) -> f32 {
+        1.0 - self.percent()
+    }
+
+    pub fn pause(&mut self) {
+        self.stopwatch.pause();
+    }
+
+    pub fn unpause(&mut self) {
+        self.stopwatch.unpause();
+    }
+
+    pub fn is_paused(&self) -> bool {
+        self.stopwatch.is_paused()
+    }
+
+    pub fn reset(&mut self) {
+        self.stopwatch.reset();
+        self.finished = false;
+        self.times_finished = 0;
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::{Timer, Stopwatch};
+
+    #[test]
+    fn once_timer() {
+        let mut timer = Timer::new(Duration::from_millis(100), false);
+        assert!(!timer.tick(Duration::from_millis(60)).just_finished());
+        assert!(timer.tick(Duration::from_millis(60)).just_finished());
+        assert_eq!(Duration::from_millis(100), timer.elapsed());
+        assert!(!timer.tick(Duration::from_millis(60)).just_finished());
+        assert!(timer.finished());
+
+        timer.reset();
+        assert!(!timer.finished());
+    }
+
+    #[test]
+    fn repeating_timer() {
+        let mut timer = Timer::new(Duration::from_millis(100), true);
+        timer.tick(Duration::from_millis(250));
+        assert_eq!(2, timer.times_finished());
+        assert_eq!(Duration::from_millis(50), timer.elapsed());
+        assert!(!timer.tick(Duration::from_millis(10)).just_finished());
+    }
+
+    #[test]
+    fn paused_stopwatch() {
+        let mut stopwatch = Stopwatch::new();
+        stopwatch.tick(Duration::from_millis(10));
+        stopwatch.pause();
+        stopwatch.tick(Duration::from_millis(10));
+        assert_eq!(Duration::from_millis(10), stopwatch.elapsed());
+    }
+}
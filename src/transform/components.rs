@@ -0,0 +1,165 @@
+use glam::{Mat4, Quat, Vec3};
+use serde::{Serialize, Deserialize};
+
+use crate::ecs::{reflect::Reflect, stable_hash::StableHash};
+
+
+/// Position, rotation and scale of an entity relative to its `Parent`, or to the world for roots
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize, Reflect, StableHash)]
+#[serde(default)]
+pub struct Transform {
+    pub translation: Vec3,
+    pub rotation: Quat,
+    pub scale: Vec3,
+}
+
+impl Default for Transform {
+    fn default() -> Self {
+        Self::identity()
+    }
+}
+
+impl Transform {
+    #[inline]
+    pub const fn identity() -> Self {
+        Transform {
+            translation: Vec3::ZERO,
+            rotation: Quat::IDENTITY,
+            scale: Vec3::ONE,
+        }
+    }
+
+    #[inline]
+    pub fn from_xyz(x: f32, y: f32, z: f32) -> Self {
+        Self::from_translation(Vec3::new(x, y, z))
+    }
+
+    #[inline]
+    pub fn from_translation(translation: Vec3) -> Self {
+        Transform {
+            translation,
+            ..Self::identity()
+        }
+    }
+
+    #[inline]
+    pub fn from_rotation(rotation: Quat) -> Self {
+        Transform {
+            rotation,
+            ..Self::identity()
+        }
+    }
+
+    #[inline]
+    pub fn from_scale(scale: Vec3) -> Self {
+        Transform {
+            scale,
+            ..Self::identity()
+        }
+    }
+
+    #[inline]
+    pub fn from_matrix(matrix: Mat4) -> Self {
+        let (scale, rotation, translation) = matrix.to_scale_rotation_translation();
+        Transform {
+            translation,
+            rotation,
+            scale,
+        }
+    }
+
+    #[inline]
+    pub fn with_translation(mut self, translation: Vec3) -> Self {
+        self.translation = translation;
+        self
+    }
+
+    #[inline]
+    pub fn with_rotation(mut self, rotation: Quat) -> Self {
+        self.rotation = rotation;
+        self
+    }
+
+    #[inline]
+    pub fn with_scale(mut self, scale: Vec3) -> Self {
+        self.scale = scale;
+        self
+    }
+
+    /// Scale, then rotation, then translation
+    #[inline]
+    pub fn compute_matrix(&self) -> Mat4 {
+        Mat4::from_scale_rotation_translation(self.scale, self.rotation, self.translation)
+    }
+
+    #[inline]
+    pub fn translate(&mut self, offset: Vec3) {
+        self.translation += offset;
+    }
+
+    #[inline]
+    pub fn rotate(&mut self, rotation: Quat) {
+        self.rotation = rotation * self.rotation;
+    }
+
+    /// Applies `self` on top of `child`: `self * child`
+    #[inline]
+    pub fn mul_transform(&self, child: &Transform) -> Transform {
+        Transform {
+            translation: self.mul_vec3(child.translation),
+            rotation: self.rotation * child.rotation,
+            scale: self.scale * child.scale,
+        }
+    }
+
+    #[inline]
+    pub fn mul_vec3(&self, point: Vec3) -> Vec3 {
+        self.translation + self.rotation * (self.scale * point)
+    }
+}
+
+/// World space transform, written by `transform_propagate_system`
+/// Non-uniform scale under a rotated parent is approximated like `Transform::mul_transform`
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct GlobalTransform(pub Transform);
+
+impl GlobalTransform {
+    #[inline]
+    pub fn translation(&self) -> Vec3 {
+        self.0.translation
+    }
+
+    #[inline]
+    pub fn rotation(&self) -> Quat {
+        self.0.rotation
+    }
+
+    #[inline]
+    pub fn scale(&self) -> Vec3 {
+        self.0.scale
+    }
+
+    #[inline]
+    pub fn compute_matrix(&self) -> Mat4 {
+        self.0.compute_matrix()
+    }
+
+    /// Column major `mat4 model` uniform of `res/basic.shader`
+    ///
+    /// `uniform! { model: global_transform.model_matrix() }`
+    #[inline]
+    pub fn model_matrix(&self) -> [[f32; 4]; 4] {
+        self.compute_matrix().to_cols_array_2d()
+    }
+
+    #[inline]
+    pub fn mul_transform(&self, child: &Transform) -> GlobalTransform {
+        GlobalTransform(self.0.mul_transform(child))
+    }
+}
+
+impl From<Transform> for GlobalTransform {
+    fn from(transform: Transform) -> Self {
+        GlobalTransform(transform)
+    }
+}
@@ -0,0 +1,154 @@
+use std::collections::BTreeSet;
+
+use crate::ecs::{World, entity::Entity};
+use crate::hierarchy::{Parent, Children, ancestors};
+
+pub use self::components::{Transform, GlobalTransform};
+
+pub mod components;
+
+
+/// Change tick and `Parent` removal log position of the last propagation
+#[derive(Default)]
+struct PropagationState {
+    last_run: u32,
+    removed_parents: usize,
+}
+
+/// Writes the `GlobalTransform` of every entity with a `Transform`,
+/// inserting it where it is missing
+///
+/// Only the subtrees of entities whose `Transform` or `Parent` changed since the last run,
+/// or that lost their `Parent`, are walked. Entities without `Transform` cut the hierarchy.
+pub fn transform_propagate_system(world: &mut World) {
+    let mut state = world.remove_resource::<PropagationState>().unwrap_or_default();
+    let this_run = world.increment_change_tick();
+
+    let mut changed: BTreeSet<Entity> = world.changed_since::<Transform>(state.last_run).into_iter().collect();
+    changed.extend(world.changed_since::<Parent>(state.last_run));
+    if let Some(parent_id) = world.get_components().get_component_id::<Parent>() {
+        let (removed, end) = world.removed_components().since(&parent_id, state.removed_parents);
+        changed.extend(removed.iter().copied().filter(|entity| world.contains_entity(*entity)));
+        state.removed_parents = end;
+    }
+
+    // subtrees of changed entities below another changed entity are walked from the upper one
+    let mut stack: Vec<(Entity, Option<GlobalTransform>)> = Vec::new();
+    for entity in changed.iter().copied() {
+        if ancestors(world, entity).any(|ancestor| changed.contains(&ancestor)) {
+            continue;
+        }
+        match world.get::<Parent>(entity) {
+            Some(Parent(parent)) => {
+                let parent_global = world.get::<Transform>(*parent)
+                    .and_then(|_| world.get::<GlobalTransform>(*parent));
+                if let Some(parent_global) = parent_global {
+                    stack.push((entity, Some(*parent_global)));
+                }
+            },
+            None => stack.push((entity, None)),
+        }
+    }
+
+    while let Some((entity, parent_global)) = stack.pop() {
+        let local = match world.get::<Transform>(entity) {
+            Some(local) => *local,
+            None => continue,
+        };
+        let global = match parent_global {
+            Some(parent_global) => parent_global.mul_transform(&local),
+            None => GlobalTransform(local),
+        };
+        match world.get_mut::<GlobalTransform>(entity) {
+            Some(current) => *current = global,
+            None => {
+                world.entity_mut(entity).insert(global);
+            },
+        }
+        if let Some(children) = world.get::<Children>(entity) {
+            stack.extend(children.iter().map(|child| (child, Some(global))));
+        }
+    }
+
+    state.last_run = this_run;
+    world.insert_resource(state);
+}
+
+
+#[cfg(test)]
+mod tests {
+    use glam::{Quat, Vec3};
+
+    use crate::ecs::World;
+    use crate::ecs::query::filter::Without;
+    use crate::hierarchy::{BuildWorldChildren, Parent};
+
+    use super::{GlobalTransform, Transform, transform_propagate_system};
+
+    fn assert_near(expected: Vec3, actual: Vec3) {
+        assert!(expected.abs_diff_eq(actual, 1e-5), "{:?} != {:?}", expected, actual);
+    }
+
+    #[test]
+    fn propagates_through_hierarchy() {
+        let mut world = World::new();
+        let grandchild = world.spawn().insert(Transform::from_xyz(0.0, 1.0, 0.0)).id();
+        let child = world.spawn()
+            .insert(Transform::from_xyz(1.0, 0.0, 0.0).with_scale(Vec3::splat(2.0)))
+            .push_children(&[grandchild])
+            .id();
+        let root = world.spawn()
+            .insert(Transform::from_xyz(10.0, 0.0, 0.0).with_rotation(Quat::from_rotation_z(std::f32::consts::FRAC_PI_2)))
+            .push_children(&[child])
+            .id();
+
+        transform_propagate_system(&mut world);
+        assert_near(Vec3::new(10.0, 1.0, 0.0), world.get::<GlobalTransform>(child).unwrap().translation());
+        // (0, 1) scaled by 2 then rotated by 90 degrees
+        assert_near(Vec3::new(8.0, 1.0, 0.0), world.get::<GlobalTransform>(grandchild).unwrap().translation());
+
+        world.get_mut::<Transform>(root).unwrap().translation = Vec3::ZERO;
+        transform_propagate_system(&mut world);
+        assert_near(Vec3::new(-2.0, 1.0, 0.0), world.get::<GlobalTransform>(grandchild).unwrap().translation());
+
+        // reparenting recomputes even without a Transform change
+        world.entity_mut(grandchild).remove_parent();
+        transform_propagate_system(&mut world);
+        assert_near(Vec3::new(0.0, 1.0, 0.0), world.get::<GlobalTransform>(grandchild).unwrap().translation());
+    }
+
+    #[test]
+    fn only_changed_subtrees_are_walked() {
+        let mut world = World::new();
+        let child = world.spawn().insert(Transform::from_xyz(0.0, 1.0, 0.0)).id();
+        world.spawn()
+            .insert(Transform::from_xyz(1.0, 0.0, 0.0))
+            .push_children(&[child]);
+        let other = world.spawn().insert(Transform::from_xyz(5.0, 0.0, 0.0)).id();
+        transform_propagate_system(&mut world);
+
+        // values written behind the system's back stay until their subtree changes
+        for entity in [child, other] {
+            *world.get_mut::<GlobalTransform>(entity).unwrap() = GlobalTransform::default();
+        }
+        transform_propagate_system(&mut world);
+        assert_eq!(Vec3::ZERO, world.get::<GlobalTransform>(child).unwrap().translation());
+
+        let transforms = world.query_filtered::<&mut Transform, Without<Parent>>();
+        for transform in transforms.query_mut(&mut world).iter() {
+            if transform.translation.x < 2.0 {
+                transform.translation.x = 2.0;
+            }
+        }
+        transform_propagate_system(&mut world);
+        assert_near(Vec3::new(2.0, 1.0, 0.0), world.get::<GlobalTransform>(child).unwrap().translation());
+        // iterating a mutable query marks every visited value
+        assert_near(Vec3::new(5.0, 0.0, 0.0), world.get::<GlobalTransform>(other).unwrap().translation());
+    }
+
+    #[test]
+    fn model_matrix_is_column_major() {
+        let global = GlobalTransform(Transform::from_xyz(1.0, 2.0, 3.0));
+        assert_eq!([1.0, 2.0, 3.0, 1.0], global.model_matrix()[3]);
+    }
+}